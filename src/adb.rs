@@ -0,0 +1,85 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::error::{Error, Result};
+
+/// The game qpm deploys to
+pub const GAME_PACKAGE: &str = "com.beatgames.beatsaber";
+/// Where the mod loader looks for mod binaries
+pub const MODS_DIR: &str = "/sdcard/ModData/com.beatgames.beatsaber/Modloader/mods";
+/// Where the mod loader looks for the libraries mods link against
+pub const LIBS_DIR: &str = "/sdcard/ModData/com.beatgames.beatsaber/Modloader/libs";
+
+fn run(args: &[&str]) -> Result<String> {
+    let output = Command::new("adb")
+        .args(args)
+        .output()
+        .map_err(|e| Error::Adb(format!("failed to run adb: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::Adb(format!(
+            "adb {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Fails unless exactly one device is connected and authorized
+pub fn ensure_device() -> Result<()> {
+    let devices = run(&["devices"])?;
+    let connected: Vec<&str> = devices
+        .lines()
+        .skip(1)
+        .filter(|line| line.ends_with("\tdevice"))
+        .collect();
+    match connected.len() {
+        1 => Ok(()),
+        0 => Err(Error::Adb("no device connected over adb".into())),
+        n => Err(Error::Adb(format!(
+            "{} devices are connected, leave only the Quest to deploy to",
+            n
+        ))),
+    }
+}
+
+pub fn push(local: &Path, remote: &str) -> Result<()> {
+    let local = local.to_string_lossy();
+    run(&["push", &local, remote]).map(|_| ())
+}
+
+pub fn shell(command: &str) -> Result<String> {
+    run(&["shell", command])
+}
+
+/// Force stops the game and launches it again
+pub fn restart_game() -> Result<()> {
+    shell(&format!("am force-stop {}", GAME_PACKAGE))?;
+    shell(&format!(
+        "monkey -p {} -c android.intent.category.LAUNCHER 1",
+        GAME_PACKAGE
+    ))
+    .map(|_| ())
+}
+
+/// Streams `adb logcat` line by line into `handle` until adb exits or `handle` returns
+/// false
+pub fn logcat(mut handle: impl FnMut(&str) -> bool) -> Result<()> {
+    let mut child = Command::new("adb")
+        .arg("logcat")
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Adb(format!("failed to run adb: {}", e)))?;
+    let stdout = child.stdout.take().unwrap();
+    for line in BufReader::new(stdout).lines() {
+        if !handle(&line?) {
+            break;
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    Ok(())
+}
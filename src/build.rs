@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    cache, cancel,
+    data::{
+        config::Config,
+        package::{PackageConfig, DEFAULT_ABI},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    ndk,
+    repository::Repository,
+    scaffold::BuildSystem,
+    utils::{
+        copy::{copy_tree, CopyOptions},
+        fs::{encode_path_component, remove_dir_if_exists},
+    },
+};
+
+pub const BUILD_TYPES: &[&str] = &["Debug", "Release", "RelWithDebInfo", "MinSizeRel"];
+
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+    pub abi: String,
+    /// One of [`BUILD_TYPES`]
+    pub build_type: String,
+}
+
+impl BuildOptions {
+    /// A RelWithDebInfo build for the first ABI the project targets
+    pub fn for_package(package: &PackageConfig) -> BuildOptions {
+        BuildOptions {
+            abi: package.abis().remove(0),
+            build_type: "RelWithDebInfo".to_string(),
+        }
+    }
+}
+
+/// Where a build of `options` puts its binary, `build/<abi>/<build type>`
+pub fn output_dir(dir: &Path, options: &BuildOptions) -> PathBuf {
+    dir.join("build")
+        .join(&options.abi)
+        .join(options.build_type.to_lowercase())
+}
+
+/// CMake when the project has a CMakeLists.txt, ndk-build when it has an Android.mk
+pub fn detect(dir: &Path) -> Option<BuildSystem> {
+    if dir.join("CMakeLists.txt").exists() {
+        Some(BuildSystem::CMake)
+    } else if dir.join("Android.mk").exists() {
+        Some(BuildSystem::NdkBuild)
+    } else {
+        None
+    }
+}
+
+fn run(mut cmd: Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = cmd
+        .status()
+        .map_err(|e| Error::Build(format!("failed to run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(Error::Build(format!("{} exited with {}", program, status)));
+    }
+    Ok(())
+}
+
+fn cmake(dir: &Path, ndk: &Path, out: &Path, options: &BuildOptions) -> Result<()> {
+    let build_dir = out.join("cmake");
+    let mut configure = Command::new("cmake");
+    configure
+        .current_dir(dir)
+        .arg("-S")
+        .arg(".")
+        .arg("-B")
+        .arg(&build_dir)
+        .arg(format!(
+            "-DCMAKE_TOOLCHAIN_FILE={}",
+            ndk.join("build/cmake/android.toolchain.cmake").display()
+        ))
+        .arg(format!("-DANDROID_ABI={}", options.abi))
+        .arg("-DANDROID_PLATFORM=android-24")
+        .arg(format!("-DCMAKE_BUILD_TYPE={}", options.build_type))
+        .arg(format!(
+            "-DCMAKE_LIBRARY_OUTPUT_DIRECTORY={}",
+            out.display()
+        ))
+        .arg(format!(
+            "-DCMAKE_ARCHIVE_OUTPUT_DIRECTORY={}",
+            out.display()
+        ));
+    run(configure)?;
+
+    let mut build = Command::new("cmake");
+    build.current_dir(dir).arg("--build").arg(&build_dir);
+    run(build)
+}
+
+fn ndk_build(dir: &Path, ndk: &Path, out: &Path, options: &BuildOptions) -> Result<()> {
+    let script = if cfg!(windows) {
+        "ndk-build.cmd"
+    } else {
+        "ndk-build"
+    };
+    let mut cmd = Command::new(ndk.join(script));
+    cmd.current_dir(dir)
+        .arg("NDK_PROJECT_PATH=.")
+        .arg("APP_BUILD_SCRIPT=./Android.mk")
+        .arg(format!("APP_ABI={}", options.abi))
+        .arg(format!("NDK_OUT={}", out.join("obj").display()))
+        .arg(format!("NDK_LIBS_OUT={}", out.join("libs").display()));
+    if dir.join("Application.mk").exists() {
+        cmd.arg("NDK_APPLICATION_MK=./Application.mk");
+    }
+    if options.build_type == "Debug" {
+        cmd.arg("NDK_DEBUG=1");
+    }
+    run(cmd)
+}
+
+/// The binary a build produced: one already named like the package's so, else the
+/// `lib<id>.so` or `lib<id>.a` build systems name it by default
+fn find_binary(dirs: &[PathBuf], package: &PackageConfig, so_name: &str) -> Option<PathBuf> {
+    let extension = if cache::is_static(package) { "a" } else { "so" };
+    let default_name = format!("lib{}.{}", package.info.id, extension);
+    [so_name, default_name.as_str()].iter().find_map(|name| {
+        dirs.iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.exists())
+    })
+}
+
+/// Builds the project in `dir` with its NDK and returns the binary. An arm64-v8a binary
+/// is also copied to `build/<so name>`, where older tools look for it.
+pub fn build(
+    dir: &Path,
+    package: &PackageConfig,
+    options: &BuildOptions,
+    config: &Config,
+) -> Result<PathBuf> {
+    let system = detect(dir).ok_or_else(|| {
+        Error::Build("found neither CMakeLists.txt nor Android.mk to build".into())
+    })?;
+    let ndk = ndk::resolve(package, config)?.ok_or_else(|| {
+        Error::Build("no NDK found, set ndkPath or install one with qpm ndk download".into())
+    })?;
+    let out = output_dir(dir, options);
+    fs::create_dir_all(&out)?;
+
+    match system {
+        BuildSystem::CMake => cmake(dir, &ndk.path, &out, options)?,
+        BuildSystem::NdkBuild => ndk_build(dir, &ndk.path, &out, options)?,
+    }
+
+    let so_name = cache::so_name(package);
+    // ndk-build leaves static libraries among its intermediates
+    let searched = [
+        out.clone(),
+        out.join("libs").join(&options.abi),
+        out.join("obj").join("local").join(&options.abi),
+    ];
+    let built = find_binary(&searched, package, &so_name)
+        .ok_or_else(|| Error::Build(format!("the build did not produce {}", so_name)))?;
+    let binary = out.join(&so_name);
+    if built != binary {
+        fs::copy(&built, &binary)?;
+    }
+    if options.abi == DEFAULT_ABI {
+        fs::copy(&binary, dir.join("build").join(&so_name))?;
+    }
+    Ok(binary)
+}
+
+/// Builds the cached sources of a dependency for `abi` and caches the binary like a
+/// downloaded one, for packages that publish none. The sources are built in a copy with
+/// their own dependencies restored, so the cache is left as it was.
+pub fn build_from_source(
+    shared: &SharedPackageConfig,
+    abi: &str,
+    repository: &dyn Repository,
+    config: &Config,
+) -> Result<PathBuf> {
+    let info = &shared.config.info;
+    let src = cache::src_dir(config, &info.id, &info.version);
+    let dir = config.tmp_dir().join(format!(
+        "build-{}-{}",
+        encode_path_component(&info.id),
+        encode_path_component(&info.version)
+    ));
+    outln!(
+        "Building {} {} for {} from source",
+        info.id,
+        info.version,
+        abi
+    );
+    remove_dir_if_exists(&dir)?;
+    let _cleanup = cancel::remove_on_cancel(&dir);
+    let built = copy_tree(&src, &dir, &CopyOptions::new())
+        .and_then(|_| shared.write(&dir))
+        .and_then(|_| shared.restore(&dir, repository, config))
+        .and_then(|_| {
+            let options = BuildOptions {
+                abi: abi.to_string(),
+                build_type: "RelWithDebInfo".to_string(),
+            };
+            build(&dir, &shared.config, &options, config)
+        })
+        .and_then(|binary| {
+            let lib = cache::abi_lib_dir(config, &info.id, &info.version, abi);
+            fs::create_dir_all(&lib)?;
+            let cached = lib.join(cache::so_name(&shared.config));
+            fs::copy(binary, &cached)?;
+            Ok(cached)
+        });
+    remove_dir_if_exists(&dir)?;
+    built
+}
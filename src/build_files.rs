@@ -0,0 +1,417 @@
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache,
+    data::{
+        package::{IncludeLayout, PackageConfig},
+        shared_package::SharedPackageConfig,
+    },
+    error::Result,
+    network::git,
+};
+
+pub const EXTERN_CMAKE_FILE_NAME: &str = "extern.cmake";
+/// The ndk-build counterpart of extern.cmake, for Android.mk to `-include`
+pub const EXTERN_MK_FILE_NAME: &str = "extern.mk";
+/// Holds the path of the project's NDK for build scripts that don't read extern.cmake
+pub const NDK_PATH_FILE_NAME: &str = "ndkpath.txt";
+/// Header describing what a mod was built from, generated into the includes folder
+pub const BUILD_INFO_FILE_NAME: &str = "qpm_build_info.hpp";
+
+/// Generates `extern.cmake`, which exposes the restored dependencies to CMake through
+/// `QPM_INCLUDE_DIRS` (one folder per dependency with the namespaced include layout) and
+/// `QPM_LINK_LIBRARIES` (binaries picked by `ANDROID_ABI`, extra binaries as
+/// `qpm::<package>::<binary>`, INTERFACE targets carrying the includes for headers only
+/// packages), and the NDK through `QPM_NDK_PATH`
+pub fn write_extern_cmake(
+    dir: &Path,
+    package: &PackageConfig,
+    restored: &[SharedPackageConfig],
+    ndk: Option<&Path>,
+) -> Result<()> {
+    let extern_dir = dir.join(&package.dependencies_dir);
+    let mut out = String::new();
+    writeln!(out, "# generated by qpm restore, do not edit").unwrap();
+    writeln!(
+        out,
+        "set(QPM_EXTERN_DIR ${{CMAKE_CURRENT_LIST_DIR}}/{})",
+        package.dependencies_dir
+    )
+    .unwrap();
+    let layout = &package.extern_layout;
+    writeln!(
+        out,
+        "set(QPM_INCLUDE_DIRS ${{QPM_EXTERN_DIR}}/{})",
+        layout.includes_dir
+    )
+    .unwrap();
+    if package.include_layout == IncludeLayout::Namespaced {
+        for shared in restored {
+            writeln!(
+                out,
+                "list(APPEND QPM_INCLUDE_DIRS ${{QPM_EXTERN_DIR}}/{})",
+                layout.include_dir(
+                    package.include_layout,
+                    package.dependency_name(&shared.config.info.id)
+                )
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "set(QPM_LINK_LIBRARIES)").unwrap();
+    if let Some(ndk) = ndk {
+        writeln!(out, "set(QPM_NDK_PATH {})", cmake_path(ndk)).unwrap();
+    }
+
+    let options = merge_compile_options(package, restored);
+    let system_includes: Vec<_> = options
+        .system_includes
+        .iter()
+        .map(|include| format!("${{QPM_EXTERN_DIR}}/{}", include))
+        .collect();
+    writeln!(
+        out,
+        "set(QPM_SYSTEM_INCLUDE_DIRS {})",
+        system_includes.join(" ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "set(QPM_COMPILE_OPTIONS {})",
+        options.cmake_flags().join(" ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "set(QPM_COMPILE_DEFINITIONS {})",
+        options.defines.join(" ")
+    )
+    .unwrap();
+    // directory wide, so targets added after the include get them without any edits
+    if !system_includes.is_empty() {
+        writeln!(
+            out,
+            "include_directories(SYSTEM ${{QPM_SYSTEM_INCLUDE_DIRS}})"
+        )
+        .unwrap();
+    }
+    if !options.cmake_flags().is_empty() {
+        writeln!(out, "add_compile_options(${{QPM_COMPILE_OPTIONS}})").unwrap();
+    }
+    if !options.defines.is_empty() {
+        writeln!(out, "add_compile_definitions(${{QPM_COMPILE_DEFINITIONS}})").unwrap();
+    }
+    if let Some(standard) = options.cpp_standard {
+        writeln!(out, "set(QPM_CXX_STANDARD {})", standard).unwrap();
+        writeln!(
+            out,
+            "if(NOT CMAKE_CXX_STANDARD OR CMAKE_CXX_STANDARD LESS QPM_CXX_STANDARD)"
+        )
+        .unwrap();
+        writeln!(out, "    set(CMAKE_CXX_STANDARD ${{QPM_CXX_STANDARD}})").unwrap();
+        writeln!(out, "endif()").unwrap();
+    }
+
+    for shared in restored {
+        // targets and folders are named after the dependency's alias when it has one
+        let name = package.dependency_name(&shared.config.info.id);
+        if cache::is_headers_only(&shared.config) {
+            let includes = layout.include_dir(package.include_layout, name);
+            writeln!(out).unwrap();
+            writeln!(out, "add_library(qpm::{} INTERFACE IMPORTED)", name).unwrap();
+            writeln!(
+                out,
+                "set_target_properties(qpm::{} PROPERTIES INTERFACE_INCLUDE_DIRECTORIES ${{QPM_EXTERN_DIR}}/{})",
+                name, includes
+            )
+            .unwrap();
+            writeln!(out, "list(APPEND QPM_LINK_LIBRARIES qpm::{})", name).unwrap();
+            continue;
+        }
+        // only binaries restored for at least one ABI get a target
+        let restored_any = |file: &str| {
+            package.abis().iter().any(|abi| {
+                extern_dir
+                    .join(layout.binary_dir(name, abi))
+                    .join(file)
+                    .exists()
+            })
+        };
+        let so_name = cache::so_name(&shared.config);
+        if restored_any(&so_name) {
+            let location = format!("{}/{}", layout.binary_dir(name, "${ANDROID_ABI}"), so_name);
+            imported_library(
+                &mut out,
+                name,
+                cache::is_static(&shared.config),
+                &location,
+                &[],
+            );
+        }
+        // extra binaries are targets of their own, `qpm::<package>::<binary>`
+        for binary in cache::extra_binaries(&shared.config) {
+            if !binary.is_linked() || !restored_any(&binary.name) {
+                continue;
+            }
+            let location = format!(
+                "{}/{}",
+                layout.binary_dir(name, "${ANDROID_ABI}"),
+                binary.name
+            );
+            imported_library(
+                &mut out,
+                &format!("{}::{}", name, binary.target_name()),
+                binary.is_static(),
+                &location,
+                &binary.link_options,
+            );
+        }
+    }
+
+    fs::write(dir.join(EXTERN_CMAKE_FILE_NAME), out)?;
+    Ok(())
+}
+
+/// Declares the imported `qpm::<target>` for a binary at `location` in the dependencies dir
+/// and adds it to `QPM_LINK_LIBRARIES`
+fn imported_library(
+    out: &mut String,
+    target: &str,
+    is_static: bool,
+    location: &str,
+    link_options: &[String],
+) {
+    let kind = if is_static { "STATIC" } else { "SHARED" };
+    writeln!(out).unwrap();
+    writeln!(out, "add_library(qpm::{} {} IMPORTED)", target, kind).unwrap();
+    writeln!(
+        out,
+        "set_target_properties(qpm::{} PROPERTIES IMPORTED_LOCATION ${{QPM_EXTERN_DIR}}/{})",
+        target, location
+    )
+    .unwrap();
+    if !link_options.is_empty() {
+        writeln!(
+            out,
+            "set_target_properties(qpm::{} PROPERTIES INTERFACE_LINK_OPTIONS \"{}\")",
+            target,
+            link_options.join(";")
+        )
+        .unwrap();
+    }
+    writeln!(out, "list(APPEND QPM_LINK_LIBRARIES qpm::{})", target).unwrap();
+}
+
+/// The compile options of every restored dependency combined: lists in dependency order
+/// without duplicates, the newest C++ standard any of them asks for
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergedCompileOptions {
+    /// Relative to the dependencies dir
+    pub system_includes: Vec<String>,
+    pub cpp_features: Vec<String>,
+    pub cpp_flags: Vec<String>,
+    pub defines: Vec<String>,
+    pub cpp_standard: Option<u32>,
+}
+
+impl MergedCompileOptions {
+    /// The flags for compilers invoked directly, with the features spelled as `-f<feature>`
+    pub fn cmake_flags(&self) -> Vec<String> {
+        self.cpp_features
+            .iter()
+            .map(|feature| format!("-f{}", feature))
+            .chain(self.cpp_flags.iter().cloned())
+            .collect()
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, values: impl IntoIterator<Item = String>) {
+    for value in values {
+        if !list.contains(&value) {
+            list.push(value);
+        }
+    }
+}
+
+pub fn merge_compile_options(
+    package: &PackageConfig,
+    restored: &[SharedPackageConfig],
+) -> MergedCompileOptions {
+    let mut merged = MergedCompileOptions::default();
+    for shared in restored {
+        let info = &shared.config.info;
+        let options = match &info.additional_data.compile_options {
+            Some(options) => options,
+            None => continue,
+        };
+        let includes = package
+            .extern_layout
+            .include_dir(package.include_layout, package.dependency_name(&info.id));
+        push_unique(
+            &mut merged.system_includes,
+            options
+                .system_includes
+                .iter()
+                .map(|include| format!("{}/{}", includes, include.trim_matches('/'))),
+        );
+        push_unique(&mut merged.cpp_features, options.cpp_features.clone());
+        push_unique(&mut merged.cpp_flags, options.cpp_flags.clone());
+        push_unique(&mut merged.defines, options.defines.clone());
+        merged.cpp_standard = merged.cpp_standard.max(options.cpp_standard);
+    }
+    merged
+}
+
+fn mk_variable(out: &mut String, name: &str, values: &[String]) {
+    let line = format!("{} := {}", name, values.join(" "));
+    writeln!(out, "{}", line.trim_end()).unwrap();
+}
+
+/// Generates `extern.mk`, which exposes the restored dependencies to ndk-build through
+/// `QPM_INCLUDE_DIRS`, and their compile options through `QPM_C_INCLUDES`,
+/// `QPM_CPP_FEATURES`, `QPM_CPPFLAGS` and `QPM_CPP_STANDARD`
+pub fn write_extern_mk(
+    dir: &Path,
+    package: &PackageConfig,
+    restored: &[SharedPackageConfig],
+) -> Result<()> {
+    let options = merge_compile_options(package, restored);
+    let mut out = String::new();
+    writeln!(out, "# generated by qpm restore, do not edit").unwrap();
+    writeln!(
+        out,
+        "QPM_EXTERN_DIR := $(call my-dir)/{}",
+        package.dependencies_dir
+    )
+    .unwrap();
+    let layout = &package.extern_layout;
+    let mut include_dirs = vec![format!("$(QPM_EXTERN_DIR)/{}", layout.includes_dir)];
+    if package.include_layout == IncludeLayout::Namespaced {
+        include_dirs.extend(restored.iter().map(|shared| {
+            format!(
+                "$(QPM_EXTERN_DIR)/{}",
+                layout.include_dir(
+                    package.include_layout,
+                    package.dependency_name(&shared.config.info.id)
+                )
+            )
+        }));
+    }
+    mk_variable(&mut out, "QPM_INCLUDE_DIRS", &include_dirs);
+    let includes: Vec<_> = options
+        .system_includes
+        .iter()
+        .map(|include| format!("$(QPM_EXTERN_DIR)/{}", include))
+        .collect();
+    mk_variable(&mut out, "QPM_C_INCLUDES", &includes);
+    mk_variable(&mut out, "QPM_CPP_FEATURES", &options.cpp_features);
+    let mut flags = options.cpp_flags.clone();
+    flags.extend(options.defines.iter().map(|define| format!("-D{}", define)));
+    mk_variable(&mut out, "QPM_CPPFLAGS", &flags);
+    // left to Android.mk, a -std in the flags would downgrade projects on a newer standard
+    if let Some(standard) = options.cpp_standard {
+        writeln!(out, "QPM_CPP_STANDARD := {}", standard).unwrap();
+    }
+    fs::write(dir.join(EXTERN_MK_FILE_NAME), out)?;
+    Ok(())
+}
+
+/// Generates `qpm_build_info.hpp` into the dependencies' includes, holding the package id
+/// and version, the git commit, the resolved dependencies and when it was generated.
+/// `SOURCE_DATE_EPOCH` replaces the current time for reproducible builds.
+pub fn write_build_info(dir: &Path, shared: &SharedPackageConfig) -> Result<PathBuf> {
+    let info = &shared.config.info;
+    let mut dependencies: Vec<_> = shared
+        .restored_dependencies
+        .iter()
+        .map(|d| format!("{} {}", d.dependency.id, d.version))
+        .collect();
+    dependencies.sort();
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(cache::now);
+
+    let mut out = String::new();
+    writeln!(out, "// generated by qpm, do not edit").unwrap();
+    writeln!(out, "#pragma once").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "namespace qpm_build_info {{").unwrap();
+    let constants = [
+        ("id", info.id.clone()),
+        ("version", info.version.clone()),
+        ("git_commit", git::head_commit(dir).unwrap_or_default()),
+        ("build_timestamp", utc_timestamp(timestamp)),
+        ("dependencies", dependencies.join(", ")),
+    ];
+    for (name, value) in &constants {
+        writeln!(
+            out,
+            "constexpr char const* {} = \"{}\";",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "constexpr unsigned dependency_count = {};",
+        dependencies.len()
+    )
+    .unwrap();
+    writeln!(out, "}}").unwrap();
+
+    let includes = dir
+        .join(&shared.config.dependencies_dir)
+        .join(&shared.config.extern_layout.includes_dir);
+    fs::create_dir_all(&includes)?;
+    let path = includes.join(BUILD_INFO_FILE_NAME);
+    fs::write(&path, out)?;
+    Ok(path)
+}
+
+/// Formats seconds since the epoch as an ISO 8601 UTC time
+pub(crate) fn utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+    // civil date from days since 1970-01-01, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Writes ndkpath.txt, or removes a stale one when there is no NDK
+pub fn write_ndk_path(dir: &Path, ndk: Option<&Path>) -> Result<()> {
+    let path = dir.join(NDK_PATH_FILE_NAME);
+    match ndk {
+        Some(ndk) => fs::write(path, ndk.display().to_string())?,
+        None if path.exists() => fs::remove_file(path)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// CMake wants forward slashes even on Windows
+fn cmake_path(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use crate::{
+    cache::{self, last_accessed},
+    data::{
+        config::{Config, GcConfig},
+        file_repository::FileRepository,
+    },
+    error::Result,
+    utils::fs::{decode_path_component, dir_size},
+    version::Version,
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GcPolicy {
+    pub max_size: Option<u64>,
+    pub max_age_secs: Option<u64>,
+    pub keep_last: Option<usize>,
+}
+
+impl From<&GcConfig> for GcPolicy {
+    fn from(gc: &GcConfig) -> GcPolicy {
+        GcPolicy {
+            max_size: gc.max_size_mb.map(|mb| mb * 1024 * 1024),
+            max_age_secs: gc.max_age_days.map(|days| days * 24 * 60 * 60),
+            keep_last: gc.keep_last,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    pub id: String,
+    pub version: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_access: u64,
+}
+
+/// Every cached package version by id and path, skipping the tmp folder
+pub fn entries(config: &Config) -> Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    let cache_dir = config.cache_dir();
+    if !cache_dir.exists() {
+        return Ok(entries);
+    }
+    for package in fs::read_dir(cache_dir)? {
+        let package = package?;
+        if !package.file_type()?.is_dir() || package.path() == config.tmp_dir() {
+            continue;
+        }
+        for version in fs::read_dir(package.path())? {
+            let version = version?;
+            if !version.file_type()?.is_dir() {
+                continue;
+            }
+            let path = version.path();
+            entries.push(CacheEntry {
+                id: decode_path_component(&package.file_name().to_string_lossy()),
+                version: decode_path_component(&version.file_name().to_string_lossy()),
+                size: dir_size(&path)?,
+                last_access: last_accessed(&path),
+                path,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.path.cmp(&b.path)));
+    Ok(entries)
+}
+
+/// Picks the entries `policy` evicts. Locally installed artifacts and anything in `keep`
+/// (id, version) are never evicted, since they can't be downloaded again.
+pub fn select(
+    entries: &[CacheEntry],
+    policy: &GcPolicy,
+    keep: &HashSet<(String, String)>,
+    now: u64,
+) -> Vec<CacheEntry> {
+    let mut candidates: Vec<&CacheEntry> = entries
+        .iter()
+        .filter(|e| !keep.contains(&(e.id.clone(), e.version.clone())))
+        .collect();
+    let mut evicted: Vec<CacheEntry> = Vec::new();
+
+    if let Some(max_age) = policy.max_age_secs {
+        candidates.retain(|e| {
+            let expired = now.saturating_sub(e.last_access) > max_age;
+            if expired {
+                evicted.push((*e).clone());
+            }
+            !expired
+        });
+    }
+
+    if let Some(keep_last) = policy.keep_last {
+        let mut by_id: HashMap<&str, Vec<&CacheEntry>> = HashMap::new();
+        for entry in entries {
+            by_id.entry(&entry.id).or_default().push(entry);
+        }
+        let mut newest = HashSet::new();
+        for versions in by_id.values_mut() {
+            versions.sort_by_key(|e| std::cmp::Reverse(Version::parse(&e.version).ok()));
+            for entry in versions.iter().take(keep_last) {
+                newest.insert(entry.path.clone());
+            }
+        }
+        candidates.retain(|e| {
+            let old = !newest.contains(&e.path);
+            if old {
+                evicted.push((*e).clone());
+            }
+            !old
+        });
+    }
+
+    if let Some(max_size) = policy.max_size {
+        let mut total: u64 = entries.iter().map(|e| e.size).sum::<u64>()
+            - evicted.iter().map(|e| e.size).sum::<u64>();
+        candidates.sort_by_key(|e| e.last_access);
+        for entry in candidates {
+            if total <= max_size {
+                break;
+            }
+            total -= entry.size;
+            evicted.push(entry.clone());
+        }
+    }
+    evicted
+}
+
+/// Removes what `policy` evicts from the cache, returning the removed entries
+pub fn run(
+    config: &Config,
+    policy: &GcPolicy,
+    keep: &HashSet<(String, String)>,
+    dry_run: bool,
+) -> Result<Vec<CacheEntry>> {
+    // nothing may be cached or installed between choosing what to evict and evicting it
+    let _lock = (!dry_run).then(|| cache::lock::lock(config)).transpose()?;
+    let mut keep = keep.clone();
+    let repository = FileRepository::read()?;
+    for (id, versions) in &repository.artifacts {
+        for version in versions.keys() {
+            keep.insert((id.clone(), version.clone()));
+        }
+    }
+
+    let evicted = select(&entries(config)?, policy, &keep, cache::now());
+    if !dry_run {
+        for entry in &evicted {
+            fs::remove_dir_all(&entry.path)?;
+            if let Some(parent) = entry.path.parent() {
+                if fs::read_dir(parent)?.next().is_none() {
+                    fs::remove_dir(parent)?;
+                }
+            }
+        }
+    }
+    Ok(evicted)
+}
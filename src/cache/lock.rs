@@ -0,0 +1,120 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{cancel, data::config::Config, error::Result};
+
+pub const LOCK_FILE_NAME: &str = "qpm.lock";
+
+/// A lock older than this is taken over even when its owner can't be checked
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How many [`CacheLock`]s this process holds, a restore building from source and then
+/// collecting garbage takes it several times
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+pub fn path(config: &Config) -> PathBuf {
+    config.cache_dir().join(LOCK_FILE_NAME)
+}
+
+/// Keeps other qpm processes from writing to the cache until dropped, waiting for the one
+/// holding it first. Dropping it, also while a cancelled command unwinds, releases it.
+pub fn lock(config: &Config) -> Result<CacheLock> {
+    let path = path(config);
+    if DEPTH.fetch_add(1, Ordering::SeqCst) > 0 {
+        return Ok(CacheLock { path, owned: false });
+    }
+    let mut guard = CacheLock { path, owned: false };
+    fs::create_dir_all(config.cache_dir())?;
+    let mut waiting = false;
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&guard.path)
+        {
+            Ok(mut file) => {
+                guard.owned = true;
+                write!(file, "{}", std::process::id())?;
+                return Ok(guard);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+        if is_stale(&guard.path) {
+            let _ = fs::remove_file(&guard.path);
+            continue;
+        }
+        if !waiting {
+            outln!("Waiting for another qpm to finish with the cache...");
+            waiting = true;
+        }
+        cancel::check()?;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub struct CacheLock {
+    path: PathBuf,
+    /// Whether this one created the lock file, the outermost lock of the process
+    owned: bool,
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        DEPTH.fetch_sub(1, Ordering::SeqCst);
+        if self.owned {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Whether the process that wrote the lock is gone
+fn is_stale(path: &Path) -> bool {
+    let pid = fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+    if let Some(alive) = pid.and_then(platform::is_running) {
+        return !alive;
+    }
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_AFTER)
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{io, os::raw::c_int};
+
+    const ESRCH: i32 = 3;
+
+    extern "C" {
+        fn kill(pid: c_int, sig: c_int) -> c_int;
+    }
+
+    /// `None` when that can't be told, like for a process of another user
+    pub fn is_running(pid: u32) -> Option<bool> {
+        if unsafe { kill(pid as c_int, 0) } == 0 {
+            return Some(true);
+        }
+        match io::Error::last_os_error().raw_os_error() {
+            Some(ESRCH) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    pub fn is_running(_: u32) -> Option<bool> {
+        None
+    }
+}
@@ -0,0 +1,303 @@
+pub mod gc;
+pub mod lock;
+pub mod remote;
+pub mod verify;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cancel,
+    data::{
+        config::Config,
+        dependency::AdditionalDependencyData,
+        package::{ExtraBinary, PackageConfig, DEFAULT_ABI},
+        shared_package::{SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    network::{self, git::CloneOptions},
+    utils::{
+        copy::{copy_tree, CopyOptions},
+        fs::{encode_path_component, escapes_root, long_path, remove_dir_if_exists},
+    },
+};
+
+const ACCESS_FILE_NAME: &str = ".last_access";
+
+/// Where every cached version of `id` lives. Ids and versions are encoded so that any
+/// of them makes a valid folder name, see [`encode_path_component`].
+pub fn id_dir(config: &Config, id: &str) -> PathBuf {
+    config.cache_dir().join(encode_path_component(id))
+}
+
+pub fn package_dir(config: &Config, id: &str, version: &str) -> PathBuf {
+    long_path(id_dir(config, id).join(encode_path_component(version)))
+}
+
+pub fn src_dir(config: &Config, id: &str, version: &str) -> PathBuf {
+    package_dir(config, id, version).join("src")
+}
+
+pub fn lib_dir(config: &Config, id: &str, version: &str) -> PathBuf {
+    package_dir(config, id, version).join("lib")
+}
+
+/// Assets are cached with their paths in the package
+pub fn assets_dir(config: &Config, id: &str, version: &str) -> PathBuf {
+    package_dir(config, id, version).join("assets")
+}
+
+/// Copies the assets `package` declares from `src`, the folder of its sources, into the
+/// cache, replacing what was cached before
+pub fn store_assets(config: &Config, package: &PackageConfig, src: &Path) -> Result<()> {
+    let info = &package.info;
+    let assets = assets_dir(config, &info.id, &info.version);
+    remove_dir_if_exists(&assets)?;
+    for asset in &package.assets {
+        let from = src.join(&asset.path);
+        if escapes_root(&asset.path) || !from.exists() {
+            return Err(Error::Validation(format!(
+                "{} declares the asset {}, which does not exist in the package",
+                info.id, asset.path
+            )));
+        }
+        copy_tree(
+            &from,
+            &assets.join(&asset.path),
+            &CopyOptions::new().preserve_mtime(true),
+        )?;
+    }
+    Ok(())
+}
+
+/// Binaries are cached per ABI, as `lib/<abi>/<so name>`
+pub fn abi_lib_dir(config: &Config, id: &str, version: &str, abi: &str) -> PathBuf {
+    lib_dir(config, id, version).join(abi)
+}
+
+/// The cached binary of a package for `abi`, also finding arm64-v8a binaries cached
+/// directly in `lib/` before the cache was split by ABI
+pub fn cached_binary(config: &Config, package: &PackageConfig, abi: &str) -> Option<PathBuf> {
+    let info = &package.info;
+    let so_name = so_name(package);
+    let mut candidates = vec![abi_lib_dir(config, &info.id, &info.version, abi).join(&so_name)];
+    if abi == DEFAULT_ABI {
+        candidates.push(lib_dir(config, &info.id, &info.version).join(&so_name));
+    }
+    candidates.into_iter().find(|path| path.exists())
+}
+
+/// The binaries `package` ships besides its main one, none for headers only packages
+pub fn extra_binaries(package: &PackageConfig) -> &[ExtraBinary] {
+    match &package.info.additional_data.extra_binaries {
+        Some(binaries) if !is_headers_only(package) => binaries,
+        _ => &[],
+    }
+}
+
+/// The cached extra `binary` of a package for `abi`, kept next to the main one
+pub fn cached_extra_binary(
+    config: &Config,
+    package: &PackageConfig,
+    binary: &ExtraBinary,
+    abi: &str,
+) -> Option<PathBuf> {
+    let info = &package.info;
+    Some(abi_lib_dir(config, &info.id, &info.version, abi).join(&binary.name))
+        .filter(|path| path.exists())
+}
+
+/// Whether `package` only ships headers, so it has no binaries to download or link
+pub fn is_headers_only(package: &PackageConfig) -> bool {
+    package.info.additional_data.headers_only.unwrap_or(false)
+}
+
+/// Whether `package` is linked as a static library rather than loaded as a shared one
+pub fn is_static(package: &PackageConfig) -> bool {
+    package.info.additional_data.static_linking.unwrap_or(false)
+}
+
+/// Where the binary of `package` for `abi` can be downloaded from, the dependency's own
+/// links winning over the published ones. Static packages publish their `.a` under
+/// `staticLink` and `abiStaticLinks` instead.
+pub fn so_link<'a>(
+    package: &'a PackageConfig,
+    data: &'a AdditionalDependencyData,
+    abi: &str,
+) -> Option<&'a String> {
+    let additional_data = &package.info.additional_data;
+    let (abi_links, link) = if is_static(package) {
+        (
+            &additional_data.abi_static_links,
+            &additional_data.static_link,
+        )
+    } else {
+        (&additional_data.abi_so_links, &additional_data.so_link)
+    };
+    data.abi_so_links
+        .iter()
+        .chain(abi_links.iter())
+        .find_map(|links| links.get(abi))
+        .or_else(|| link.as_ref().filter(|_| abi == DEFAULT_ABI))
+}
+
+/// The file name the binary of a package is restored as, `lib<id>.a` for static libraries
+pub fn so_name(package: &PackageConfig) -> String {
+    match &package.info.additional_data.override_so_name {
+        Some(name) => name.clone(),
+        None if is_static(package) => format!("lib{}.a", package.info.id),
+        None => format!(
+            "lib{}_{}.so",
+            package.info.id,
+            package.info.version.replace('.', "_")
+        ),
+    }
+}
+
+/// Where the binary of a package in a local folder lives, checking the `lib/<abi>` folder
+/// used by vendored packages and the `build/<abi>/<build type>` folders of a local checkout
+pub fn local_binary(dir: &Path, abi: &str, so_name: &str) -> Option<PathBuf> {
+    let mut candidates = vec![dir.join("lib").join(abi).join(so_name)];
+    for build_type in ["release", "relwithdebinfo", "minsizerel", "debug"] {
+        candidates.push(dir.join("build").join(abi).join(build_type).join(so_name));
+    }
+    if abi == DEFAULT_ABI {
+        candidates.push(dir.join("lib").join(so_name));
+        candidates.push(dir.join("build").join(so_name));
+    }
+    candidates.into_iter().find(|path| path.exists())
+}
+
+/// The shared config a cached version was downloaded with, if it was cached before
+pub fn read_shared_package(
+    config: &Config,
+    id: &str,
+    version: &str,
+) -> Result<Option<SharedPackageConfig>> {
+    let dir = package_dir(config, id, version);
+    if !dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+        return Ok(None);
+    }
+    // qpm wrote it, a file that doesn't parse anymore was damaged
+    match SharedPackageConfig::read_fetched(&dir) {
+        Err(Error::Validation(message)) => Err(Error::Cache(format!(
+            "{}, run qpm cache verify --fix",
+            message
+        ))),
+        shared => shared.map(Some),
+    }
+}
+
+/// Makes sure the sources and the binaries for `abis` of a package version are in the
+/// cache, downloading whatever is missing. Returns how the sources were fetched, `None`
+/// when they were cached already.
+pub fn ensure_cached(
+    shared: &SharedPackageConfig,
+    data: &AdditionalDependencyData,
+    abis: &[String],
+    config: &Config,
+) -> Result<Option<&'static str>> {
+    let _lock = lock::lock(config)?;
+    let info = &shared.config.info;
+    let dir = package_dir(config, &info.id, &info.version);
+    fs::create_dir_all(&dir)?;
+
+    let src = src_dir(config, &info.id, &info.version);
+    // an override may point the same version at a different source or branch
+    let cached = read_shared_package(config, &info.id, &info.version)?.map(|cached| {
+        (
+            cached.config.info.url,
+            cached.config.info.additional_data.branch_name,
+        )
+    });
+    if let Some((cached_url, cached_branch)) = cached {
+        let moved = (cached_url.is_some() && cached_url != info.url)
+            || cached_branch != info.additional_data.branch_name;
+        if src.exists() && moved {
+            remove_dir_if_exists(&src)?;
+        }
+    }
+    let mut fetched = None;
+    if !src.exists() {
+        let cleanup = cancel::remove_on_cancel(&src);
+        outln!("Downloading {} {}", info.id, info.version);
+        let options = CloneOptions::for_package(&shared.config, data);
+        match network::download_package(&shared.config, &options, &src, config) {
+            Ok(method) => {
+                cleanup.keep();
+                fetched = Some(method);
+            }
+            Err(e) => {
+                remove_dir_if_exists(&src)?;
+                return Err(e);
+            }
+        }
+    }
+
+    if !shared.config.assets.is_empty() && !assets_dir(config, &info.id, &info.version).exists() {
+        store_assets(config, &shared.config, &src)?;
+    }
+
+    if !is_headers_only(&shared.config) {
+        for abi in abis {
+            if cached_binary(config, &shared.config, abi).is_some() {
+                continue;
+            }
+            if let Some(so_link) = so_link(&shared.config, data, abi) {
+                let so =
+                    abi_lib_dir(config, &info.id, &info.version, abi).join(so_name(&shared.config));
+                network::download_file(so_link, &so, config)?;
+            }
+        }
+        for binary in extra_binaries(&shared.config) {
+            for abi in abis {
+                if cached_extra_binary(config, &shared.config, binary, abi).is_some() {
+                    continue;
+                }
+                if let Some(link) = binary.link_for(abi) {
+                    let path = abi_lib_dir(config, &info.id, &info.version, abi).join(&binary.name);
+                    network::download_file(link, &path, config)?;
+                }
+            }
+        }
+    }
+
+    shared.write(&dir)?;
+    mark_accessed(config, &info.id, &info.version)?;
+    Ok(fetched)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Records that a cached version was just used, so gc keeps recently used versions around
+pub fn mark_accessed(config: &Config, id: &str, version: &str) -> Result<()> {
+    fs::write(
+        package_dir(config, id, version).join(ACCESS_FILE_NAME),
+        now().to_string(),
+    )?;
+    Ok(())
+}
+
+/// Seconds since the epoch a cached version was last used, falling back to the folder mtime
+/// for versions cached before access tracking
+pub fn last_accessed(dir: &Path) -> u64 {
+    fs::read_to_string(dir.join(ACCESS_FILE_NAME))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .or_else(|| {
+            fs::metadata(dir)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+        })
+        .unwrap_or(0)
+}
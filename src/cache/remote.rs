@@ -0,0 +1,116 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache, cancel,
+    data::{
+        config::{BinaryCacheConfig, Config},
+        package::PackageConfig,
+    },
+    error::{Error, Result},
+    network,
+    utils::fs::encode_path_component,
+};
+
+pub const TOKEN_ENV: &str = "QPM_BINARY_CACHE_TOKEN";
+
+/// Where the binary of `package` for `abi` is kept in the binary cache, if one is set up
+pub fn url(config: &Config, package: &PackageConfig, abi: &str) -> Option<String> {
+    let base = config.binary_cache.url.as_ref()?;
+    let info = &package.info;
+    Some(format!(
+        "{}/{}/{}/{}/{}",
+        base.trim_end_matches('/'),
+        encode_path_component(&info.id),
+        encode_path_component(&info.version),
+        abi,
+        cache::so_name(package)
+    ))
+}
+
+/// The curl arguments authenticating requests to the binary cache
+fn credentials(binary_cache: &BinaryCacheConfig) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    if let Some(region) = &binary_cache.s3_region {
+        let key = |name: &str| {
+            env::var(name).map_err(|_| {
+                Error::Usage(format!("binaryCache.s3Region is set, but {} isn't", name))
+            })
+        };
+        args.push("--aws-sigv4".to_string());
+        args.push(format!("aws:amz:{}:s3", region));
+        args.push("--user".to_string());
+        args.push(format!(
+            "{}:{}",
+            key("AWS_ACCESS_KEY_ID")?,
+            key("AWS_SECRET_ACCESS_KEY")?
+        ));
+    } else if let Some(token) = binary_cache
+        .token
+        .clone()
+        .or_else(|| env::var(TOKEN_ENV).ok())
+    {
+        args.push("--header".to_string());
+        args.push(format!("Authorization: Bearer {}", token));
+    }
+    Ok(args)
+}
+
+/// Downloads the binary of `package` for `abi` from the binary cache into the local cache.
+/// A cache that can't be reached is only warned about, the binary is then missing like
+/// one that isn't there.
+pub fn fetch(config: &Config, package: &PackageConfig, abi: &str) -> Result<Option<PathBuf>> {
+    let url = match url(config, package, abi) {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    let info = &package.info;
+    let dest =
+        cache::abi_lib_dir(config, &info.id, &info.version, abi).join(cache::so_name(package));
+    let args = credentials(&config.binary_cache)?;
+    match network::fetch_file(&url, &dest, &args, config) {
+        Ok(true) => {
+            outln!(
+                "Downloaded the {} binary of {} {} from the binary cache",
+                abi,
+                info.id,
+                info.version
+            );
+            Ok(Some(dest))
+        }
+        Ok(false) => Ok(None),
+        Err(e) => {
+            cancel::check()?;
+            eprintln!("warning: binary cache: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Uploads `binary`, the binary of `package` for `abi`, when the binary cache takes pushes.
+/// A failed upload is only warned about, it doesn't make what was built any worse.
+pub fn push(config: &Config, package: &PackageConfig, abi: &str, binary: &Path) -> Result<()> {
+    if !config.binary_cache.push {
+        return Ok(());
+    }
+    let url = match url(config, package, abi) {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let args = credentials(&config.binary_cache)?;
+    match network::put_file(&url, binary, &args, config) {
+        Ok(()) => outln!(
+            "Pushed the {} binary of {} {} to the binary cache",
+            abi,
+            package.info.id,
+            package.info.version
+        ),
+        Err(e) => {
+            cancel::check()?;
+            eprintln!("warning: binary cache: {}", e)
+        }
+    }
+    Ok(())
+}
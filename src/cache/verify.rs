@@ -0,0 +1,194 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    cache,
+    data::{
+        config::Config, file_repository::FileRepository, package::PACKAGE_FILE_NAME,
+        shared_package::SHARED_PACKAGE_FILE_NAME,
+    },
+    error::Result,
+    utils::fs::{decode_path_component, remove_dir_if_exists},
+};
+
+/// Something wrong between qpm.repository.json and the cache folders
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// A locally installed artifact whose cached files are gone
+    MissingFolder { id: String, version: String },
+    /// A repository entry whose package claims another id or version than it is stored as
+    RepositoryMismatch {
+        id: String,
+        version: String,
+        found: String,
+    },
+    /// A cached qpm.json whose version differs from the folder it is cached in
+    VersionMismatch {
+        id: String,
+        version: String,
+        found: String,
+        path: PathBuf,
+    },
+    /// A cached version without its qpm.shared.json, usually an interrupted download
+    Incomplete { path: PathBuf },
+    /// Anything in the cache that isn't a package version, like leftover staging folders
+    Orphaned { path: PathBuf },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::MissingFolder { id, version } => write!(
+                f,
+                "{} {} is installed locally but missing from the cache",
+                id, version
+            ),
+            Problem::RepositoryMismatch { id, version, found } => write!(
+                f,
+                "{} {} in {} is actually {}",
+                id,
+                version,
+                crate::data::file_repository::FILE_REPOSITORY_FILE_NAME,
+                found
+            ),
+            Problem::VersionMismatch {
+                id,
+                version,
+                found,
+                path,
+            } => write!(
+                f,
+                "{} {} is cached with version {} in {}",
+                id,
+                version,
+                found,
+                path.display()
+            ),
+            Problem::Incomplete { path } => {
+                write!(f, "{} has no {}", path.display(), SHARED_PACKAGE_FILE_NAME)
+            }
+            Problem::Orphaned { path } => {
+                write!(f, "{} does not belong to any package", path.display())
+            }
+        }
+    }
+}
+
+/// The `info.version` of a package manifest, or of the config in a shared one
+fn manifest_version(path: &Path) -> Option<String> {
+    let value: Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    let package = value.get("config").unwrap_or(&value);
+    package
+        .pointer("/info/version")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Cross-checks the local repository against the cache folders
+pub fn verify(config: &Config, repository: &FileRepository) -> Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+
+    for (id, versions) in &repository.artifacts {
+        for (version, shared) in versions {
+            let info = &shared.config.info;
+            if info.id != *id || info.version != *version {
+                problems.push(Problem::RepositoryMismatch {
+                    id: id.clone(),
+                    version: version.clone(),
+                    found: format!("{} {}", info.id, info.version),
+                });
+            }
+            if !cache::src_dir(config, id, version).exists() {
+                problems.push(Problem::MissingFolder {
+                    id: id.clone(),
+                    version: version.clone(),
+                });
+            }
+        }
+    }
+
+    let cache_dir = config.cache_dir();
+    if !cache_dir.exists() {
+        return Ok(problems);
+    }
+    for package in fs::read_dir(cache_dir)? {
+        let package = package?;
+        let package_path = package.path();
+        if package_path == config.tmp_dir() || package_path == cache::lock::path(config) {
+            continue;
+        }
+        if !package.file_type()?.is_dir() {
+            problems.push(Problem::Orphaned { path: package_path });
+            continue;
+        }
+        let id = decode_path_component(&package.file_name().to_string_lossy());
+        for version in fs::read_dir(&package_path)? {
+            let version = version?;
+            let path = version.path();
+            let name = decode_path_component(&version.file_name().to_string_lossy());
+            if !version.file_type()?.is_dir() || name.ends_with(".extracting") {
+                problems.push(Problem::Orphaned { path });
+                continue;
+            }
+            if !path.join(SHARED_PACKAGE_FILE_NAME).exists() {
+                problems.push(Problem::Incomplete { path });
+                continue;
+            }
+            for manifest in [
+                path.join(SHARED_PACKAGE_FILE_NAME),
+                path.join("src").join(PACKAGE_FILE_NAME),
+            ] {
+                if let Some(found) = manifest_version(&manifest).filter(|found| *found != name) {
+                    problems.push(Problem::VersionMismatch {
+                        id: id.clone(),
+                        version: name.clone(),
+                        found,
+                        path: manifest,
+                    });
+                    break;
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// Repairs what can be repaired: bad entries are dropped from the repository and bad
+/// folders removed so the next restore downloads them again
+pub fn fix(config: &Config, repository: &mut FileRepository, problems: &[Problem]) -> Result<()> {
+    let _lock = cache::lock::lock(config)?;
+    for problem in problems {
+        match problem {
+            Problem::MissingFolder { id, version }
+            | Problem::RepositoryMismatch { id, version, .. } => {
+                repository.remove_artifact(id, version);
+            }
+            Problem::VersionMismatch { id, version, .. } => {
+                repository.remove_artifact(id, version);
+                remove_dir_if_exists(&cache::package_dir(config, id, version))?;
+            }
+            Problem::Incomplete { path } => remove_dir_if_exists(path)?,
+            Problem::Orphaned { path } if path.is_dir() => remove_dir_if_exists(path)?,
+            Problem::Orphaned { path } => fs::remove_file(path)?,
+        }
+    }
+
+    // don't leave empty package folders behind
+    for problem in problems {
+        let path = match problem {
+            Problem::VersionMismatch { id, version, .. } => cache::package_dir(config, id, version),
+            Problem::Incomplete { path } | Problem::Orphaned { path } => path.clone(),
+            _ => continue,
+        };
+        if let Some(parent) = path.parent().filter(|p| *p != config.cache_dir()) {
+            if fs::read_dir(parent).is_ok_and(|mut entries| entries.next().is_none()) {
+                fs::remove_dir(parent)?;
+            }
+        }
+    }
+    repository.write()
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::error::{Error, Result};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C cancel the running command instead of killing qpm, so it can stop at the
+/// next [`check`] and clean up. A second Ctrl-C exits right away.
+pub fn install_handler() {
+    platform::install();
+}
+
+/// Called from the signal handler, which may do nothing but this
+fn on_interrupt() -> bool {
+    CANCELLED.swap(true, Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+    const SIGTERM: c_int = 15;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    extern "C" fn handle(_: c_int) {
+        if super::on_interrupt() {
+            unsafe { _exit(crate::error::exit_code::CANCELLED) }
+        }
+    }
+
+    pub fn install() {
+        unsafe {
+            signal(SIGINT, handle);
+            signal(SIGTERM, handle);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+    }
+
+    /// Returning 0 the second time lets Windows end the process
+    extern "system" fn handle(_: u32) -> i32 {
+        if super::on_interrupt() {
+            0
+        } else {
+            1
+        }
+    }
+
+    pub fn install() {
+        unsafe {
+            SetConsoleCtrlHandler(handle, 1);
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    pub fn install() {}
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Fails with [`Error::Cancelled`] once Ctrl-C was pressed, for long running loops to stop
+/// at a point where nothing is half done
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        Err(Error::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Removes `path` if the command is cancelled while the returned guard lives. The guard
+/// does it as it drops, which is while [`Error::Cancelled`] unwinds past it.
+pub fn remove_on_cancel(path: &Path) -> Cleanup {
+    Cleanup {
+        path: path.to_path_buf(),
+        armed: true,
+    }
+}
+
+pub struct Cleanup {
+    path: PathBuf,
+    armed: bool,
+}
+
+impl Cleanup {
+    /// Keeps the path even if the command is cancelled later, once it is fully written
+    pub fn keep(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if !self.armed || !is_cancelled() {
+            return;
+        }
+        if self.path.is_dir() {
+            let _ = fs::remove_dir_all(&self.path);
+        } else {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
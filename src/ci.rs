@@ -0,0 +1,68 @@
+use std::{env, fs::OpenOptions, io::Write};
+
+use crate::error::{Error, Result};
+
+/// Environment variable GitHub Actions names the step's output file in
+pub const GITHUB_OUTPUT_ENV: &str = "GITHUB_OUTPUT";
+
+/// CI systems whose workflow commands `--ci` can emit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHub,
+}
+
+impl CiProvider {
+    pub fn parse(name: &str) -> Result<CiProvider> {
+        match name {
+            "github" => Ok(CiProvider::GitHub),
+            _ => Err(Error::Usage(format!(
+                "unknown CI provider {}, only github is supported",
+                name
+            ))),
+        }
+    }
+
+    /// Reports a problem so that it shows up as an annotation on the run
+    pub fn warning(&self, message: &str) {
+        match self {
+            CiProvider::GitHub => outln!("::warning title=qpm::{}", escape_data(message)),
+        }
+    }
+
+    pub fn error(&self, message: &str) {
+        match self {
+            CiProvider::GitHub => outln!("::error title=qpm::{}", escape_data(message)),
+        }
+    }
+
+    /// Sets step outputs later steps can read. Without an output file, like when run
+    /// outside of Actions, they are printed instead.
+    pub fn set_outputs(&self, outputs: &[(&str, String)]) -> Result<()> {
+        match self {
+            CiProvider::GitHub => {
+                let mut lines = String::new();
+                for (name, value) in outputs {
+                    // multi line values would need a delimiter, none of ours has one
+                    lines.push_str(&format!("{}={}\n", name, value.replace('\n', " ")));
+                }
+                match env::var_os(GITHUB_OUTPUT_ENV) {
+                    Some(path) => OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)?
+                        .write_all(lines.as_bytes())?,
+                    None => out!("{}", lines),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Escapes a workflow command's message, which ends at the first newline
+fn escape_data(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
@@ -0,0 +1,59 @@
+use std::env;
+
+use crate::{
+    build::{self, BuildOptions, BUILD_TYPES},
+    build_files,
+    commands::{install, Args},
+    data::{config::Config, package::PackageConfig, shared_package::SharedPackageConfig},
+    error::{Error, Result},
+    repository::RepositoryChain,
+};
+
+/// Builds the project for `--abi` and `--build-type`, `--install` then adds the result to
+/// the local repository so other projects can depend on it. `--build-info` regenerates
+/// qpm_build_info.hpp first, as the `buildInfo` setting does.
+pub fn execute(mut args: Args) -> Result<()> {
+    let abi = args.value("--abi")?;
+    let build_type = args.value("--build-type")?;
+    let install = args.flag("--install");
+    let build_info = args.flag("--build-info");
+    args.finish()?;
+
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let mut options = BuildOptions::for_package(&package);
+    if let Some(abi) = abi {
+        options.abi = abi;
+    }
+    if let Some(build_type) = build_type {
+        options.build_type = BUILD_TYPES
+            .iter()
+            .find(|t| t.eq_ignore_ascii_case(&build_type))
+            .ok_or_else(|| {
+                Error::Usage(format!(
+                    "unknown build type '{}', expected one of {}",
+                    build_type,
+                    BUILD_TYPES.join(", ")
+                ))
+            })?
+            .to_string();
+    }
+    if build_info || package.build_info {
+        let repository = RepositoryChain::new(&config)?;
+        let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+        build_files::write_build_info(&dir, &shared)?;
+    }
+    let binary = build::build(&dir, &package, &options, &config)?;
+    outln!("Built {}", binary.display());
+
+    if install {
+        install::install(&dir, &package, Some(&binary), &options.abi, &config, false)?;
+        outln!(
+            "Installed {} {} into the local repository",
+            package.info.id,
+            package.info.version
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::{cmp::Reverse, collections::BTreeMap, fs};
+
+use crate::{
+    cache::{
+        self,
+        gc::{self, CacheEntry, GcPolicy},
+        verify,
+    },
+    commands::Args,
+    data::{config::Config, file_repository::FileRepository, package::normalize_id},
+    error::{Error, Result},
+    utils::fs::{format_size, remove_dir_if_exists},
+};
+
+pub fn execute(mut args: Args) -> Result<()> {
+    match args.subcommand().as_deref() {
+        Some("gc") => run_gc(args),
+        Some("verify") => run_verify(args),
+        Some("du") => run_du(args),
+        Some("remove-local") => run_remove_local(args),
+        _ => Err(Error::Usage(
+            "usage: qpm cache <gc|verify|du|remove-local>".into(),
+        )),
+    }
+}
+
+/// Parses sizes like `500M`, `2G` or `750K`, a bare number being megabytes
+pub fn parse_size(text: &str) -> Result<u64> {
+    let invalid = || Error::Usage(format!("invalid size '{}'", text));
+    let upper = text.trim().to_uppercase();
+    let upper = upper.trim_end_matches('B');
+    let (number, multiplier) = match upper.chars().last() {
+        Some('K') => (&upper[..upper.len() - 1], 1024),
+        Some('M') => (&upper[..upper.len() - 1], 1024 * 1024),
+        Some('G') => (&upper[..upper.len() - 1], 1024 * 1024 * 1024),
+        _ => (upper, 1024 * 1024),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|n| n * multiplier)
+        .map_err(|_| invalid())
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, text: &str) -> Result<T> {
+    text.trim_end_matches('d')
+        .parse()
+        .map_err(|_| Error::Usage(format!("invalid value '{}' for {}", text, name)))
+}
+
+fn run_gc(mut args: Args) -> Result<()> {
+    let config = Config::read()?;
+    let mut policy = GcPolicy::from(&config.gc);
+    if let Some(size) = args.value("--max-size")? {
+        policy.max_size = Some(parse_size(&size)?);
+    }
+    if let Some(age) = args.value("--max-age")? {
+        policy.max_age_secs = Some(parse_number::<u64>("--max-age", &age)? * 24 * 60 * 60);
+    }
+    if let Some(keep_last) = args.value("--keep-last")? {
+        policy.keep_last = Some(parse_number("--keep-last", &keep_last)?);
+    }
+    let dry_run = args.flag("--dry-run");
+    args.finish()?;
+
+    if policy == GcPolicy::default() {
+        return Err(Error::Usage(
+            "no gc policy configured, pass --max-size, --max-age or --keep-last".into(),
+        ));
+    }
+
+    let evicted = gc::run(&config, &policy, &Default::default(), dry_run)?;
+    let freed: u64 = evicted.iter().map(|e| e.size).sum();
+    for entry in &evicted {
+        outln!(
+            "{} {} {} ({})",
+            if dry_run { "Would remove" } else { "Removed" },
+            entry.id,
+            entry.version,
+            format_size(entry.size)
+        );
+    }
+    outln!(
+        "{} {} across {} versions",
+        if dry_run { "Would free" } else { "Freed" },
+        format_size(freed),
+        evicted.len()
+    );
+    Ok(())
+}
+
+/// Prints the disk usage of every cached package and its versions, largest first, or only
+/// of the package given. Locally installed versions are marked since gc never evicts them.
+fn run_du(mut args: Args) -> Result<()> {
+    let only = args.positional().map(|id| normalize_id(&id));
+    args.finish()?;
+    let config = Config::read()?;
+    let repository = FileRepository::read()?;
+
+    let mut packages: BTreeMap<String, Vec<CacheEntry>> = BTreeMap::new();
+    for entry in gc::entries(&config)? {
+        if only.as_ref().is_none_or(|id| *id == entry.id) {
+            packages.entry(entry.id.clone()).or_default().push(entry);
+        }
+    }
+    if let Some(id) = &only {
+        if packages.is_empty() {
+            return Err(Error::Usage(format!("{} is not cached", id)));
+        }
+    }
+    let mut packages: Vec<(u64, Vec<CacheEntry>)> = packages
+        .into_values()
+        .map(|mut versions| {
+            versions.sort_by_key(|version| Reverse(version.size));
+            (versions.iter().map(|v| v.size).sum(), versions)
+        })
+        .collect();
+    packages.sort_by_key(|(size, _)| Reverse(*size));
+
+    let width = packages
+        .iter()
+        .flat_map(|(_, versions)| versions)
+        .map(|v| v.id.len().max(v.version.len() + 2))
+        .max()
+        .unwrap_or(0);
+    let mut versions = 0;
+    for (size, entries) in &packages {
+        outln!(
+            "{:width$}  {:>12}",
+            entries[0].id,
+            format_size(*size),
+            width = width
+        );
+        for entry in entries {
+            let local = repository
+                .get_artifacts(&entry.id)
+                .iter()
+                .any(|shared| shared.config.info.version == entry.version);
+            outln!(
+                "  {:width$}  {:>12}{}",
+                entry.version,
+                format_size(entry.size),
+                if local { "  local" } else { "" },
+                width = width - 2
+            );
+        }
+        versions += entries.len();
+    }
+    outln!(
+        "Total {} across {} packages, {} versions",
+        format_size(packages.iter().map(|(size, _)| size).sum()),
+        packages.len(),
+        versions
+    );
+    Ok(())
+}
+
+/// Reports mismatches between the local repository and the cache, `--fix` repairs them
+fn run_verify(mut args: Args) -> Result<()> {
+    let fix = args.flag("--fix");
+    args.finish()?;
+    let config = Config::read()?;
+    // what is found has to stay as it is until it is fixed
+    let _lock = fix.then(|| cache::lock::lock(&config)).transpose()?;
+    let mut repository = FileRepository::read()?;
+
+    let problems = verify::verify(&config, &repository)?;
+    for problem in &problems {
+        outln!("{}", problem);
+    }
+    if problems.is_empty() {
+        outln!("The cache is consistent");
+    } else if fix {
+        verify::fix(&config, &mut repository, &problems)?;
+        outln!("Fixed {} problems", problems.len());
+    } else {
+        return Err(Error::Cache(format!(
+            "found {} problems, run qpm cache verify --fix to repair them",
+            problems.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Unregisters locally installed versions of a package and deletes their cached files,
+/// all versions unless one is given as `<id>@<version>`
+fn run_remove_local(mut args: Args) -> Result<()> {
+    let spec = args
+        .positional()
+        .ok_or_else(|| Error::Usage("usage: qpm cache remove-local <id>[@version]".into()))?;
+    args.finish()?;
+    let config = Config::read()?;
+    let _lock = cache::lock::lock(&config)?;
+    let mut repository = FileRepository::read()?;
+
+    let name = spec.clone();
+    let (id, versions) = match spec.split_once('@') {
+        Some((id, version)) => (normalize_id(id), vec![version.to_string()]),
+        None => {
+            let versions = repository
+                .get_artifacts(&spec)
+                .iter()
+                .map(|shared| shared.config.info.version.clone())
+                .collect();
+            (normalize_id(&spec), versions)
+        }
+    };
+    let mut removed = 0;
+    for version in &versions {
+        if repository.remove_artifact(&id, version).is_none() {
+            continue;
+        }
+        remove_dir_if_exists(&cache::package_dir(&config, &id, version))?;
+        outln!("Removed {} {}", id, version);
+        removed += 1;
+    }
+    if removed == 0 {
+        return Err(Error::Usage(format!("{} is not installed locally", name)));
+    }
+    let package_dir = cache::id_dir(&config, &id);
+    if fs::read_dir(&package_dir).is_ok_and(|mut entries| entries.next().is_none()) {
+        fs::remove_dir(package_dir)?;
+    }
+    repository.write()
+}
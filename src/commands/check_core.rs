@@ -0,0 +1,82 @@
+use std::env;
+
+use crate::{
+    commands::Args,
+    core_mods,
+    data::{config::Config, package::PackageConfig, shared_package::SharedPackageConfig},
+    error::{Error, Result},
+    repository::RepositoryChain,
+    version::Version,
+};
+
+/// Compares the project's dependencies against the core mods of a game version. The game
+/// loads its own copy of every core mod, so a dependency built against another version of
+/// one may break at runtime even though it restored fine.
+pub fn execute(mut args: Args) -> Result<()> {
+    let game_version = args.value("--game-version")?;
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let game_version = game_version
+        .or_else(|| package.target.game_version.clone())
+        .ok_or_else(|| {
+            Error::Usage(
+                "pass --game-version or set target.gameVersion in qpm.json to pick the core mods"
+                    .into(),
+            )
+        })?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    let all = core_mods::fetch(&config)?;
+    let (matched, core) = core_mods::for_game_version(&all, &game_version).ok_or_else(|| {
+        Error::Validation(format!(
+            "there are no core mods for game version {}",
+            game_version
+        ))
+    })?;
+
+    let mut mismatches = 0;
+    for core_mod in &core.mods {
+        let restored = match shared
+            .restored_dependencies
+            .iter()
+            .find(|restored| restored.dependency.id == core_mod.id)
+        {
+            Some(restored) => restored,
+            None => continue,
+        };
+        if restored.version == core_mod.version {
+            outln!("{} {} matches the core mod", core_mod.id, restored.version);
+            continue;
+        }
+        mismatches += 1;
+        // a range that allows the core version only needs a restore --update to match
+        let allowed = Version::parse(&core_mod.version)
+            .ok()
+            .zip(restored.dependency.version_req().ok())
+            .is_some_and(|(version, range)| range.matches(&version));
+        let hint = if allowed {
+            format!("run qpm restore --update {}", core_mod.id)
+        } else {
+            format!(
+                "its range {} does not allow {}",
+                restored.dependency.version_range, core_mod.version
+            )
+        };
+        eprintln!(
+            "warning: {} resolves to {} but {} loads core mod {}, {}",
+            core_mod.id, restored.version, matched, core_mod.version, hint
+        );
+    }
+
+    if mismatches > 0 {
+        return Err(Error::Validation(format!(
+            "{} dependencies don't match the core mods of {}",
+            mismatches, matched
+        )));
+    }
+    outln!("All core mod dependencies match game version {}", matched);
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::env;
+
+use crate::{
+    commands::Args,
+    conflicts,
+    data::{
+        config::Config, file_repository::FileRepository, package::PackageConfig,
+        shared_package::SharedPackageConfig,
+    },
+    error::Result,
+    repository::RepositoryChain,
+};
+
+/// Re-resolves qpm.json and rewrites qpm.shared.json without restoring. On a terminal a
+/// version conflict offers fixes to apply to qpm.json, unless `--no-interactive`.
+pub fn execute(mut args: Args) -> Result<()> {
+    let include_prerelease = args.flag("--include-prerelease");
+    let no_interactive = args.flag("--no-interactive");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+
+    let repository = RepositoryChain::new(&config)?;
+    let interactive = conflicts::is_interactive(no_interactive);
+    let shared = conflicts::resolve_with_prompt(&dir, &repository, &config, interactive, || {
+        let package = FileRepository::read()?.apply_links(&dir, &PackageConfig::read(&dir)?);
+        SharedPackageConfig::from_package(&package, &repository, &config)
+    })?;
+    shared.write(&dir)?;
+    for dependency in &shared.restored_dependencies {
+        outln!("{}: {}", dependency.dependency.id, dependency.version);
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use std::{env, path::PathBuf};
+
+use serde_json::Value;
+
+use crate::{
+    commands::Args,
+    data::config::{self, Config},
+    error::{Error, Result},
+};
+
+/// Settings holding paths, relative ones given on the command line are made absolute
+const PATH_KEYS: &[&str] = &["cachePath", "tmpPath", "ndkPath", "caBundle"];
+
+/// `qpm config get/set/list [--local]`, keys are the camelCase names in qpm.settings.json
+/// with dots for nested ones, like `gc.maxSizeMb`
+pub fn execute(mut args: Args) -> Result<()> {
+    let local = args.flag("--local");
+    match args.subcommand().as_deref() {
+        Some("get") => {
+            let key = args
+                .positional()
+                .map(|key| canonical_key(&key))
+                .ok_or_else(|| Error::Usage("usage: qpm config get [--local] <key>".into()))?;
+            args.finish()?;
+            let value = if local {
+                Value::Object(config::read_local(&project_root()?)?)
+            } else {
+                serde_json::to_value(Config::read()?)?
+            };
+            match lookup(&value, &key) {
+                Some(Value::String(s)) => outln!("{}", s),
+                Some(value) => outln!("{}", value),
+                None if local => outln!("{} is not set for this project", key),
+                None => return Err(Error::Usage(format!("unknown config key '{}'", key))),
+            }
+        }
+        Some("set") => {
+            let key = args.positional();
+            let raw = args.positional();
+            args.finish()?;
+            let (key, raw) = key.zip(raw).ok_or_else(|| {
+                Error::Usage("usage: qpm config set [--local] <key> <value>".into())
+            })?;
+            set(&canonical_key(&key), raw, local)?;
+        }
+        Some("list") => {
+            let json = args.flag("--json");
+            args.finish()?;
+            let value = if local {
+                Value::Object(config::read_local(&project_root()?)?)
+            } else {
+                serde_json::to_value(Config::read()?)?
+            };
+            if json {
+                outln!("{}", serde_json::to_string_pretty(&value)?);
+            } else {
+                print_flat(&value, "");
+            }
+        }
+        Some(other) => {
+            return Err(Error::Usage(format!("unknown config command '{}'", other)));
+        }
+        None => {
+            return Err(Error::Usage(
+                "usage: qpm config <get|set|list> [--local] [key] [value]".into(),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn set(key: &str, raw: String, local: bool) -> Result<()> {
+    let global = Config::read_global()?;
+    if lookup(&serde_json::to_value(&global)?, key).is_none() {
+        return Err(Error::Usage(format!("unknown config key '{}'", key)));
+    }
+    let mut value = serde_json::from_str(&raw).unwrap_or(Value::String(raw));
+    if let (true, Some(path)) = (PATH_KEYS.contains(&key), value.as_str()) {
+        if !local {
+            value = Value::String(env::current_dir()?.join(path).display().to_string());
+        }
+    }
+    let mut overrides = Value::Object(Default::default());
+    insert(&mut overrides, key, value);
+
+    if local {
+        let top = key.split('.').next().unwrap_or(key);
+        if !config::LOCAL_KEYS.contains(&top) {
+            return Err(Error::Usage(format!(
+                "{} can't be set per project, only {}",
+                key,
+                config::LOCAL_KEYS.join(", ")
+            )));
+        }
+        let root = project_root()?;
+        let mut local_config = Value::Object(config::read_local(&root)?);
+        config::merge(&mut local_config, overrides);
+        let local_config = match local_config {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        };
+        // make sure the project's config still makes sense before keeping it
+        global
+            .with_overrides(&root, local_config.clone())
+            .map_err(|e| Error::Validation(format!("invalid value for {}: {}", key, e)))?
+            .validate_key(key)?;
+        config::write_local(&root, &local_config)?;
+    } else {
+        let mut merged = serde_json::to_value(&global)?;
+        config::merge(&mut merged, overrides);
+        let config = parse(merged, key)?;
+        config.validate_key(key)?;
+        config.write()?;
+    }
+    outln!("Set {}", key);
+    Ok(())
+}
+
+/// Short names for the settings people reach for most
+fn canonical_key(key: &str) -> String {
+    match key {
+        "cache" => "cachePath",
+        "tmp" | "temp" => "tmpPath",
+        "ndk" => "ndkPath",
+        key => key,
+    }
+    .to_string()
+}
+
+fn project_root() -> Result<PathBuf> {
+    let cwd = env::current_dir()?;
+    Ok(config::find_project_root(&cwd).unwrap_or(cwd))
+}
+
+fn parse(value: Value, key: &str) -> Result<Config> {
+    serde_json::from_value(value)
+        .map_err(|e| Error::Validation(format!("invalid value for {}: {}", key, e)))
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+fn insert(value: &mut Value, key: &str, new: Value) {
+    let mut parts = key.split('.').peekable();
+    let mut value = value;
+    while let Some(part) = parts.next() {
+        let object = value.as_object_mut().unwrap();
+        if parts.peek().is_none() {
+            object.insert(part.to_string(), new);
+            return;
+        }
+        value = object
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+}
+
+/// Prints every leaf setting as `key = value`
+fn print_flat(value: &Value, prefix: &str) {
+    match value {
+        Value::Object(object) if !object.is_empty() || prefix.is_empty() => {
+            for (key, child) in object {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                print_flat(child, &key);
+            }
+        }
+        Value::String(s) => outln!("{} = {}", prefix, s),
+        value => outln!("{} = {}", prefix, value),
+    }
+}
@@ -0,0 +1,250 @@
+use std::{env, path::Path};
+
+use crate::{
+    commands::Args,
+    data::{
+        config::Config,
+        dependency::{AdditionalDependencyData, Dependency},
+        file_repository::FileRepository,
+        package::{normalize_id, validate_alias, PackageConfig},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::{qpackages, RepositoryChain},
+    version::{Version, VersionReq},
+};
+
+pub fn execute(mut args: Args) -> Result<()> {
+    match args.subcommand().as_deref() {
+        Some("add") => add(args),
+        Some("remove") => remove(args),
+        Some("pin") => pin(args),
+        Some("unpin") => unpin(args),
+        _ => Err(Error::Usage(
+            "usage: qpm dependency <add|remove|pin|unpin> <id>[@range] [--local-path <path>] [--private] [--as <name>]"
+                .into(),
+        )),
+    }
+}
+
+/// Splits `id@range` into its parts, the range being optional
+pub fn parse_spec(spec: &str) -> Result<(String, Option<VersionReq>)> {
+    match spec.split_once('@') {
+        Some((id, range)) => Ok((normalize_id(id), Some(VersionReq::parse(range)?))),
+        None => Ok((normalize_id(spec), None)),
+    }
+}
+
+/// A caret range on the newest published version of `id`, `None` if the registry doesn't know it
+pub fn latest_range(id: &str, config: &Config) -> Result<Option<VersionReq>> {
+    Ok(
+        qpackages::get_latest_matching(id, &VersionReq::STAR, config)?
+            .map(|version| VersionReq::caret(&version)),
+    )
+}
+
+fn add(mut args: Args) -> Result<()> {
+    let local_path = args.value("--local-path")?;
+    let features: Vec<String> = args
+        .values("--feature")?
+        .iter()
+        .flat_map(|f| f.split(','))
+        .map(|f| f.trim().to_string())
+        .filter(|f| !f.is_empty())
+        .collect();
+    let include_prerelease = args.flag("--include-prerelease");
+    let private = args.flag("--private");
+    let alias = args.value("--as")?;
+    let spec = args
+        .positional()
+        .ok_or_else(|| Error::Usage("usage: qpm dependency add <id>[@range]".into()))?;
+    args.finish()?;
+    if let Some(alias) = &alias {
+        validate_alias(alias)?;
+    }
+
+    let dir = env::current_dir()?;
+    let mut package = PackageConfig::read(&dir)?;
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+    let (id, range) = parse_spec(&spec)?;
+
+    let versions = match qpackages::get_versions(&id, &config) {
+        Ok(Some(versions)) => Some(versions),
+        Ok(None) => {
+            eprintln!("warning: package {} does not exist in any registry", id);
+            None
+        }
+        // the registry isn't needed when we're told exactly what to depend on
+        Err(e) if range.is_some() || local_path.is_some() => {
+            eprintln!(
+                "warning: couldn't check {} against the registries: {}",
+                id, e
+            );
+            None
+        }
+        Err(e) => return Err(e),
+    };
+
+    let range = match range {
+        Some(range) => {
+            let published = versions.iter().flatten().any(|v| {
+                Version::parse(&v.version)
+                    .map(|v| range.matches_with_prerelease(&v, config.include_prerelease))
+                    .unwrap_or(false)
+            });
+            if versions.is_some() && !published {
+                eprintln!("warning: no published version of {} matches {}", id, range);
+            }
+            range
+        }
+        None => match (&versions, &local_path) {
+            (Some(_), _) => latest_range(&id, &config)?.ok_or_else(|| {
+                Error::Validation(format!("{} has no stable version to depend on", id))
+            })?,
+            (None, Some(local_path)) => {
+                let local = PackageConfig::read(Path::new(local_path))?;
+                VersionReq::caret(&Version::parse(&local.info.version)?)
+            }
+            (None, None) => {
+                return Err(Error::Validation(format!(
+                    "can't pick a version for unknown package {}, pass one as {}@<range>",
+                    id, id
+                )))
+            }
+        },
+    };
+
+    let dependency = Dependency {
+        id: id.clone(),
+        alias,
+        version_range: range.to_string(),
+        additional_data: AdditionalDependencyData {
+            local_path,
+            is_private: Some(true).filter(|_| private),
+            features: Some(features).filter(|f| !f.is_empty()),
+            ..Default::default()
+        },
+    };
+    match package.dependencies.iter_mut().find(|d| d.id == id) {
+        Some(existing) => {
+            eprintln!(
+                "warning: overwriting existing dependency {} ({})",
+                id, existing.version_range
+            );
+            *existing = dependency;
+        }
+        None => package.dependencies.push(dependency),
+    }
+    package.validate_aliases()?;
+    package.write(&dir)?;
+    outln!("Added {}@{}", id, range);
+    Ok(())
+}
+
+/// Drops the dependency from qpm.json, then re-resolves and restores so that orphaned
+/// transitive dependencies and the removed headers and binaries disappear as well
+fn remove(mut args: Args) -> Result<()> {
+    let id = args
+        .positional()
+        .map(|id| normalize_id(&id))
+        .ok_or_else(|| Error::Usage("usage: qpm dependency remove <id>".into()))?;
+    let include_prerelease = args.flag("--include-prerelease");
+    args.finish()?;
+
+    let dir = env::current_dir()?;
+    let mut package = PackageConfig::read(&dir)?;
+    let before = package.dependencies.len();
+    package.dependencies.retain(|d| d.id != id);
+    if package.dependencies.len() == before {
+        return Err(Error::Usage(format!("{} is not a dependency", id)));
+    }
+    package.write(&dir)?;
+
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+    let repository = RepositoryChain::new(&config)?;
+    let package = FileRepository::read()?.apply_links(&dir, &package);
+    let shared = SharedPackageConfig::from_package(&package, &repository, &config)?;
+    shared.write(&dir)?;
+    shared.restore(&dir, &repository, &config)?;
+    outln!("Removed {}", id);
+    Ok(())
+}
+
+/// Locks a dependency to exactly one version, the resolved one unless given as
+/// `id@version`, keeping its range around for `unpin`
+fn pin(mut args: Args) -> Result<()> {
+    let spec = args
+        .positional()
+        .ok_or_else(|| Error::Usage("usage: qpm dependency pin <id>[@version]".into()))?;
+    args.finish()?;
+    let (id, version) = match spec.split_once('@') {
+        Some((id, version)) => (normalize_id(id), Some(Version::parse(version)?)),
+        None => (normalize_id(&spec), None),
+    };
+
+    let dir = env::current_dir()?;
+    let mut package = PackageConfig::read(&dir)?;
+    if !package.dependencies.iter().any(|d| d.id == id) {
+        return Err(Error::Usage(format!("{} is not a dependency", id)));
+    }
+    let version = match version {
+        Some(version) => version,
+        None => {
+            let config = Config::read()?;
+            let repository = RepositoryChain::new(&config)?;
+            let shared =
+                SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+            let restored = shared
+                .restored_dependencies
+                .iter()
+                .find(|restored| restored.dependency.id == id)
+                .ok_or_else(|| Error::Usage(format!("{} isn't resolved, run qpm restore", id)))?;
+            Version::parse(&restored.version)?
+        }
+    };
+
+    let dependency = package
+        .dependencies
+        .iter_mut()
+        .find(|d| d.id == id)
+        .unwrap();
+    // pinning again keeps the range from before the first pin
+    if !dependency.is_pinned() {
+        dependency.additional_data.unpinned_range = Some(dependency.version_range.clone());
+    }
+    dependency.version_range = format!("={}", version);
+    package.write(&dir)?;
+    outln!("Pinned {} to ={}", id, version);
+    Ok(())
+}
+
+/// Puts back the range a pinned dependency had, a caret range on the pinned version for
+/// pins written by hand
+fn unpin(mut args: Args) -> Result<()> {
+    let id = args
+        .positional()
+        .map(|id| normalize_id(&id))
+        .ok_or_else(|| Error::Usage("usage: qpm dependency unpin <id>".into()))?;
+    args.finish()?;
+
+    let dir = env::current_dir()?;
+    let mut package = PackageConfig::read(&dir)?;
+    let dependency = package
+        .dependencies
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| Error::Usage(format!("{} is not a dependency", id)))?;
+    let range = match dependency.additional_data.unpinned_range.take() {
+        Some(range) => range,
+        None => match dependency.version_range.trim().strip_prefix('=') {
+            Some(version) => VersionReq::caret(&Version::parse(version.trim())?).to_string(),
+            None => return Err(Error::Usage(format!("{} is not pinned", id))),
+        },
+    };
+    dependency.version_range = range.clone();
+    package.write(&dir)?;
+    outln!("Unpinned {}, back to {}", id, range);
+    Ok(())
+}
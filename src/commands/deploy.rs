@@ -0,0 +1,94 @@
+use std::{env, fs};
+
+use crate::{
+    adb, build, cache,
+    commands::{log, qmod, Args},
+    data::{
+        config::Config,
+        package::{BinaryLayout, PackageConfig},
+    },
+    error::{Error, Result},
+};
+
+/// Pushes the built mod to a connected Quest and restarts the game.
+///
+/// `--with-dependencies` also pushes the restored dependency libraries, `--qmod` the
+/// project's .qmod, `--no-restart` leaves the game alone and `--log` streams the mod's
+/// logcat output afterwards.
+pub fn execute(mut args: Args) -> Result<()> {
+    let with_dependencies = args.flag("--with-dependencies");
+    let push_qmod = args.flag("--qmod");
+    let no_restart = args.flag("--no-restart");
+    let log = args.flag("--log");
+    let remote_dir = args
+        .value("--remote-dir")?
+        .unwrap_or_else(|| adb::MODS_DIR.to_string());
+    args.finish()?;
+
+    let dir = env::current_dir()?;
+    let package = PackageConfig::read(&dir)?;
+    if cache::is_static(&package) {
+        return Err(Error::Usage(format!(
+            "{} is a static library, it is linked into its dependents instead of deployed",
+            package.info.id
+        )));
+    }
+    let abi = build::BuildOptions::for_package(&package).abi;
+    let so_name = cache::so_name(&package);
+    let binary = cache::local_binary(&dir, &abi, &so_name)
+        .ok_or_else(|| Error::Usage(format!("{} isn't built yet, run qpm build", so_name)))?;
+    let qmod = dir.join(format!("{}.qmod", package.info.id));
+    if push_qmod && !qmod.exists() {
+        return Err(Error::Usage(format!("{} does not exist", qmod.display())));
+    }
+    adb::ensure_device()?;
+
+    adb::push(&binary, &format!("{}/{}", remote_dir, so_name))?;
+    outln!("Pushed {}", so_name);
+    if with_dependencies {
+        let extern_dir = dir.join(&package.dependencies_dir);
+        let layout = &package.extern_layout;
+        let folders = match layout.binary_layout {
+            BinaryLayout::Shared => vec![extern_dir.join(&layout.libs_dir).join(&abi)],
+            BinaryLayout::PerPackage => match fs::read_dir(extern_dir.join(&layout.libs_dir)) {
+                Ok(packages) => packages
+                    .map(|entry| Ok(entry?.path().join(&abi)))
+                    .collect::<Result<Vec<_>>>()?,
+                Err(_) => Vec::new(),
+            },
+        };
+        for libs in folders.into_iter().filter(|libs| libs.exists()) {
+            for lib in fs::read_dir(libs)? {
+                let lib = lib?;
+                let name = lib.file_name().to_string_lossy().to_string();
+                // static libraries are already linked into the mod
+                if name.ends_with(".a") {
+                    continue;
+                }
+                adb::push(&lib.path(), &format!("{}/{}", adb::LIBS_DIR, name))?;
+                outln!("Pushed {}", name);
+            }
+        }
+    }
+    if push_qmod {
+        let name = qmod.file_name().unwrap().to_string_lossy().to_string();
+        adb::push(&qmod, &format!("{}/{}", qmod::DEFAULT_REMOTE_DIR, name))?;
+        outln!("Pushed {}", name);
+    }
+    if !no_restart {
+        adb::restart_game()?;
+        outln!("Restarted {}", adb::GAME_PACKAGE);
+    }
+
+    if log {
+        let config = Config::read()?;
+        log::follow(
+            &dir,
+            &package,
+            &config,
+            false,
+            env::var_os("NO_COLOR").is_none(),
+        )?;
+    }
+    Ok(())
+}
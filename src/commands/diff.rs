@@ -0,0 +1,110 @@
+use std::{env, fs, path::Path};
+
+use crate::{
+    cancel,
+    commands::Args,
+    data::{
+        config::Config,
+        shared_package::{ChangeKind, SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    network::git,
+    utils::fs::remove_dir_if_exists,
+};
+
+/// Prints the dependencies resolved differently between two qpm.shared.json files. With
+/// none given the one committed at `--rev` (HEAD by default) is compared with the working
+/// copy, with one given that file is. `--json` prints the changes for tooling.
+pub fn execute(mut args: Args) -> Result<()> {
+    let rev = args.value("--rev")?;
+    let json = args.flag("--json");
+    let old = args.positional();
+    let new = args.positional();
+    args.finish()?;
+    if rev.is_some() && old.is_some() {
+        return Err(Error::Usage(
+            "pass either --rev or the files to compare, not both".into(),
+        ));
+    }
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+
+    let (before, after) = match (old, new) {
+        (Some(old), Some(new)) => (read_file(&old, &config)?, read_file(&new, &config)?),
+        (Some(old), None) => (read_file(&old, &config)?, SharedPackageConfig::read(&dir)?),
+        _ => {
+            // everything is new when it wasn't committed yet
+            let committed = match rev.as_deref() {
+                Some(rev) => git::show_file(&dir, rev, SHARED_PACKAGE_FILE_NAME)?,
+                None if git::head_commit(&dir).is_none() => None,
+                None => git::show_file(&dir, "HEAD", SHARED_PACKAGE_FILE_NAME)?,
+            };
+            let before = match committed {
+                Some(contents) => parse(&contents, &config)?,
+                None => SharedPackageConfig::default(),
+            };
+            (before, SharedPackageConfig::read(&dir)?)
+        }
+    };
+
+    let changes = before.changes(&after);
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&changes)?);
+        return Ok(());
+    }
+    if changes.is_empty() {
+        outln!("No dependency changes");
+        return Ok(());
+    }
+    for change in &changes {
+        let before = change.before.as_deref().unwrap_or_default();
+        let after = change.after.as_deref().unwrap_or_default();
+        let delta = change
+            .delta
+            .map(|delta| format!(" ({})", delta))
+            .unwrap_or_default();
+        match change.kind {
+            ChangeKind::Added => outln!("+ {} {}", change.id, after),
+            ChangeKind::Removed => outln!("- {} {}", change.id, before),
+            ChangeKind::Upgraded => {
+                outln!("^ {} {} -> {}{}", change.id, before, after, delta)
+            }
+            ChangeKind::Downgraded => {
+                outln!("v {} {} -> {}{}", change.id, before, after, delta)
+            }
+        }
+    }
+    let count = |kind: ChangeKind| changes.iter().filter(|c| c.kind == kind).count();
+    outln!(
+        "{} added, {} removed, {} upgraded, {} downgraded",
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Upgraded),
+        count(ChangeKind::Downgraded)
+    );
+    Ok(())
+}
+
+/// A shared config from a file of any name, or the one in a folder
+fn read_file(path: &str, config: &Config) -> Result<SharedPackageConfig> {
+    let path = Path::new(path);
+    if path.is_dir() {
+        return SharedPackageConfig::read(path);
+    }
+    parse(&fs::read(path)?, config)
+}
+
+/// Reads `contents` like a qpm.shared.json on disk, migrating older schema versions
+fn parse(contents: &[u8], config: &Config) -> Result<SharedPackageConfig> {
+    let tmp = config
+        .tmp_dir()
+        .join(format!("diff-{}", std::process::id()));
+    remove_dir_if_exists(&tmp)?;
+    fs::create_dir_all(&tmp)?;
+    let _cleanup = cancel::remove_on_cancel(&tmp);
+    let shared = fs::write(tmp.join(SHARED_PACKAGE_FILE_NAME), contents)
+        .map_err(Error::from)
+        .and_then(|_| SharedPackageConfig::read(&tmp));
+    remove_dir_if_exists(&tmp)?;
+    shared
+}
@@ -0,0 +1,270 @@
+use std::env;
+
+use crate::{
+    commands::{dependency::parse_spec, Args},
+    data::{
+        config::Config,
+        file_repository::FileRepository,
+        package::normalize_id,
+        shared_package::{SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    repository::qpackages,
+    version::{Version, VersionReq},
+};
+
+/// Shows a package's metadata, `--changelog` adds the release notes of every version
+/// since the one the current project restored
+pub fn execute(mut args: Args) -> Result<()> {
+    let include_prerelease = args.flag("--include-prerelease");
+    let changelog = args.flag("--changelog");
+    let spec = args
+        .positional()
+        .ok_or_else(|| Error::Usage("usage: qpm info <id>[@version] [--changelog]".into()))?;
+    args.finish()?;
+
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+    let repository = FileRepository::read()?;
+    // a full version means exactly that version, anything else is a range
+    let (id, req) = match spec.split_once('@') {
+        Some((id, version)) if Version::parse(version).is_ok() => (
+            normalize_id(id),
+            VersionReq::parse(&format!("={}", version))?,
+        ),
+        _ => {
+            let (id, req) = parse_spec(&spec)?;
+            (id, req.unwrap_or_default())
+        }
+    };
+
+    let mut local_versions: Vec<Version> = repository
+        .get_artifacts(&id)
+        .iter()
+        .filter_map(|shared| Version::parse(&shared.config.info.version).ok())
+        .collect();
+    local_versions.sort();
+    let registry_versions = match qpackages::get_versions(&id, &config) {
+        Ok(versions) => versions,
+        Err(e) if !local_versions.is_empty() => {
+            eprintln!("warning: couldn't reach the registries: {}", e);
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    let mut registry_versions: Vec<Version> = registry_versions
+        .iter()
+        .flatten()
+        .filter_map(|v| Version::parse(&v.version).ok())
+        .collect();
+    registry_versions.sort();
+
+    // local artifacts win, they are what restore would pick up first
+    let local = local_versions
+        .iter()
+        .rev()
+        .find(|v| req.matches_with_prerelease(v, config.include_prerelease));
+    let (shared, source): (SharedPackageConfig, &str) = match local {
+        Some(version) => (
+            repository
+                .get_artifact(&id, &version.to_string())
+                .cloned()
+                .unwrap(),
+            "local repository",
+        ),
+        None => {
+            let version = registry_versions
+                .iter()
+                .rev()
+                .find(|v| req.matches_with_prerelease(v, config.include_prerelease))
+                .ok_or_else(|| {
+                    Error::Resolution(format!("no version of {} matches {}", id, req))
+                })?;
+            let shared = qpackages::get_shared_package(&id, &version.to_string(), &config)?
+                .ok_or_else(|| Error::Resolution(format!("{} {} not found", id, version)))?;
+            (shared, "registry")
+        }
+    };
+
+    print_info(&shared, source);
+    if changelog {
+        print_changelog(
+            &shared,
+            &local_versions,
+            &registry_versions,
+            &repository,
+            &config,
+        )?;
+    }
+    if !registry_versions.is_empty() {
+        outln!("published versions: {}", join(&registry_versions));
+    }
+    if !local_versions.is_empty() {
+        outln!("local versions: {}", join(&local_versions));
+    }
+    Ok(())
+}
+
+/// The version of `id` the project in the working directory restored, if it depends on it
+fn restored_version(id: &str) -> Option<Version> {
+    let dir = env::current_dir().ok()?;
+    if !dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+        return None;
+    }
+    let shared = SharedPackageConfig::read(&dir).ok()?;
+    let restored = shared
+        .restored_dependencies
+        .iter()
+        .find(|d| d.dependency.id == id)?;
+    Version::parse(&restored.version).ok()
+}
+
+/// Prints the release notes of `shared`, preceded by those of every version between the
+/// restored one and it when upgrading
+fn print_changelog(
+    shared: &SharedPackageConfig,
+    local_versions: &[Version],
+    registry_versions: &[Version],
+    repository: &FileRepository,
+    config: &Config,
+) -> Result<()> {
+    let info = &shared.config.info;
+    let target = Version::parse(&info.version)?;
+    let mut versions: Vec<&Version> = match restored_version(&info.id) {
+        Some(from) => {
+            outln!("changes since {}:", from);
+            local_versions
+                .iter()
+                .chain(registry_versions)
+                .filter(|v| **v > from && **v <= target)
+                .collect()
+        }
+        None => {
+            outln!("release notes:");
+            Vec::new()
+        }
+    };
+    versions.sort();
+    versions.dedup();
+    if versions.is_empty() {
+        versions.push(&target);
+    }
+
+    for version in versions {
+        let version = version.to_string();
+        let notes = if version == info.version {
+            info.release_notes.clone()
+        } else {
+            match repository.get_artifact(&info.id, &version) {
+                Some(local) => local.config.info.release_notes.clone(),
+                None => qpackages::get_shared_package(&info.id, &version, config)?
+                    .and_then(|s| s.config.info.release_notes),
+            }
+        };
+        outln!("  {}:", version);
+        match notes {
+            Some(notes) => {
+                for line in notes.lines() {
+                    outln!("    {}", line);
+                }
+            }
+            None => outln!("    no release notes"),
+        }
+    }
+    if let Some(changelog) = &info.changelog {
+        outln!("full changelog: {}", changelog);
+    }
+    Ok(())
+}
+
+fn join(versions: &[Version]) -> String {
+    versions
+        .iter()
+        .map(Version::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_info(shared: &SharedPackageConfig, source: &str) {
+    let info = &shared.config.info;
+    let data = &info.additional_data;
+    outln!("{} {} ({})", info.id, info.version, source);
+    outln!("name: {}", info.name);
+    if shared.yanked {
+        outln!("yanked: true");
+    }
+    if let Some(deprecation) = &shared.deprecated {
+        outln!(
+            "deprecated: {}",
+            deprecation.message.as_deref().unwrap_or("yes")
+        );
+        if let Some(replacement) = &deprecation.replacement {
+            outln!("replaced by: {}", replacement);
+        }
+    }
+    if let Some(description) = &info.description {
+        outln!("description: {}", description);
+    }
+    if let Some(url) = &info.url {
+        outln!("url: {}", url);
+    }
+    if !info.authors.is_empty() {
+        outln!("authors: {}", info.authors.join(", "));
+    }
+    if let Some(license) = &info.license {
+        outln!("license: {}", license);
+    }
+    if let Some(changelog) = &info.changelog {
+        outln!("changelog: {}", changelog);
+    }
+    if let Some(game_versions) = &data.game_versions {
+        outln!("game versions: {}", game_versions.join(", "));
+    }
+
+    let headers_only = crate::cache::is_headers_only(&shared.config);
+    outln!("headers only: {}", headers_only);
+    outln!("static linking: {}", data.static_linking.unwrap_or(false));
+    if !headers_only {
+        outln!("so name: {}", crate::cache::so_name(&shared.config));
+    }
+    for binary in crate::cache::extra_binaries(&shared.config) {
+        let linked = if binary.is_linked() {
+            ""
+        } else {
+            ", not linked"
+        };
+        outln!(
+            "extra binary: {} (qpm::{}::{}{})",
+            binary.name,
+            shared.config.info.id,
+            binary.target_name(),
+            linked
+        );
+    }
+    let links = [
+        ("so link", &data.so_link),
+        ("debug so link", &data.debug_so_link),
+        ("static link", &data.static_link),
+        ("mod link", &data.mod_link),
+        ("branch", &data.branch_name),
+    ];
+    for (label, link) in links.iter() {
+        if let Some(link) = link {
+            outln!("{}: {}", label, link);
+        }
+    }
+
+    if shared.restored_dependencies.is_empty() {
+        outln!("dependencies: none");
+    } else {
+        outln!("dependencies:");
+        for dependency in &shared.restored_dependencies {
+            outln!(
+                "    {} {} ({})",
+                dependency.dependency.id,
+                dependency.version,
+                dependency.dependency.version_range
+            );
+        }
+    }
+}
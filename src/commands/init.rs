@@ -0,0 +1,158 @@
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use crate::{
+    commands::{dependency, Args},
+    data::{
+        config::Config,
+        dependency::Dependency,
+        package::{normalize_id, validate_id, PackageConfig, PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    migration,
+    scaffold::{self, BuildSystem},
+    utils::prompt::Prompt,
+};
+
+/// Dependencies nearly every Beat Saber mod starts out with
+pub const COMMON_DEPENDENCIES: &[&str] = &["beatsaber-hook", "codegen"];
+
+pub fn execute(mut args: Args) -> Result<()> {
+    let prompt = Prompt {
+        assume_yes: args.flag("--yes"),
+    };
+    let force = args.flag("--force");
+    let id = args.value("--id")?;
+    let name = args.value("--name")?;
+    let author = args.value("--author")?;
+    let version = args.value("--version")?;
+    let ndk_version = args.value("--ndk-version")?;
+    let build_system = args.value("--build-system")?;
+    let template = args.value("--template")?;
+    let common = args.flag("--common-dependencies");
+    let dir = args
+        .positional()
+        .map(PathBuf::from)
+        .unwrap_or(env::current_dir()?);
+    args.finish()?;
+
+    if dir.join(PACKAGE_FILE_NAME).exists() && !force {
+        return Err(Error::Usage(format!(
+            "{} already exists in {}, pass --force to overwrite it",
+            PACKAGE_FILE_NAME,
+            dir.display()
+        )));
+    }
+
+    let folder_name = dir
+        .canonicalize()
+        .unwrap_or_else(|_| dir.clone())
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "my-mod".to_string());
+    let id = match id {
+        Some(id) => id,
+        None => prompt.ask("Package id", &folder_name)?,
+    };
+    let id = normalize_id(&id);
+    validate_id(&id)?;
+    let name = match name {
+        Some(name) => name,
+        None => prompt.ask("Package name", &id)?,
+    };
+    let author = match author {
+        Some(author) => author,
+        None => prompt.ask("Author", "")?,
+    };
+    let version = match version {
+        Some(version) => version,
+        None => prompt.ask("Version", "0.1.0")?,
+    };
+    let ndk_version = match ndk_version {
+        Some(ndk_version) => ndk_version,
+        None => prompt.ask("NDK version", "")?,
+    };
+
+    let config = Config::read()?;
+    let files = match &template {
+        Some(template) => {
+            let url = scaffold::resolve_template(template, &config);
+            outln!("Fetching template {}", url);
+            scaffold::fetch_template(url, &config)?
+        }
+        None => {
+            let build_system = match build_system {
+                Some(build_system) => build_system,
+                None => prompt.ask("Build system (cmake/ndk-build)", "cmake")?,
+            };
+            let build_system = BuildSystem::parse(&build_system)
+                .ok_or_else(|| Error::Usage(format!("unknown build system '{}'", build_system)))?;
+            scaffold::default_files(build_system)
+        }
+    };
+    let common =
+        common || prompt.confirm(&format!("Add {}", COMMON_DEPENDENCIES.join(" and ")), true)?;
+
+    let vars: BTreeMap<String, String> = [
+        ("id", &id),
+        ("name", &name),
+        ("author", &author),
+        ("version", &version),
+        ("ndk", &ndk_version),
+    ]
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect();
+
+    // a template may bring its own qpm.json, which only gets its identity filled in
+    let template_package = files.iter().any(|f| f.path == PACKAGE_FILE_NAME);
+    if template_package && dir.join(PACKAGE_FILE_NAME).exists() {
+        fs::remove_file(dir.join(PACKAGE_FILE_NAME))?;
+    }
+    let written = scaffold::write_files(&dir, &files, &vars)?;
+
+    let mut package = if template_package {
+        PackageConfig::read(&dir)?
+    } else {
+        PackageConfig {
+            schema_version: migration::SCHEMA_VERSION,
+            shared_dir: "shared".to_string(),
+            dependencies_dir: "extern".to_string(),
+            ..Default::default()
+        }
+    };
+    package.info.id = id;
+    package.info.name = name;
+    package.info.version = version;
+    if common {
+        for common_id in COMMON_DEPENDENCIES {
+            if !package.dependencies.iter().any(|d| d.id == *common_id) {
+                let range = match dependency::latest_range(common_id, &config) {
+                    Ok(Some(range)) => range.to_string(),
+                    _ => {
+                        eprintln!(
+                            "warning: couldn't find the latest {}, depending on any version",
+                            common_id
+                        );
+                        "*".to_string()
+                    }
+                };
+                package.dependencies.push(Dependency {
+                    id: common_id.to_string(),
+                    alias: None,
+                    version_range: range,
+                    additional_data: Default::default(),
+                });
+            }
+        }
+    }
+
+    fs::create_dir_all(dir.join(&package.shared_dir))?;
+    package.write(&dir)?;
+    scaffold::ensure_gitignore(&dir, scaffold::GITIGNORE_ENTRIES)?;
+
+    outln!("Created {}", dir.join(PACKAGE_FILE_NAME).display());
+    for path in written.iter().filter(|p| !p.ends_with(PACKAGE_FILE_NAME)) {
+        outln!("Created {}", path.display());
+    }
+    Ok(())
+}
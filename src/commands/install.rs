@@ -0,0 +1,128 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    build::{self, BuildOptions},
+    cache::{self, remote},
+    commands::Args,
+    data::{
+        config::Config,
+        file_repository::FileRepository,
+        package::{PackageConfig, DEFAULT_ABI},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::RepositoryChain,
+};
+
+/// Registers the current project in the local repository so other projects on this
+/// machine can depend on it without publishing.
+///
+/// The binary is `--binary <path>`, a fresh build with `--build`, or otherwise whatever the
+/// last `qpm build` produced. `--locked` refuses to resolve dependencies again and fails
+/// when qpm.shared.json is out of date, for CI.
+pub fn execute(mut args: Args) -> Result<()> {
+    let binary = args.value("--binary")?.map(PathBuf::from);
+    let run_build = args.flag("--build");
+    let locked = args.flag("--locked");
+    args.finish()?;
+    if binary.is_some() && run_build {
+        return Err(Error::Usage(
+            "--binary and --build can't be combined".into(),
+        ));
+    }
+
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let options = BuildOptions::for_package(&package);
+    if cache::is_headers_only(&package) && (binary.is_some() || run_build) {
+        return Err(Error::Usage(format!(
+            "{} is headers only, it has no binary to install",
+            package.info.id
+        )));
+    }
+
+    let binary = match binary {
+        Some(binary) if !binary.exists() => {
+            return Err(Error::Usage(format!("{} does not exist", binary.display())))
+        }
+        Some(binary) => Some(binary),
+        None if cache::is_headers_only(&package) => None,
+        None if run_build => Some(build::build(&dir, &package, &options, &config)?),
+        None => {
+            let found = cache::local_binary(&dir, &options.abi, &cache::so_name(&package))
+                .ok_or_else(|| {
+                    Error::Usage("no binary found, pass --binary <path> or --build".to_string())
+                })?;
+            Some(found)
+        }
+    };
+    install(
+        &dir,
+        &package,
+        binary.as_deref(),
+        &options.abi,
+        &config,
+        locked,
+    )?;
+    outln!(
+        "Installed {} {} into the local repository",
+        package.info.id,
+        package.info.version
+    );
+    Ok(())
+}
+
+/// Adds the project in `dir` to the local repository with `binary` built for `abi`,
+/// replacing an earlier install of the same version
+pub fn install(
+    dir: &Path,
+    package: &PackageConfig,
+    binary: Option<&Path>,
+    abi: &str,
+    config: &Config,
+    locked: bool,
+) -> Result<()> {
+    let shared = SharedPackageConfig::read_fresh(
+        dir,
+        package,
+        &RepositoryChain::new(config)?,
+        config,
+        locked,
+    )?;
+    let _lock = cache::lock::lock(config)?;
+    let mut repository = FileRepository::read()?;
+    // the repository takes arm64-v8a binaries, others go straight into their ABI's folder
+    let default_abi = abi == DEFAULT_ABI;
+    repository.add_artifact(shared, dir, binary.filter(|_| default_abi), config, true)?;
+    if let Some(binary) = binary {
+        remote::push(config, package, abi, binary)?;
+    }
+    if let Some(binary) = binary.filter(|_| !default_abi) {
+        let info = &package.info;
+        let abi_lib = cache::abi_lib_dir(config, &info.id, &info.version, abi);
+        fs::create_dir_all(&abi_lib)?;
+        fs::copy(binary, abi_lib.join(cache::so_name(package)))?;
+    }
+    // extra binaries are taken from where the build put the main one
+    for extra in cache::extra_binaries(package) {
+        let found = binary
+            .and_then(Path::parent)
+            .map(|folder| folder.join(&extra.name))
+            .filter(|path| path.exists())
+            .or_else(|| cache::local_binary(dir, abi, &extra.name));
+        match found {
+            Some(found) => {
+                let info = &package.info;
+                let abi_lib = cache::abi_lib_dir(config, &info.id, &info.version, abi);
+                fs::create_dir_all(&abi_lib)?;
+                fs::copy(found, abi_lib.join(&extra.name))?;
+            }
+            None => eprintln!("warning: found no {} binary {} to install", abi, extra.name),
+        }
+    }
+    repository.write()
+}
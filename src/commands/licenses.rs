@@ -0,0 +1,76 @@
+use std::{env, fs, path::Path};
+
+use crate::{
+    cache,
+    commands::Args,
+    data::{config::Config, package::PackageConfig, shared_package::SharedPackageConfig},
+    error::Result,
+    repository::RepositoryChain,
+};
+
+/// Files a package's license text is looked for in, in order
+const LICENSE_FILE_NAMES: &[&str] = &["LICENSE", "LICENSE.md", "LICENSE.txt", "COPYING"];
+
+/// Aggregates the licenses of every resolved dependency into a NOTICE file for bundling
+/// with the mod, printed unless `--output` is given
+pub fn execute(mut args: Args) -> Result<()> {
+    let output = args.value("--output")?;
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    let mut notice = format!(
+        "{} {} uses the following packages:\n",
+        package.info.name, package.info.version
+    );
+    for dependency in &shared.restored_dependencies {
+        let dependency_shared = dependency.get_shared_package(&repository, &config)?;
+        let info = &dependency_shared.config.info;
+        notice.push_str(&format!("\n{} {} ({})\n", info.name, info.version, info.id));
+        if !info.authors.is_empty() {
+            notice.push_str(&format!("Authors: {}\n", info.authors.join(", ")));
+        }
+        if let Some(url) = &info.url {
+            notice.push_str(&format!("Source: {}\n", url));
+        }
+        match &info.license {
+            Some(license) => notice.push_str(&format!("License: {}\n", license)),
+            None => {
+                eprintln!("warning: {} {} declares no license", info.id, info.version);
+                notice.push_str("License: unknown\n");
+            }
+        }
+        let source = match dependency.dependency.additional_data.local_dir() {
+            Some(local_dir) => local_dir,
+            None => cache::src_dir(&config, &info.id, &info.version),
+        };
+        if let Some(text) = license_text(&source) {
+            notice.push('\n');
+            notice.push_str(text.trim_end());
+            notice.push('\n');
+        }
+    }
+
+    match output {
+        Some(output) => {
+            fs::write(&output, notice)?;
+            outln!(
+                "Wrote the licenses of {} dependencies to {}",
+                shared.restored_dependencies.len(),
+                output
+            );
+        }
+        None => out!("{}", notice),
+    }
+    Ok(())
+}
+
+/// The license text shipped with a package's source, if any
+fn license_text(source: &Path) -> Option<String> {
+    LICENSE_FILE_NAMES
+        .iter()
+        .find_map(|name| fs::read_to_string(source.join(name)).ok())
+}
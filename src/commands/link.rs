@@ -0,0 +1,109 @@
+use std::{env, path::Path};
+
+use crate::{
+    cache,
+    commands::Args,
+    data::{
+        config::Config, file_repository::FileRepository, package::normalize_id,
+        package::PackageConfig, shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::RepositoryChain,
+};
+
+/// Without an id, registers the current package's working copy as a link. With one, makes
+/// the current project use the working copy linked under that id instead of a version,
+/// until `qpm unlink <id>`. Neither touches qpm.json.
+pub fn execute(mut args: Args) -> Result<()> {
+    let id = args.positional().map(|id| normalize_id(&id));
+    args.finish()?;
+    let dir = env::current_dir()?.canonicalize()?;
+    let package = PackageConfig::read(&dir)?;
+    // nothing else may write the repository between reading and writing it
+    let _lock = cache::lock::lock(&Config::read()?)?;
+    let mut repository = FileRepository::read()?;
+
+    let id = match id {
+        None => {
+            let id = &package.info.id;
+            repository.links.insert(id.clone(), dir.clone());
+            repository.write()?;
+            outln!(
+                "Linked {} to {}, use it in a project with qpm link {}",
+                id,
+                dir.display(),
+                id
+            );
+            return Ok(());
+        }
+        Some(id) => id,
+    };
+    let path = repository.links.get(&id).ok_or_else(|| {
+        Error::Usage(format!(
+            "{} isn't linked, run qpm link in its working copy first",
+            id
+        ))
+    })?;
+    if !package.dependencies.iter().any(|d| d.id == id) {
+        return Err(Error::Usage(format!("{} is not a dependency", id)));
+    }
+    outln!("Using {} from {}", id, path.display());
+    repository.linked.entry(dir.clone()).or_default().insert(id);
+    repository.write()?;
+    restore(&dir, &package)
+}
+
+/// Without an id, removes the current package's link from every project using it. With
+/// one, makes the current project go back to the version its range resolves to.
+pub fn unlink(mut args: Args) -> Result<()> {
+    let id = args.positional().map(|id| normalize_id(&id));
+    args.finish()?;
+    let dir = env::current_dir()?.canonicalize()?;
+    let package = PackageConfig::read(&dir)?;
+    // nothing else may write the repository between reading and writing it
+    let _lock = cache::lock::lock(&Config::read()?)?;
+    let mut repository = FileRepository::read()?;
+
+    match id {
+        None => {
+            let id = &package.info.id;
+            if repository.links.remove(id).is_none() {
+                return Err(Error::Usage(format!("{} isn't linked", id)));
+            }
+            let mut users = 0;
+            for ids in repository.linked.values_mut() {
+                users += ids.remove(id) as usize;
+            }
+            repository.linked.retain(|_, ids| !ids.is_empty());
+            repository.write()?;
+            outln!(
+                "Unlinked {}, {} projects go back to a version on their next restore",
+                id,
+                users
+            );
+            Ok(())
+        }
+        Some(id) => {
+            let removed = repository
+                .linked
+                .get_mut(&dir)
+                .is_some_and(|ids| ids.remove(&id));
+            if !removed {
+                return Err(Error::Usage(format!("this project doesn't link {}", id)));
+            }
+            repository.linked.retain(|_, ids| !ids.is_empty());
+            repository.write()?;
+            outln!("Stopped using the working copy of {}", id);
+            restore(&dir, &package)
+        }
+    }
+}
+
+/// Resolves and restores again, which picks the links up or drops them
+fn restore(dir: &Path, package: &PackageConfig) -> Result<()> {
+    let config = Config::read()?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(dir, package, &repository, &config, false)?;
+    shared.restore(dir, &repository, &config)?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{env, fs, path::Path};
+
+use crate::{
+    commands::Args,
+    data::{
+        config::Config, file_repository::FileRepository, package::PackageConfig,
+        restore_state::RestoreState, shared_package::SharedPackageConfig,
+    },
+    error::Result,
+    repository::{self, RepositoryChain},
+    utils::fs::format_size,
+};
+
+/// Lists the resolved dependencies and which source each of them comes from: a linked
+/// working copy, a local path, the local repository or one of the registries. `--size`
+/// adds what each takes up in the dependencies dir as of the last restore, largest first.
+pub fn execute(mut args: Args) -> Result<()> {
+    let size = args.flag("--size");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    let links = FileRepository::read()?;
+    let links = links.links_of(&dir);
+    let mut rows = Vec::new();
+    for dependency in &shared.restored_dependencies {
+        let source = match links.get(dependency.dependency.id.as_str()) {
+            Some(path) => format!("linked {}", path.display()),
+            None => repository::locate(&repository, dependency)?,
+        };
+        rows.push((
+            format!("{} {}", dependency.dependency.id, dependency.version),
+            source,
+        ));
+    }
+    if size {
+        return print_sizes(&dir, &shared, rows);
+    }
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, source) in rows {
+        outln!("{:width$}  {}", name, source, width = width);
+    }
+    Ok(())
+}
+
+fn print_sizes(
+    dir: &Path,
+    shared: &SharedPackageConfig,
+    rows: Vec<(String, String)>,
+) -> Result<()> {
+    let state = RestoreState::read(dir);
+    let extern_dir = dir.join(&shared.config.dependencies_dir);
+    let mut rows: Vec<_> = shared
+        .restored_dependencies
+        .iter()
+        .zip(rows)
+        .map(|(dependency, (name, source))| {
+            // symlinked binaries take up no space of their own
+            let size = state
+                .dependencies
+                .get(&dependency.dependency.id)
+                .map(|restored| {
+                    restored
+                        .files
+                        .iter()
+                        .filter_map(|file| fs::symlink_metadata(extern_dir.join(file)).ok())
+                        .map(|metadata| metadata.len())
+                        .sum::<u64>()
+                });
+            (name, source, size)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+
+    let width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, source, size) in &rows {
+        let size = size.map_or_else(|| "not restored".to_string(), format_size);
+        outln!("{:width$}  {:>12}  {}", name, size, source, width = width);
+    }
+    outln!(
+        "Total {} in {}",
+        format_size(rows.iter().filter_map(|(_, _, size)| *size).sum()),
+        extern_dir.display()
+    );
+    Ok(())
+}
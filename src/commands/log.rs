@@ -0,0 +1,115 @@
+use std::{env, path::Path};
+
+use crate::{
+    adb, cache,
+    commands::Args,
+    data::{
+        config::Config,
+        package::PackageConfig,
+        shared_package::{SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::Result,
+    ndk,
+    symbols::{self, Frame},
+};
+
+/// Tags native crashes are logged under
+const CRASH_TAGS: &[&str] = &["DEBUG", "libc", "CRASH"];
+
+/// Follows `adb logcat` showing only lines about this mod and its dependencies, `--all`
+/// shows everything. Crash backtraces are symbolized with the restored debug symbols.
+pub fn execute(mut args: Args) -> Result<()> {
+    let all = args.flag("--all");
+    let no_color = args.flag("--no-color");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let color = !no_color && env::var_os("NO_COLOR").is_none();
+    adb::ensure_device()?;
+    follow(&dir, &package, &config, all, color)
+}
+
+/// The names a log line has to mention to be about this project
+fn filter_names(dir: &Path, package: &PackageConfig) -> Vec<String> {
+    let mut names = vec![package.info.id.clone(), cache::so_name(package)];
+    if dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+        if let Ok(shared) = SharedPackageConfig::read(dir) {
+            names.extend(
+                shared
+                    .restored_dependencies
+                    .iter()
+                    .map(|d| d.dependency.id.clone()),
+            );
+        }
+    }
+    names
+}
+
+/// The priority letter of a `threadtime` (`... 1234 1240 E Tag: msg`) or `brief`
+/// (`E/Tag: msg`) logcat line
+fn level(line: &str) -> Option<char> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if let Some(level) = words.get(4).filter(|w| w.len() == 1) {
+        return level.chars().next();
+    }
+    words.iter().take(3).find_map(|word| {
+        let (level, _) = word.split_once('/')?;
+        (level.len() == 1).then(|| level.chars().next()).flatten()
+    })
+}
+
+fn colorize(line: &str, level: Option<char>) -> String {
+    let code = match level {
+        Some('F') | Some('E') => "31",
+        Some('W') => "33",
+        Some('I') => "32",
+        Some('D') | Some('V') => "2",
+        _ => return line.to_string(),
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, line)
+}
+
+/// Streams the filtered log until adb exits
+pub fn follow(
+    dir: &Path,
+    package: &PackageConfig,
+    config: &Config,
+    all: bool,
+    color: bool,
+) -> Result<()> {
+    let names = filter_names(dir, package);
+    let addr2line = ndk::resolve(package, config)
+        .ok()
+        .flatten()
+        .and_then(|ndk| symbols::addr2line_path(&ndk.path));
+
+    adb::logcat(|line| {
+        let frame: Option<Frame> = symbols::parse_backtrace(line).pop();
+        let relevant = all
+            || frame.is_some()
+            || names.iter().any(|name| line.contains(name.as_str()))
+            || CRASH_TAGS
+                .iter()
+                .any(|tag| line.contains(&format!(" {}", tag)));
+        if !relevant {
+            return true;
+        }
+        let mut out = if color {
+            colorize(line, level(line))
+        } else {
+            line.to_string()
+        };
+        if let (Some(frame), Some(tool)) = (&frame, &addr2line) {
+            if let Some(symbols) = symbols::find_symbols(dir, package, &frame.library) {
+                if let Ok(resolved) =
+                    symbols::addr2line(tool, &symbols, std::slice::from_ref(&frame.pc))
+                {
+                    out.push_str(&format!("  -> {}", resolved.join("; ")));
+                }
+            }
+        }
+        outln!("{}", out);
+        true
+    })
+}
@@ -0,0 +1,56 @@
+use std::{env, path::PathBuf};
+
+use crate::{
+    commands::Args,
+    data::{
+        package::{PackageConfig, PACKAGE_FILE_NAME},
+        shared_package::{SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::Result,
+    legacy::{self, ConversionReport},
+    migration, schema,
+};
+
+/// Converts the C# QPM manifests in a project to this tool's format and reports what
+/// couldn't be carried over
+pub fn execute(mut args: Args) -> Result<()> {
+    let dry_run = args.flag("--dry-run");
+    let dir = args
+        .positional()
+        .map(PathBuf::from)
+        .unwrap_or(env::current_dir()?);
+    args.finish()?;
+
+    let path = dir.join(PACKAGE_FILE_NAME);
+    let (value, report) = legacy::convert_package(schema::read_value(&path)?)?;
+    let package: PackageConfig = schema::from_value(&path, value, &schema::package_config())?;
+    print_report(PACKAGE_FILE_NAME, &report);
+    if !dry_run {
+        migration::write_upgraded(&path, &package)?;
+    }
+
+    let shared_path = dir.join(SHARED_PACKAGE_FILE_NAME);
+    if shared_path.exists() {
+        let (value, report) = legacy::convert_shared_package(schema::read_value(&shared_path)?)?;
+        let shared: SharedPackageConfig =
+            schema::from_value(&shared_path, value, &schema::shared_package_config())?;
+        print_report(SHARED_PACKAGE_FILE_NAME, &report);
+        if !dry_run {
+            migration::write_upgraded(&shared_path, &shared)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_report(file: &str, report: &ConversionReport) {
+    if report.changes.is_empty() && report.untranslated.is_empty() {
+        outln!("{}: nothing to translate", file);
+        return;
+    }
+    for change in &report.changes {
+        outln!("{}: {}", file, change);
+    }
+    for untranslated in &report.untranslated {
+        eprintln!("warning: {}: {}", file, untranslated);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache, cancel,
+    commands::Args,
+    data::{
+        config::Config, file_repository::FileRepository, package::PackageConfig,
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    network::{
+        archive::{self, ExtractOptions},
+        source::ArchiveKind,
+    },
+    repository::{Repository, RepositoryChain},
+    utils::{
+        copy::{copy_tree, CopyOptions},
+        fs::remove_dir_if_exists,
+    },
+};
+
+/// Manifest at the root of a mirror archive
+pub const MIRROR_FILE_NAME: &str = "mirror.json";
+/// Folder in a mirror archive holding the cached package versions as `<id>/<version>`
+const PACKAGES_DIR: &str = "packages";
+
+/// What a mirror archive holds
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Mirror {
+    pub packages: Vec<SharedPackageConfig>,
+}
+
+pub fn execute(mut args: Args) -> Result<()> {
+    match args.subcommand().as_deref() {
+        Some("export") => export(args),
+        Some("import") => import(args),
+        _ => Err(Error::Usage(
+            "usage: qpm mirror <export [--output <file>]|import <file> [--force]>".into(),
+        )),
+    }
+}
+
+/// Bundles the sources, binaries and metadata of every resolved dependency into one
+/// archive, for restoring on a machine without network access
+fn export(mut args: Args) -> Result<()> {
+    let output = args.value("--output")?;
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+    let output =
+        dir.join(output.unwrap_or_else(|| {
+            format!("{}-{}-mirror.tar.gz", package.info.id, package.info.version)
+        }));
+
+    let staging = config
+        .tmp_dir()
+        .join(format!("mirror-{}", std::process::id()));
+    remove_dir_if_exists(&staging)?;
+    let _cleanup = cancel::remove_on_cancel(&staging);
+    let result = stage(&staging, &shared, &package, &repository, &config)
+        .and_then(|mirror| archive(&staging, &output).map(|_| mirror));
+    remove_dir_if_exists(&staging)?;
+    let mirror = result?;
+    outln!(
+        "Exported {} packages to {}",
+        mirror.packages.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn stage(
+    staging: &Path,
+    shared: &SharedPackageConfig,
+    package: &PackageConfig,
+    repository: &dyn Repository,
+    config: &Config,
+) -> Result<Mirror> {
+    let mut mirror = Mirror::default();
+    for dependency in &shared.restored_dependencies {
+        let id = &dependency.dependency.id;
+        if dependency.dependency.additional_data.local_path.is_some() {
+            outln!("Skipping {}, it is a local package", id);
+            continue;
+        }
+        let mut dependency_shared = dependency.get_shared_package(repository, config)?;
+        package.apply_sources(&mut dependency_shared);
+        cache::ensure_cached(
+            &dependency_shared,
+            &dependency.dependency.additional_data,
+            &package.abis(),
+            config,
+        )?;
+        copy_tree(
+            &cache::package_dir(config, id, &dependency.version),
+            &staging
+                .join(PACKAGES_DIR)
+                .join(id)
+                .join(&dependency.version),
+            &CopyOptions::new(),
+        )?;
+        mirror.packages.push(dependency_shared);
+    }
+    fs::write(
+        staging.join(MIRROR_FILE_NAME),
+        serde_json::to_vec_pretty(&mirror)?,
+    )?;
+    Ok(mirror)
+}
+
+fn archive(staging: &Path, output: &Path) -> Result<()> {
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(output)
+        .arg("-C")
+        .arg(staging)
+        .arg(".")
+        .status()?;
+    if !status.success() {
+        return Err(Error::Io(std::io::Error::other(format!(
+            "failed to write {}",
+            output.display()
+        ))));
+    }
+    Ok(())
+}
+
+/// Loads a mirror archive into the cache and registers its packages in the local
+/// repository, so they resolve without the registry. Versions already cached are kept
+/// unless `--force` is given.
+fn import(mut args: Args) -> Result<()> {
+    let force = args.flag("--force");
+    let file = args
+        .positional()
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::Usage("usage: qpm mirror import <file> [--force]".into()))?;
+    args.finish()?;
+    let config = Config::read()?;
+
+    let staging = config
+        .tmp_dir()
+        .join(format!("mirror-{}", std::process::id()));
+    remove_dir_if_exists(&staging)?;
+    let _cleanup = cancel::remove_on_cancel(&staging);
+    // the archive holds the mirror file next to the packages, never a single folder
+    let options = ExtractOptions::new().strip_top_level(false);
+    let entries = archive::extract(&file, ArchiveKind::TarGz, &staging, &options)?;
+    outln!("Extracted {} entries from {}", entries, file.display());
+    let result = load(&staging, &config, force);
+    remove_dir_if_exists(&staging)?;
+    let (imported, skipped) = result?;
+    outln!(
+        "Imported {} packages, {} were already cached",
+        imported,
+        skipped
+    );
+    Ok(())
+}
+
+fn load(staging: &Path, config: &Config, force: bool) -> Result<(usize, usize)> {
+    let _lock = cache::lock::lock(config)?;
+    let manifest = staging.join(MIRROR_FILE_NAME);
+    if !manifest.exists() {
+        return Err(Error::Validation(format!(
+            "not a mirror archive, it has no {}",
+            MIRROR_FILE_NAME
+        )));
+    }
+    let mirror: Mirror = serde_json::from_slice(&fs::read(manifest)?)?;
+    let mut repository = FileRepository::read()?;
+    let (mut imported, mut skipped) = (0, 0);
+    for shared in mirror.packages {
+        let info = &shared.config.info;
+        let target = cache::package_dir(config, &info.id, &info.version);
+        if !force && cache::read_shared_package(config, &info.id, &info.version)?.is_some() {
+            skipped += 1;
+        } else {
+            remove_dir_if_exists(&target)?;
+            copy_tree(
+                &staging
+                    .join(PACKAGES_DIR)
+                    .join(&info.id)
+                    .join(&info.version),
+                &target,
+                &CopyOptions::new(),
+            )?;
+            outln!("Imported {} {}", info.id, info.version);
+            imported += 1;
+        }
+        repository.add_artifact_to_map(shared, force);
+    }
+    repository.write()?;
+    Ok((imported, skipped))
+}
@@ -0,0 +1,249 @@
+pub mod build;
+pub mod cache;
+pub mod check_core;
+pub mod collect;
+pub mod config;
+pub mod dependency;
+pub mod deploy;
+pub mod diff;
+pub mod info;
+pub mod init;
+pub mod install;
+pub mod licenses;
+pub mod link;
+pub mod list;
+pub mod log;
+pub mod migrate;
+pub mod mirror;
+pub mod ndk;
+pub mod outdated;
+pub mod package;
+pub mod publish;
+pub mod qmod;
+pub mod resolve;
+pub mod restore;
+pub mod schema;
+pub mod search;
+pub mod self_update;
+pub mod symbols;
+pub mod template;
+pub mod vendor;
+pub mod verify_links;
+pub mod watch;
+pub mod why;
+
+use crate::{
+    data::config as data_config,
+    error::{Error, Result},
+};
+
+pub const USAGE: &str = "usage: qpm <command> [options]
+
+commands:
+    build         build the project with its NDK for --abi and --build-type, --install to add it locally
+    cache gc      evict cached packages by --max-size, --max-age (days) or --keep-last
+    cache verify  check the cache against the local repository, --fix to repair it
+    cache du      show the disk usage of each cached package and version, largest first
+    cache remove-local  unregister a locally installed <id>[@version] and delete its files
+    check-core    warn about dependencies that differ from the core mods of --game-version
+    collect       resolve qpm.json into qpm.shared.json, offering fixes for conflicts unless --no-interactive
+    config        get or set a setting, --local to override cachePath, tmpPath or symlink for this project only
+    dependency    add, remove, pin or unpin dependencies in qpm.json, add --as <name> to alias one
+    deploy        push the built mod to a Quest over adb and restart the game, --log to follow it
+    diff          show dependency changes between two qpm.shared.json, or against --rev (HEAD)
+    info          show a package's metadata, from the local repository or qpackages, --changelog for release notes
+    init [dir]    scaffold a new mod project, optionally from --template <name|git-url>
+    install       register this project in the local repository, with --binary <so> or --build
+    licenses      aggregate the licenses of all dependencies into a NOTICE, --output <file>
+    link [id]     register this package for linking, or use the linked working copy of id
+    list          list the resolved dependencies and their sources, --size for their disk usage
+    log           follow logcat for this mod and its dependencies, symbolizing crashes
+    migrate [dir] convert manifests written by the C# QPM to this format
+    mirror        export the resolved dependencies into an archive, or import one for offline use
+    ndk           list, download or pin (use) Android NDKs
+    outdated      list dependencies with newer versions, flagging pinned ones that fell behind
+    package create  write a qpm.json for <id> [version] from --name, --url, --author, --description, --license, --so-name, --headers-only or --static
+    publish       publish qpm.shared.json to qpackages with --key, --sign to sign it, --json for CI, --workspace for every package below here in dependency order
+    qmod restore  download the .qmod of every dependency into mods/, --push to a Quest
+    qmod build    pack mod.json, the files it lists and dependency assets into <id>.qmod
+    qmod zip      pack the files qpm.json lists under qmod and the built .so into <id>.qmod, --late for a late mod
+    resolve       print the versions qpm.json resolves to, --print-constraints for the ranges behind them
+    restore       download and install the dependencies from qpm.shared.json, --ci github for Actions, --no-hooks to skip the hooks, --build-missing to build unpublished binaries
+    schema        print the json schema of qpm.json (package) or qpm.shared.json (shared)
+    search        find packages on qpackages by id
+    self-update   install the latest qpm release in place of this one, --check to only look
+    symbols       symbolize a tombstone backtrace with the restored debug symbols
+    template      add, remove or list registered project templates
+    unlink [id]   remove this package's link, or stop using the working copy of id
+    vendor        copy all dependencies into vendor/ for offline builds
+    verify-links  check the download links of qpm.json are alive, --tree for every dependency's
+    watch         reinstall this package on every change and restore --consumer <dir> projects
+    why <id>      show every dependency chain that pulls a package in, with the ranges involved
+
+global options:
+    --timeout <s>           seconds a registry request may take
+    --connect-timeout <s>   seconds to wait for a connection
+    --download-timeout <s>  seconds a single download may take
+    --limit-rate <rate>     cap download speed, like 500K or 2M bytes per second
+    --log-file <path>       write the debug log here instead of the config dir's logs folder
+
+exit codes:
+    0  success
+    1  any other failure
+    2  invalid usage
+    3  resolution conflict
+    4  network failure
+    5  cache corruption
+    6  validation error
+    7  publish conflict
+  130  cancelled with Ctrl-C";
+
+/// A small cursor over the command line, commands pull out the flags they
+/// understand and `finish` rejects anything left over
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    pub fn new(args: Vec<String>) -> Args {
+        Args { args }
+    }
+
+    /// The next argument, if it is a subcommand name rather than a flag
+    pub fn subcommand(&mut self) -> Option<String> {
+        if self.args.first().is_some_and(|a| !a.starts_with('-')) {
+            Some(self.args.remove(0))
+        } else {
+            None
+        }
+    }
+
+    pub fn flag(&mut self, name: &str) -> bool {
+        match self.args.iter().position(|a| a == name) {
+            Some(i) => {
+                self.args.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes `--name value` or `--name=value`
+    pub fn value(&mut self, name: &str) -> Result<Option<String>> {
+        let prefix = format!("{}=", name);
+        for i in 0..self.args.len() {
+            if let Some(value) = self.args[i].strip_prefix(&prefix) {
+                let value = value.to_string();
+                self.args.remove(i);
+                return Ok(Some(value));
+            }
+            if self.args[i] == name {
+                if i + 1 >= self.args.len() {
+                    return Err(Error::Usage(format!("{} expects a value", name)));
+                }
+                self.args.remove(i);
+                return Ok(Some(self.args.remove(i)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Takes every occurrence of a repeatable `--name value` option
+    pub fn values(&mut self, name: &str) -> Result<Vec<String>> {
+        let mut values = Vec::new();
+        while let Some(value) = self.value(name)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// The next remaining positional argument, call after taking all flags
+    pub fn positional(&mut self) -> Option<String> {
+        let i = self.args.iter().position(|a| !a.starts_with("--"))?;
+        Some(self.args.remove(i))
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self.args.first() {
+            Some(arg) => Err(Error::Usage(format!("unexpected argument '{}'", arg))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Flags every command takes, passed on through the environment `Config::read` honours
+const GLOBAL_FLAGS: &[(&str, &str)] = &[
+    ("--timeout", data_config::TIMEOUT_ENV),
+    ("--connect-timeout", data_config::CONNECT_TIMEOUT_ENV),
+    ("--download-timeout", data_config::DOWNLOAD_TIMEOUT_ENV),
+    ("--limit-rate", data_config::BANDWIDTH_LIMIT_ENV),
+    ("--log-file", crate::logging::LOG_FILE_ENV),
+];
+
+pub fn run(args: Vec<String>) -> Result<()> {
+    let command_line = args.clone();
+    let mut args = Args::new(args);
+    for (flag, variable) in GLOBAL_FLAGS {
+        if let Some(value) = args.value(flag)? {
+            std::env::set_var(variable, value);
+        }
+    }
+    // a broken config or qpm.json is reported by the command itself
+    let logging = data_config::Config::read()
+        .map(|c| c.logging)
+        .unwrap_or_default();
+    crate::logging::init(&command_line, &logging);
+    let command = args.subcommand();
+    let result = match command.as_deref() {
+        Some("build") => build::execute(args),
+        Some("cache") => cache::execute(args),
+        Some("check-core") => check_core::execute(args),
+        Some("collect") => collect::execute(args),
+        Some("config") => config::execute(args),
+        Some("dependency") => dependency::execute(args),
+        Some("deploy") => deploy::execute(args),
+        Some("diff") => diff::execute(args),
+        Some("info") => info::execute(args),
+        Some("init") => init::execute(args),
+        Some("install") => install::execute(args),
+        Some("licenses") => licenses::execute(args),
+        Some("link") => link::execute(args),
+        Some("list") => list::execute(args),
+        Some("log") => log::execute(args),
+        Some("migrate") => migrate::execute(args),
+        Some("mirror") => mirror::execute(args),
+        Some("ndk") => ndk::execute(args),
+        Some("outdated") => outdated::execute(args),
+        Some("package") => package::execute(args),
+        Some("publish") => publish::execute(args),
+        Some("qmod") => qmod::execute(args),
+        Some("resolve") => resolve::execute(args),
+        Some("restore") => restore::execute(args),
+        Some("schema") => schema::execute(args),
+        Some("search") => search::execute(args),
+        Some("self-update") => self_update::execute(args),
+        Some("symbols") => symbols::execute(args),
+        Some("template") => template::execute(args),
+        Some("unlink") => link::unlink(args),
+        Some("vendor") => vendor::execute(args),
+        Some("verify-links") => verify_links::execute(args),
+        Some("watch") => watch::execute(args),
+        Some("why") => why::execute(args),
+        Some("help") | None => {
+            outln!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(Error::Usage(format!("unknown command '{}'", other))),
+    }
+    .and_then(|()| crate::utils::output::check());
+    if let Err(e) = &result {
+        crate::logging::debug("run", format!("failed: {}", e));
+    }
+    crate::self_update::clean_up();
+    if command.as_deref() != Some("self-update") {
+        if let Ok(config) = data_config::Config::read() {
+            crate::self_update::notify(&config);
+        }
+    }
+    result
+}
@@ -0,0 +1,64 @@
+use std::env;
+
+use crate::{
+    build_files,
+    commands::Args,
+    data::{config::Config, package::PackageConfig},
+    error::{Error, Result},
+    ndk,
+};
+
+pub fn execute(mut args: Args) -> Result<()> {
+    let config = Config::read()?;
+    match args.subcommand().as_deref() {
+        Some("list") | None => {
+            args.finish()?;
+            let installed = ndk::installed(&config);
+            if installed.is_empty() {
+                outln!("No NDKs found, install one with qpm ndk download <release>");
+            }
+            for found in installed {
+                let managed = if found.managed { " (managed)" } else { "" };
+                outln!("{}{}: {}", found.revision, managed, found.path.display());
+            }
+        }
+        Some("download") => {
+            let release = args.positional().ok_or_else(|| {
+                Error::Usage("usage: qpm ndk download <release, like r25c>".into())
+            })?;
+            args.finish()?;
+            outln!("Downloading {}", ndk::download_url(&release));
+            let installed = ndk::download(&release, &config)?;
+            outln!(
+                "Installed NDK {} to {}",
+                installed.revision,
+                installed.path.display()
+            );
+        }
+        Some("use") => {
+            let pin = args
+                .positional()
+                .ok_or_else(|| Error::Usage("usage: qpm ndk use <revision>".into()))?;
+            args.finish()?;
+            let dir = env::current_dir()?;
+            let mut package = PackageConfig::read(&dir)?;
+            let found = ndk::installed(&config)
+                .into_iter()
+                .find(|found| ndk::matches(&pin, &found.revision))
+                .ok_or_else(|| {
+                    Error::Resolution(format!(
+                        "NDK {} is not installed, see qpm ndk download",
+                        pin
+                    ))
+                })?;
+            package.ndk = Some(pin.trim_start_matches('r').to_string());
+            package.write(&dir)?;
+            build_files::write_ndk_path(&dir, Some(&found.path))?;
+            outln!("Pinned NDK {}, currently {}", pin, found.path.display());
+        }
+        Some(other) => {
+            return Err(Error::Usage(format!("unknown ndk command '{}'", other)));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::env;
+
+use crate::{
+    commands::Args,
+    data::{config::Config, package::PackageConfig, shared_package::SharedPackageConfig},
+    error::Result,
+    repository::{Repository, RepositoryChain},
+    version::{Version, VersionReq},
+};
+
+/// Lists the project's dependencies that have newer versions: the newest their range
+/// allows and the newest there is. Pinned dependencies never move on their own, so they
+/// are flagged whenever they fall behind.
+pub fn execute(args: Args) -> Result<()> {
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    let mut rows = vec![[
+        "id".to_string(),
+        "current".to_string(),
+        "wanted".to_string(),
+        "latest".to_string(),
+        String::new(),
+    ]];
+    for dependency in &package.dependencies {
+        // a local checkout is whatever version it is
+        if dependency.additional_data.local_path.is_some() {
+            continue;
+        }
+        let current = match shared
+            .restored_dependencies
+            .iter()
+            .find(|restored| restored.dependency.id == dependency.id)
+        {
+            Some(restored) => Version::parse(&restored.version)?,
+            None => continue,
+        };
+        let range = match &dependency.additional_data.unpinned_range {
+            Some(range) => VersionReq::parse(range)?,
+            None => dependency.version_req()?,
+        };
+        let wanted = repository.get_latest_matching(&dependency.id, &range)?;
+        let latest = repository.get_latest_matching(&dependency.id, &VersionReq::STAR)?;
+        let behind = |newest: &Option<Version>| newest.as_ref().is_some_and(|v| *v > current);
+        if !behind(&wanted) && !behind(&latest) {
+            continue;
+        }
+        let note = if dependency.is_pinned() {
+            format!("pinned, {} before the pin", range)
+        } else {
+            String::new()
+        };
+        let show = |version: Option<Version>| version.map_or("-".to_string(), |v| v.to_string());
+        rows.push([
+            dependency.id.clone(),
+            current.to_string(),
+            show(wanted),
+            show(latest),
+            note,
+        ]);
+    }
+
+    if rows.len() == 1 {
+        outln!("All dependencies are up to date");
+        return Ok(());
+    }
+    let widths: Vec<usize> = (0..4)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    for row in rows {
+        let line = format!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+        outln!("{}", line.trim_end());
+    }
+    Ok(())
+}
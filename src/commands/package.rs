@@ -0,0 +1,107 @@
+use std::env;
+
+use crate::{
+    commands::Args,
+    data::package::{
+        normalize_id, so_name_problem, validate_id, PackageConfig, PackageInfo, PACKAGE_FILE_NAME,
+    },
+    error::{Error, Result},
+    migration,
+    version::Version,
+};
+
+const USAGE: &str = "usage: qpm package create <id> [version] [--name <name>] [--url <url>] [--author <name>]... [--description <text>] [--license <spdx>] [--so-name <lib.so>] [--headers-only] [--static] [--force]";
+
+pub fn execute(mut args: Args) -> Result<()> {
+    match args.subcommand().as_deref() {
+        Some("create") => create(args),
+        _ => Err(Error::Usage(USAGE.into())),
+    }
+}
+
+/// Writes a qpm.json for a library from the flags alone, without scaffolding a project
+/// like `init` does
+fn create(mut args: Args) -> Result<()> {
+    let force = args.flag("--force");
+    let name = args.value("--name")?;
+    let url = args.value("--url")?;
+    let authors = args.values("--author")?;
+    let description = args.value("--description")?;
+    let license = args.value("--license")?;
+    let so_name = args.value("--so-name")?;
+    let headers_only = args.flag("--headers-only");
+    let is_static = args.flag("--static");
+    let id = args
+        .positional()
+        .ok_or_else(|| Error::Usage(USAGE.into()))?;
+    let version = args.positional().unwrap_or_else(|| "0.1.0".to_string());
+    args.finish()?;
+
+    // the name keeps the casing the id was typed with
+    let name = name.unwrap_or_else(|| id.trim().to_string());
+    let id = normalize_id(&id);
+    validate_id(&id)?;
+    Version::parse(&version)?;
+    if let Some(url) = &url {
+        if !["http://", "https://", "git@"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            return Err(Error::Validation(format!(
+                "{} is not an http(s) or git@ url",
+                url
+            )));
+        }
+    }
+    if headers_only && is_static {
+        return Err(Error::Usage(
+            "--headers-only and --static exclude each other".into(),
+        ));
+    }
+    if let Some(so_name) = &so_name {
+        if headers_only {
+            return Err(Error::Usage(
+                "a headers only package has no binary to name with --so-name".into(),
+            ));
+        }
+        if let Some(problem) = so_name_problem(so_name, is_static) {
+            return Err(Error::Validation(problem));
+        }
+    }
+
+    let dir = env::current_dir()?;
+    let path = dir.join(PACKAGE_FILE_NAME);
+    if path.exists() && !force {
+        return Err(Error::Usage(format!(
+            "{} already exists in {}, pass --force to overwrite it",
+            PACKAGE_FILE_NAME,
+            dir.display()
+        )));
+    }
+
+    let mut package = PackageConfig {
+        schema_version: migration::SCHEMA_VERSION,
+        shared_dir: "shared".to_string(),
+        dependencies_dir: "extern".to_string(),
+        info: PackageInfo {
+            name,
+            id,
+            version,
+            url,
+            description,
+            license,
+            authors,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let data = &mut package.info.additional_data;
+    data.headers_only = Some(true).filter(|_| headers_only);
+    data.static_linking = Some(true).filter(|_| is_static);
+    data.override_so_name = so_name;
+    package.write(&dir)?;
+
+    outln!("Created {}", path.display());
+    outln!("{}", serde_json::to_string_pretty(&package.file_value()?)?);
+    Ok(())
+}
@@ -0,0 +1,611 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    cache, cancel,
+    commands::{verify_links, Args},
+    data::{
+        config::Config,
+        dependency::Visibility,
+        package::{so_name_problem, validate_id, PackageConfig, PACKAGE_FILE_NAME},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::{qpackages::QPackages, Repository, RepositoryChain},
+    signing,
+    utils::fs::files_below,
+    version::VersionReq,
+    workspace,
+};
+
+/// Environment variable the publish key is read from when `--key` isn't given
+pub const PUBLISH_KEY_ENV: &str = "QPM_PUBLISH_KEY";
+/// Seconds `--workspace` waits for a published member to show up on the registry, unless
+/// `--wait` says otherwise
+const DEFAULT_WAIT: u64 = 300;
+/// How often the registry is asked whether a published member shows up yet
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What `qpm publish --json` prints, for CI to act on without parsing log text
+#[derive(Serialize, Debug)]
+pub struct PublishResult {
+    /// `published`, `unchanged` when the same contents were published before, `conflict`
+    /// when the version was published with other contents, `error`, or `skipped` for
+    /// workspace members depending on one that failed
+    pub status: &'static str,
+    pub id: Option<String>,
+    pub version: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Publishes the project's qpm.shared.json to the first configured registry, qpackages
+/// unless configured otherwise. `--sign` signs it first with the configured ssh key or
+/// `--signing-key`. Publishing a version again with the same contents succeeds without
+/// doing anything, so CI jobs can be re-run. Every download link is checked first, unless
+/// `--no-verify-links`.
+///
+/// `--workspace` publishes every package below the working directory instead, see
+/// [`publish_workspace`].
+pub fn execute(mut args: Args) -> Result<()> {
+    let key = args.value("--key")?;
+    let sign = args.flag("--sign");
+    let signing_key = args.value("--signing-key")?;
+    let json = args.flag("--json");
+    let no_verify_links = args.flag("--no-verify-links");
+    let workspace = args.flag("--workspace");
+    let wait = match args.value("--wait")? {
+        Some(wait) => wait
+            .parse()
+            .map_err(|_| Error::Usage(format!("invalid --wait '{}', expected seconds", wait)))?,
+        None => DEFAULT_WAIT,
+    };
+    args.finish()?;
+    let options = PublishOptions {
+        key,
+        sign,
+        signing_key,
+        no_verify_links,
+    };
+    if workspace {
+        return publish_workspace(&options, Duration::from_secs(wait), json);
+    }
+    let mut result = PublishResult::new();
+    let published = publish(&env::current_dir()?, &options, &mut result);
+    if let Err(e) = &published {
+        if result.status != "conflict" {
+            result.status = "error";
+        }
+        result.message = Some(e.to_string());
+    }
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&result)?);
+    } else if let Some(message) = result.message.as_ref().filter(|_| published.is_ok()) {
+        outln!("{}", message);
+    }
+    published
+}
+
+/// The flags of `qpm publish` every package is published with
+#[derive(Clone)]
+struct PublishOptions {
+    key: Option<String>,
+    sign: bool,
+    signing_key: Option<String>,
+    no_verify_links: bool,
+}
+
+impl PublishResult {
+    fn new() -> PublishResult {
+        PublishResult {
+            status: "published",
+            id: None,
+            version: None,
+            message: None,
+        }
+    }
+}
+
+fn publish_key(key: Option<String>) -> Result<String> {
+    key.or_else(|| env::var(PUBLISH_KEY_ENV).ok()).ok_or_else(|| {
+        Error::Usage(format!(
+            "usage: qpm publish --key <publish key> [--sign] [--json] [--no-verify-links] [--workspace [--wait <s>]], or set {}",
+            PUBLISH_KEY_ENV
+        ))
+    })
+}
+
+/// Publishes the package in `dir`
+fn publish(dir: &Path, options: &PublishOptions, result: &mut PublishResult) -> Result<()> {
+    let key = publish_key(options.key.clone())?;
+    let config = Config::read_global()?.with_local(dir)?.with_env()?;
+    let package = PackageConfig::read(dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let mut shared = SharedPackageConfig::read_fresh(dir, &package, &repository, &config, true)?;
+    let info = &shared.config.info;
+    result.id = Some(info.id.clone());
+    result.version = Some(info.version.clone());
+    validate_id(&info.id)?;
+    if info.url.is_none() {
+        return Err(Error::Validation(format!(
+            "{} has no url, dependents couldn't download it",
+            info.id
+        )));
+    }
+    let data = &info.additional_data;
+    if cache::is_headers_only(&shared.config) {
+        let binary_links = [
+            ("soLink", data.so_link.is_some()),
+            ("abiSoLinks", data.abi_so_links.is_some()),
+            ("staticLink", data.static_link.is_some()),
+            ("abiStaticLinks", data.abi_static_links.is_some()),
+            ("debugSoLink", data.debug_so_link.is_some()),
+            ("extraBinaries", data.extra_binaries.is_some()),
+        ];
+        if let Some((name, _)) = binary_links.iter().find(|(_, set)| *set) {
+            return Err(Error::Validation(format!(
+                "{} is headersOnly but declares {}, remove one of them",
+                info.id, name
+            )));
+        }
+    } else if cache::is_static(&shared.config) {
+        if data.static_link.is_none() {
+            return Err(Error::Validation(format!(
+                "{} links statically but has no staticLink",
+                info.id
+            )));
+        }
+    } else if data.so_link.is_none() {
+        return Err(Error::Validation(format!(
+            "{} has no soLink, set one or mark it headersOnly",
+            info.id
+        )));
+    }
+
+    validate_extra_binaries(&shared.config)?;
+
+    let problems = shared_dir_problems(dir, &shared.config)?;
+    if !problems.is_empty() {
+        return Err(Error::Validation(format!(
+            "{} isn't ready to publish:\n{}",
+            shared.config.info.id,
+            problems.join("\n")
+        )));
+    }
+
+    let unrestorable = unpublished_dependencies(&shared.config, &config)?;
+    if !unrestorable.is_empty() {
+        return Err(Error::Validation(format!(
+            "dependents couldn't restore {}, no registry has a version of these dependencies in range:\n{}",
+            shared.config.info.id,
+            unrestorable.join("\n")
+        )));
+    }
+
+    let leaks = private_header_leaks(dir, &shared, &repository, &config)?;
+    if !leaks.is_empty() {
+        return Err(Error::Validation(format!(
+            "the public headers use private dependencies, make them public or move the includes out of {}:\n{}",
+            shared.config.shared_dir,
+            leaks.join("\n")
+        )));
+    }
+
+    let registry = QPackages::primary(&config);
+    if let Some(existing) =
+        registry.get_shared_package(&shared.config.info.id, &shared.config.info.version)?
+    {
+        let info = &shared.config.info;
+        if existing.config != shared.config
+            || existing.restored_dependencies != shared.restored_dependencies
+        {
+            result.status = "conflict";
+            return Err(Error::Conflict(format!(
+                "{} {} is already published with different contents, bump the version",
+                info.id, info.version
+            )));
+        }
+        result.status = "unchanged";
+        result.message = Some(format!(
+            "{} {} is already published with the same contents, nothing to do",
+            info.id, info.version
+        ));
+        return Ok(());
+    }
+
+    // dead release urls are the most common reason restores of a package fail
+    if !options.no_verify_links {
+        let dead = verify_links::dead_links(&shared.config, &config)?;
+        if !dead.is_empty() {
+            let lines: Vec<String> = dead
+                .iter()
+                .map(|link| format!("{}: {} ({})", link.field, link.url, link.problem))
+                .collect();
+            return Err(Error::Validation(format!(
+                "dependents couldn't download {}, these links are dead:\n{}",
+                shared.config.info.id,
+                lines.join("\n")
+            )));
+        }
+    }
+
+    if options.sign {
+        let signing_key = options
+            .signing_key
+            .clone()
+            .or_else(|| config.signing.key.clone())
+            .ok_or_else(|| {
+                Error::Usage(
+                    "no signing key, pass --signing-key or qpm config set signing.key <path>"
+                        .into(),
+                )
+            })?;
+        shared.signature = Some(signing::sign(&shared, &PathBuf::from(signing_key))?);
+    }
+    registry.publish(&shared, &key)?;
+    result.message = Some(format!(
+        "Published {} {} to {}{}",
+        shared.config.info.id,
+        shared.config.info.version,
+        registry.name(),
+        if options.sign { ", signed" } else { "" }
+    ));
+    Ok(())
+}
+
+/// Publishes every package below the working directory, each after the members it depends
+/// on. Once a member is published its dependents wait up to `wait` for the registry to list
+/// it, so they resolve it. When a member fails, only the members depending on it are
+/// skipped and the rest still go out; running it again picks up where it stopped, as the
+/// members already out are `unchanged`.
+fn publish_workspace(options: &PublishOptions, wait: Duration, json: bool) -> Result<()> {
+    let options = PublishOptions {
+        key: Some(publish_key(options.key.clone())?),
+        ..options.clone()
+    };
+    let members = workspace::dependency_order(workspace::members(&env::current_dir()?)?)?;
+    if members.is_empty() {
+        return Err(Error::Usage(format!(
+            "no {} at or below the working directory",
+            PACKAGE_FILE_NAME
+        )));
+    }
+    let ids: BTreeSet<String> = members.iter().map(|m| m.package.info.id.clone()).collect();
+    let config = Config::read()?;
+
+    let mut results = Vec::new();
+    let mut failed: BTreeSet<String> = BTreeSet::new();
+    let mut first_error = None;
+    for (i, member) in members.iter().enumerate() {
+        cancel::check()?;
+        let info = &member.package.info;
+        if !json {
+            outln!("[{}/{}] {} {}", i + 1, members.len(), info.id, info.version);
+        }
+        let mut result = PublishResult {
+            id: Some(info.id.clone()),
+            version: Some(info.version.clone()),
+            ..PublishResult::new()
+        };
+        let blocked: Vec<&str> = member
+            .workspace_dependencies(&ids)
+            .into_iter()
+            .filter(|id| failed.contains(*id))
+            .collect();
+        let mut published = if blocked.is_empty() {
+            publish(&member.dir, &options, &mut result)
+        } else {
+            result.status = "skipped";
+            Err(Error::Validation(format!(
+                "skipped {}, {} didn't publish",
+                info.id,
+                blocked.join(" and ")
+            )))
+        };
+        match &published {
+            Ok(()) => {
+                if let (Some(message), false) = (&result.message, json) {
+                    outln!("  {}", message);
+                }
+            }
+            Err(e) => {
+                if !matches!(result.status, "conflict" | "skipped") {
+                    result.status = "error";
+                }
+                result.message = Some(e.to_string());
+            }
+        }
+        // dependents resolve against the registry, which may lag behind the publish
+        let has_dependents = members[i + 1..]
+            .iter()
+            .any(|m| m.workspace_dependencies(&ids).contains(&info.id.as_str()));
+        if published.is_ok() && result.status == "published" && has_dependents {
+            // the version is out either way, only its dependents have to wait for another run
+            published =
+                wait_until_listed(&QPackages::primary(&config), &info.id, &info.version, wait);
+        }
+        if let Err(e) = published {
+            cancel::check()?;
+            if !json {
+                eprintln!("  {}", e);
+            }
+            failed.insert(info.id.clone());
+            first_error.get_or_insert(e);
+        }
+        results.push(result);
+    }
+
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&results)?);
+    }
+    match first_error {
+        Some(e) => {
+            if !json {
+                eprintln!(
+                    "{} of {} packages didn't publish, run publish --workspace again once fixed to continue",
+                    failed.len(),
+                    members.len()
+                );
+            }
+            Err(e)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Waits until the versions `registry` lists for `id` include `version`
+fn wait_until_listed(registry: &QPackages, id: &str, version: &str, wait: Duration) -> Result<()> {
+    let req = VersionReq::parse(&format!("={}", version))?;
+    let started = Instant::now();
+    loop {
+        if registry.get_latest_matching(id, &req)?.is_some() {
+            return Ok(());
+        }
+        if started.elapsed() >= wait {
+            return Err(Error::Network(format!(
+                "{} {} was published but {} didn't list it within {} seconds",
+                id,
+                version,
+                registry.name(),
+                wait.as_secs()
+            )));
+        }
+        eprintln!(
+            "  waiting for {} to list {} {}",
+            registry.name(),
+            id,
+            version
+        );
+        thread::sleep(POLL_INTERVAL);
+        cancel::check()?;
+    }
+}
+
+/// The public dependencies of `package` no registry has a version of in their range, which
+/// the package's dependents would fail to restore. Private dependencies and local paths
+/// aren't restored by dependents; the local repository doesn't count since only this
+/// machine has it.
+fn unpublished_dependencies(package: &PackageConfig, config: &Config) -> Result<Vec<String>> {
+    let registries = QPackages::all(config);
+    let mut problems = Vec::new();
+    for dependency in &package.dependencies {
+        let data = &dependency.additional_data;
+        if dependency.visibility() == Visibility::Private || data.local_path.is_some() {
+            continue;
+        }
+        let candidates: Vec<_> = registries
+            .iter()
+            .filter(|registry| {
+                data.source
+                    .as_ref()
+                    .is_none_or(|source| *source == registry.name())
+            })
+            .collect();
+        if candidates.is_empty() {
+            problems.push(format!(
+                "  {} is pinned to {}, which isn't a registry",
+                dependency.id,
+                data.source.as_deref().unwrap_or_default()
+            ));
+            continue;
+        }
+        let req = dependency.version_req()?;
+        let names: Vec<_> = candidates.iter().map(|registry| registry.name()).collect();
+        let mut problem = Some(format!(
+            "  {} {} on {}, mark it private if dependents don't need it",
+            dependency.id,
+            dependency.version_range,
+            names.join(", ")
+        ));
+        for registry in &candidates {
+            match registry.get_latest_matching(&dependency.id, &req) {
+                Ok(Some(_)) => {
+                    problem = None;
+                    break;
+                }
+                // every version in range was yanked, which says more than none matching
+                Ok(None) if registry.is_yanked_only(&dependency.id, &req)? => {
+                    problem = Some(format!(
+                        "  every version of {} matching {} was yanked",
+                        dependency.id, req
+                    ))
+                }
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        problems.extend(problem);
+    }
+    Ok(problems)
+}
+
+/// Extra binaries need a file name of their own that is a library, and somewhere to be
+/// downloaded from
+fn validate_extra_binaries(package: &PackageConfig) -> Result<()> {
+    let id = &package.info.id;
+    let mut names = vec![cache::so_name(package)];
+    for binary in cache::extra_binaries(package) {
+        let name = &binary.name;
+        let problem = if name.contains(['/', '\\']) || !name.starts_with("lib") {
+            Some("must be a file name starting with lib")
+        } else if !(name.ends_with(".so") || name.ends_with(".a")) {
+            Some("must end in .so or .a")
+        } else if names.contains(name) {
+            Some("is the name of another of its binaries")
+        } else if binary.so_link.is_none() && binary.abi_so_links.is_none() {
+            Some("has no soLink or abiSoLinks")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(Error::Validation(format!(
+                "extra binary {:?} of {} {}",
+                name, id, problem
+            )));
+        }
+        names.push(name.clone());
+    }
+    Ok(())
+}
+
+/// Extensions of the files that count as headers in a shared folder
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx", "inl"];
+
+/// What would make the published package unusable: a missing shared folder or one without
+/// headers, headers including absolute paths that only exist on this machine, and an
+/// `overrideSoName` that doesn't follow the `lib<name>.so` or `lib<name>.a` convention
+fn shared_dir_problems(dir: &Path, package: &PackageConfig) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    if let Some(so_name) = &package.info.additional_data.override_so_name {
+        problems.extend(so_name_problem(so_name, cache::is_static(package)));
+    }
+
+    let shared_dir = dir.join(&package.shared_dir);
+    if !shared_dir.is_dir() {
+        problems.push(format!(
+            "the shared folder {} doesn't exist",
+            package.shared_dir
+        ));
+        return Ok(problems);
+    }
+    let files = files_below(&shared_dir)?;
+    let is_header = |file: &Path| {
+        file.extension()
+            .is_some_and(|extension| HEADER_EXTENSIONS.iter().any(|h| extension == *h))
+    };
+    if !files.iter().any(|file| is_header(file)) {
+        problems.push(format!(
+            "the shared folder {} contains no headers",
+            package.shared_dir
+        ));
+    }
+    for file in files.iter().filter(|file| is_header(file)) {
+        let text = match fs::read_to_string(shared_dir.join(file)) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        for include in includes(&text) {
+            let bytes = include.as_bytes();
+            let drive = bytes.len() > 1 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+            if include.starts_with(['/', '\\']) || drive {
+                problems.push(format!(
+                    "{} includes the absolute path {}",
+                    file.display(),
+                    include
+                ));
+            }
+        }
+    }
+    Ok(problems)
+}
+
+/// The paths `#include` directives in `text` name
+fn includes(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().filter_map(|line| {
+        let directive = line.trim_start().strip_prefix('#')?.trim_start();
+        let target = directive.strip_prefix("include")?.trim();
+        let (open, close) = match target.chars().next()? {
+            '"' => ('"', '"'),
+            '<' => ('<', '>'),
+            _ => return None,
+        };
+        target.strip_prefix(open)?.split(close).next()
+    })
+}
+
+/// Includes of private dependencies' headers in the package's shared folder, which its
+/// dependents wouldn't be able to compile as they never get those headers
+fn private_header_leaks(
+    dir: &Path,
+    shared: &SharedPackageConfig,
+    repository: &dyn Repository,
+    config: &Config,
+) -> Result<Vec<String>> {
+    // header path -> id of the private dependency shipping it
+    let mut private_headers = BTreeMap::new();
+    let private = shared
+        .config
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.visibility() == Visibility::Private);
+    for dependency in private {
+        let restored = match shared
+            .restored_dependencies
+            .iter()
+            .find(|restored| restored.dependency.id == dependency.id)
+        {
+            Some(restored) => restored,
+            None => continue,
+        };
+        let dependency_shared = restored.get_shared_package(repository, config)?;
+        let src = match restored.dependency.additional_data.local_dir() {
+            Some(local_dir) => local_dir,
+            None => cache::src_dir(config, &dependency.id, &restored.version),
+        };
+        let headers = src.join(&dependency_shared.config.shared_dir);
+        if !headers.exists() {
+            eprintln!(
+                "warning: couldn't check the includes of private dependency {}, restore first",
+                dependency.id
+            );
+            continue;
+        }
+        for header in files_below(&headers)? {
+            let header = header.to_string_lossy().replace('\\', "/");
+            private_headers.insert(header, dependency.id.clone());
+        }
+    }
+    if private_headers.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let own_headers = dir.join(&shared.config.shared_dir);
+    if !own_headers.exists() {
+        return Ok(Vec::new());
+    }
+    let mut leaks = Vec::new();
+    for header in files_below(&own_headers)? {
+        // not every file in there has to be text
+        let text = match fs::read_to_string(own_headers.join(&header)) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        for include in includes(&text) {
+            if let Some(id) = private_headers.get(include) {
+                leaks.push(format!(
+                    "{} includes {} of {}",
+                    header.display(),
+                    include,
+                    id
+                ));
+            }
+        }
+    }
+    Ok(leaks)
+}
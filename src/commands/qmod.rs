@@ -0,0 +1,554 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    adb, build, cache, cancel,
+    commands::Args,
+    data::{
+        config::Config,
+        package::PackageConfig,
+        shared_package::{SharedDependency, SharedPackageConfig},
+    },
+    error::{Error, Result},
+    network, qmod,
+    repository::RepositoryChain,
+    utils::{
+        copy::{copy_tree, CopyOptions},
+        fs::{escapes_root, files_below, remove_dir_if_exists},
+    },
+};
+
+/// Folder `qpm qmod restore` puts dependency mods in
+pub const MODS_DIR: &str = "mods";
+/// Where pushed mods land on the Quest for a mod manager to import
+pub const DEFAULT_REMOTE_DIR: &str = "/sdcard/Download";
+
+/// The manifest of a mod, which `qpm qmod build` packs with the files it lists
+pub const MOD_FILE_NAME: &str = "mod.json";
+/// The Android package of Beat Saber, which mods are made for
+pub const GAME_PACKAGE_ID: &str = "com.beatgames.beatsaber";
+
+pub fn execute(mut args: Args) -> Result<()> {
+    match args.subcommand().as_deref() {
+        Some("restore") => restore(args),
+        Some("build") => build(args),
+        Some("zip") => zip(args),
+        _ => Err(Error::Usage(
+            "usage: qpm qmod <restore [--push]|build|zip [--so <path>] [--late]>".into(),
+        )),
+    }
+}
+
+/// Downloads the .qmod of every dependency with a `modLink` into `mods/`, `--push` also
+/// copies them to a connected Quest (into `--remote-dir`)
+fn restore(mut args: Args) -> Result<()> {
+    let push = args.flag("--push");
+    let remote_dir = args
+        .value("--remote-dir")?
+        .unwrap_or_else(|| DEFAULT_REMOTE_DIR.to_string());
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+    if push {
+        adb::ensure_device()?;
+    }
+
+    let mods = dir.join(MODS_DIR);
+    fs::create_dir_all(&mods)?;
+    let mut restored = Vec::new();
+    for dependency in &shared.restored_dependencies {
+        let mut dependency_shared = dependency.get_shared_package(&repository, &config)?;
+        package.apply_sources(&mut dependency_shared);
+        let info = &dependency_shared.config.info;
+        let mod_link = match &info.additional_data.mod_link {
+            Some(mod_link) => mod_link,
+            None => continue,
+        };
+        let cached =
+            cache::package_dir(&config, &info.id, &info.version).join(format!("{}.qmod", info.id));
+        if !cached.exists() {
+            outln!("Downloading the mod of {} {}", info.id, info.version);
+            network::download_file(mod_link, &cached, &config)?;
+        }
+        let file_name = format!("{}_{}.qmod", info.id, info.version);
+        let target: PathBuf = mods.join(&file_name);
+        fs::copy(&cached, &target)?;
+        if push {
+            adb::push(&target, &format!("{}/{}", remote_dir, file_name))?;
+        }
+        restored.push(file_name);
+    }
+
+    for name in &restored {
+        outln!("{}", name);
+    }
+    outln!(
+        "{} {} dependency mods",
+        if push { "Pushed" } else { "Restored" },
+        restored.len()
+    );
+    Ok(())
+}
+
+/// A file of an asset on its way into the .qmod
+struct AssetFile {
+    /// The package declaring it
+    id: String,
+    source: PathBuf,
+    /// Path inside the .qmod
+    name: String,
+    destination: String,
+}
+
+/// Packs `mod.json` and the files it lists into `<id>.qmod`, adding the assets of the
+/// project and its dependencies as file copies. Two assets for the same destination on
+/// the device are an error.
+fn build(args: Args) -> Result<()> {
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+    let manifest_path = dir.join(MOD_FILE_NAME);
+    if !manifest_path.exists() {
+        return Err(Error::Usage(format!(
+            "{} has no {}, the manifest of the mod",
+            dir.display(),
+            MOD_FILE_NAME
+        )));
+    }
+    let mut manifest: Value = serde_json::from_slice(&fs::read(&manifest_path)?)?;
+    let schema_version = qmod::schema_version(&manifest, package.qmod_schema_version.as_deref())?;
+    manifest["_QPVersion"] = Value::String(schema_version);
+    qmod::validate(&manifest)?;
+
+    let dependencies = dependency_packages(&package, &shared, &repository, &config)?;
+    let assets = all_assets(&dir, &package, &dependencies, &config)?;
+    let abi = build::BuildOptions::for_package(&package).abi;
+    let extern_dir = dir.join(&package.dependencies_dir);
+    // where the files mod.json lists are looked for, in order
+    let mut search = vec![dir.clone(), dir.join("build")];
+    for (dependency, _) in &dependencies {
+        search.push(
+            extern_dir.join(
+                package
+                    .extern_layout
+                    .binary_dir(package.dependency_name(&dependency.dependency.id), &abi),
+            ),
+        );
+    }
+    let files = find_listed(&manifest, &search)?;
+    let qmod = pack(&mut manifest, &files, &assets, &dir, &package, &config)?;
+    outln!(
+        "Built {} with {} dependency assets",
+        qmod.display(),
+        assets.iter().filter(|a| a.id != package.info.id).count()
+    );
+    Ok(())
+}
+
+/// Packs the files qpm.json lists under `qmod` into `<id>.qmod`, with the metadata of
+/// mod.json or, without one, a manifest made from the package. The built binary is added
+/// as a mod file, or a late one with `--late`, unless the list has it already. `--so`
+/// picks another binary.
+fn zip(mut args: Args) -> Result<()> {
+    let so = args.value("--so")?;
+    let late = args.flag("--late");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+    let dependencies = dependency_packages(&package, &shared, &repository, &config)?;
+
+    let manifest_path = dir.join(MOD_FILE_NAME);
+    let mut manifest = if manifest_path.exists() {
+        serde_json::from_slice(&fs::read(&manifest_path)?)?
+    } else {
+        generated_manifest(&package, &dependencies)
+    };
+
+    let declared = &package.qmod;
+    let mut mod_files = declared.mod_files.clone();
+    let mut late_mod_files = declared.late_mod_files.clone();
+    let binary = match so {
+        Some(so) => Some(so),
+        None if cache::is_static(&package) || cache::is_headers_only(&package) => None,
+        None => {
+            let abi = build::BuildOptions::for_package(&package).abi;
+            let so_name = cache::so_name(&package);
+            let found = cache::local_binary(&dir, &abi, &so_name).ok_or_else(|| {
+                Error::Usage(format!(
+                    "{} isn't built yet, run qpm build or pass --so",
+                    so_name
+                ))
+            })?;
+            Some(
+                found
+                    .strip_prefix(&dir)
+                    .unwrap_or(&found)
+                    .display()
+                    .to_string(),
+            )
+        }
+    };
+    if let Some(binary) = binary {
+        let name = file_name(&binary);
+        let listed = mod_files
+            .iter()
+            .chain(&late_mod_files)
+            .any(|file| file_name(file) == name);
+        if !listed {
+            if late {
+                late_mod_files.push(binary);
+            } else {
+                mod_files.push(binary);
+            }
+        }
+    }
+    let cover_image = declared
+        .cover_image
+        .clone()
+        .or_else(|| manifest["coverImage"].as_str().map(str::to_string));
+
+    // every declared file with its name in the .qmod
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    let mut missing = Vec::new();
+    let mut declare = |path: &String| -> String {
+        let source = dir.join(path);
+        if escapes_root(path) || !source.is_file() {
+            missing.push(path.clone());
+        }
+        let name = file_name(path);
+        files.push((source, name.clone()));
+        name
+    };
+    let names = |paths: &[String], declare: &mut dyn FnMut(&String) -> String| {
+        Value::Array(paths.iter().map(|p| Value::String(declare(p))).collect())
+    };
+    manifest["modFiles"] = names(&mod_files, &mut declare);
+    manifest["lateModFiles"] = names(&late_mod_files, &mut declare);
+    manifest["libraryFiles"] = names(&declared.library_files, &mut declare);
+    let copies = declared
+        .file_copies
+        .iter()
+        .map(|copy| json!({ "name": declare(&copy.path), "destination": copy.destination }))
+        .collect();
+    manifest["fileCopies"] = Value::Array(copies);
+    if let Some(cover_image) = &cover_image {
+        manifest["coverImage"] = Value::String(declare(cover_image));
+    }
+    if late_mod_files.is_empty() {
+        // older schema versions don't know the field
+        if let Some(object) = manifest.as_object_mut() {
+            object.remove("lateModFiles");
+        }
+    }
+    if !missing.is_empty() {
+        return Err(Error::Validation(format!(
+            "qpm.json lists files for the .qmod that don't exist: {}",
+            missing.join(", ")
+        )));
+    }
+    let mut sources: BTreeMap<&str, &Path> = BTreeMap::new();
+    for (source, name) in &files {
+        if let Some(other) = sources
+            .insert(name, source)
+            .filter(|other| *other != source)
+        {
+            return Err(Error::Validation(format!(
+                "{} and {} would both be {} in the .qmod",
+                other.display(),
+                source.display(),
+                name
+            )));
+        }
+    }
+
+    let schema_version = qmod::schema_version(&manifest, package.qmod_schema_version.as_deref())?;
+    manifest["_QPVersion"] = Value::String(schema_version);
+    qmod::validate(&manifest)?;
+    let assets = all_assets(&dir, &package, &dependencies, &config)?;
+    let qmod = pack(&mut manifest, &files, &assets, &dir, &package, &config)?;
+    outln!("Built {} with {} files", qmod.display(), sources.len());
+    Ok(())
+}
+
+/// The last component of a path as listed in qpm.json
+fn file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
+
+/// A manifest for a project without mod.json, taken from its package info. Dependencies
+/// with a `modLink` become mod dependencies the mod manager downloads when missing.
+fn generated_manifest(
+    package: &PackageConfig,
+    dependencies: &[(&SharedDependency, SharedPackageConfig)],
+) -> Value {
+    let info = &package.info;
+    let mut manifest = json!({
+        "name": info.name,
+        "id": info.id,
+        "version": info.version,
+        "packageId": GAME_PACKAGE_ID,
+        "packageVersion": package.target.game_version,
+        "modFiles": [],
+        "libraryFiles": [],
+        "fileCopies": [],
+    });
+    if !info.authors.is_empty() {
+        manifest["author"] = Value::String(info.authors.join(", "));
+    }
+    if let Some(description) = &info.description {
+        manifest["description"] = Value::String(description.clone());
+    }
+    let mods: Vec<Value> = dependencies
+        .iter()
+        .filter_map(|(dependency, shared)| {
+            let mod_link = shared.config.info.additional_data.mod_link.as_ref()?;
+            Some(json!({
+                "id": dependency.dependency.id,
+                "version": format!("^{}", dependency.version),
+                "downloadIfMissing": mod_link,
+            }))
+        })
+        .collect();
+    manifest["dependencies"] = Value::Array(mods);
+    manifest
+}
+
+/// The shared config of every restored dependency, with the project's overrides applied
+fn dependency_packages<'a>(
+    package: &PackageConfig,
+    shared: &'a SharedPackageConfig,
+    repository: &RepositoryChain,
+    config: &Config,
+) -> Result<Vec<(&'a SharedDependency, SharedPackageConfig)>> {
+    let mut packages = Vec::new();
+    for dependency in &shared.restored_dependencies {
+        let mut dependency_shared = dependency.get_shared_package(repository, config)?;
+        package.apply_sources(&mut dependency_shared);
+        packages.push((dependency, dependency_shared));
+    }
+    Ok(packages)
+}
+
+/// The assets of the project and its dependencies
+fn all_assets(
+    dir: &Path,
+    package: &PackageConfig,
+    dependencies: &[(&SharedDependency, SharedPackageConfig)],
+    config: &Config,
+) -> Result<Vec<AssetFile>> {
+    let mut assets = Vec::new();
+    collect_assets(package, dir, &mut assets)?;
+    for (dependency, dependency_shared) in dependencies {
+        let dependency_package = &dependency_shared.config;
+        let info = &dependency_package.info;
+        if dependency_package.assets.is_empty() {
+            continue;
+        }
+        let root = match dependency.dependency.additional_data.local_dir() {
+            Some(local_dir) => local_dir,
+            None => {
+                let assets = cache::assets_dir(config, &info.id, &info.version);
+                if !assets.exists() {
+                    let src = cache::src_dir(config, &info.id, &info.version);
+                    if !src.exists() {
+                        return Err(Error::Usage(format!(
+                            "{} {} isn't cached, run qpm restore",
+                            info.id, info.version
+                        )));
+                    }
+                    cache::store_assets(config, dependency_package, &src)?;
+                }
+                assets
+            }
+        };
+        collect_assets(dependency_package, &root, &mut assets)?;
+    }
+    Ok(assets)
+}
+
+/// Checks no two file copies end up at the same place on the device, then zips the
+/// files, assets and `manifest` into `<id>.qmod` in `dir`
+fn pack(
+    manifest: &mut Value,
+    files: &[(PathBuf, String)],
+    assets: &[AssetFile],
+    dir: &Path,
+    package: &PackageConfig,
+    config: &Config,
+) -> Result<PathBuf> {
+    // destinations already taken by the mod's own file copies
+    let mut destinations: BTreeMap<String, String> = BTreeMap::new();
+    for copy in manifest["fileCopies"].as_array().into_iter().flatten() {
+        if let Some(destination) = copy["destination"].as_str() {
+            destinations.insert(destination.to_string(), MOD_FILE_NAME.to_string());
+        }
+    }
+    for asset in assets {
+        if let Some(owner) = destinations.get(&asset.destination) {
+            return Err(Error::Validation(format!(
+                "{} and {} both copy a file to {}",
+                owner, asset.id, asset.destination
+            )));
+        }
+        destinations.insert(asset.destination.clone(), asset.id.clone());
+    }
+
+    let staging = config
+        .tmp_dir()
+        .join(format!("qmod-{}", std::process::id()));
+    remove_dir_if_exists(&staging)?;
+    let _cleanup = cancel::remove_on_cancel(&staging);
+    fs::create_dir_all(&staging)?;
+    let result = stage(manifest, files, assets, &staging).and_then(|_| {
+        let qmod = dir.join(format!("{}.qmod", package.info.id));
+        zip_dir(&staging, &qmod)?;
+        Ok(qmod)
+    });
+    remove_dir_if_exists(&staging)?;
+    result
+}
+
+/// The files of the assets `package` declares, read from `root`
+fn collect_assets(package: &PackageConfig, root: &Path, assets: &mut Vec<AssetFile>) -> Result<()> {
+    let id = &package.info.id;
+    for asset in &package.assets {
+        let source = root.join(&asset.path);
+        if escapes_root(&asset.path) || !source.exists() {
+            return Err(Error::Validation(format!(
+                "{} declares the asset {}, which does not exist in {}",
+                id,
+                asset.path,
+                root.display()
+            )));
+        }
+        let destination = asset.destination.trim_end_matches('/');
+        let files = if source.is_dir() {
+            files_below(&source)?
+        } else {
+            vec![PathBuf::new()]
+        };
+        for file in files {
+            let relative = file.to_string_lossy().replace('\\', "/");
+            let join = |base: &str| match relative.as_str() {
+                "" => base.to_string(),
+                _ => format!("{}/{}", base.trim_end_matches('/'), relative),
+            };
+            assets.push(AssetFile {
+                id: id.clone(),
+                source: source.join(&file),
+                name: join(&format!(
+                    "assets/{}/{}",
+                    id,
+                    asset.path.trim_start_matches("./")
+                )),
+                destination: join(destination),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The files `manifest` lists with their names in the .qmod, found in the first folder
+/// of `search` having them
+fn find_listed(manifest: &Value, search: &[PathBuf]) -> Result<Vec<(PathBuf, String)>> {
+    let mut listed: Vec<String> = Vec::new();
+    for key in ["modFiles", "lateModFiles", "libraryFiles"] {
+        for file in manifest[key].as_array().into_iter().flatten() {
+            listed.extend(file.as_str().map(str::to_string));
+        }
+    }
+    listed.extend(manifest["coverImage"].as_str().map(str::to_string));
+    for copy in manifest["fileCopies"].as_array().into_iter().flatten() {
+        listed.extend(copy["name"].as_str().map(str::to_string));
+    }
+    let mut files = Vec::new();
+    for name in listed {
+        if escapes_root(&name) {
+            return Err(Error::Validation(format!(
+                "{} lists {}, which is not a relative path",
+                MOD_FILE_NAME, name
+            )));
+        }
+        let source = search
+            .iter()
+            .map(|folder| folder.join(&name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                Error::Validation(format!(
+                    "{} lists {}, which is neither in the project nor its build or restored libraries",
+                    MOD_FILE_NAME, name
+                ))
+            })?;
+        files.push((source, name));
+    }
+    Ok(files)
+}
+
+/// Copies what the .qmod holds into `staging`: `files` by their name in it, the assets
+/// and the manifest with their copies
+fn stage(
+    manifest: &mut Value,
+    files: &[(PathBuf, String)],
+    assets: &[AssetFile],
+    staging: &Path,
+) -> Result<()> {
+    for (source, name) in files {
+        copy_tree(source, &staging.join(name), &CopyOptions::new())?;
+    }
+
+    if !assets.is_empty() {
+        if !manifest["fileCopies"].is_array() {
+            manifest["fileCopies"] = Value::Array(Vec::new());
+        }
+        let copies = manifest["fileCopies"].as_array_mut().unwrap();
+        for asset in assets {
+            copy_tree(
+                &asset.source,
+                &staging.join(&asset.name),
+                &CopyOptions::new(),
+            )?;
+            copies.push(json!({ "name": asset.name, "destination": asset.destination }));
+        }
+    }
+    fs::write(
+        staging.join(MOD_FILE_NAME),
+        serde_json::to_string_pretty(manifest)?,
+    )?;
+    Ok(())
+}
+
+/// Zips the contents of `dir` into `output`, replacing it
+fn zip_dir(dir: &Path, output: &Path) -> Result<()> {
+    if output.exists() {
+        fs::remove_file(output)?;
+    }
+    let status = Command::new("zip")
+        .arg("-q")
+        .arg("-r")
+        .arg(output)
+        .arg(".")
+        .current_dir(dir)
+        .status()?;
+    if !status.success() {
+        return Err(Error::Io(std::io::Error::other(format!(
+            "failed to write {}",
+            output.display()
+        ))));
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::env;
+
+use crate::{
+    commands::Args,
+    data::{
+        config::Config,
+        file_repository::FileRepository,
+        package::{PackageConfig, PACKAGE_FILE_NAME},
+        shared_package::SharedPackageConfig,
+    },
+    error::Result,
+    repository::RepositoryChain,
+};
+
+/// Resolves qpm.json like restore would and prints the versions, without writing
+/// anything. `--print-constraints` instead prints every range each package of the tree is
+/// required with and the range they intersect to, `--json` as json for tooling.
+pub fn execute(mut args: Args) -> Result<()> {
+    let print_constraints = args.flag("--print-constraints");
+    let json = args.flag("--json");
+    let include_prerelease = args.flag("--include-prerelease");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+    let repository = RepositoryChain::new(&config)?;
+    let package = FileRepository::read()?.apply_links(&dir, &PackageConfig::read(&dir)?);
+
+    if !print_constraints {
+        let shared = SharedPackageConfig::from_package(&package, &repository, &config)?;
+        if json {
+            outln!(
+                "{}",
+                serde_json::to_string_pretty(&shared.restored_dependencies)?
+            );
+            return Ok(());
+        }
+        for dependency in &shared.restored_dependencies {
+            outln!("{} {}", dependency.dependency.id, dependency.version);
+        }
+        return Ok(());
+    }
+
+    let constraints = SharedPackageConfig::constraints(&package, &repository, &config)?;
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&constraints)?);
+        return Ok(());
+    }
+    for constraint in &constraints {
+        outln!(
+            "{} {}",
+            constraint.id,
+            constraint
+                .intersection
+                .as_deref()
+                .unwrap_or("(unsatisfiable)")
+        );
+        for requirement in &constraint.requirements {
+            let mut from = vec![PACKAGE_FILE_NAME];
+            from.extend(requirement.required_by.iter().map(String::as_str));
+            outln!(
+                "    {} from {}",
+                requirement.version_range,
+                from.join(" -> ")
+            );
+        }
+    }
+    Ok(())
+}
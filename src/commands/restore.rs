@@ -0,0 +1,362 @@
+use std::{cmp::Reverse, env, fs, path::Path, time::Instant};
+
+use crate::{
+    build_files,
+    cache::{
+        self,
+        gc::{self, GcPolicy},
+    },
+    ci::CiProvider,
+    commands::Args,
+    conflicts,
+    data::{
+        config::Config,
+        file_repository::FileRepository,
+        package::{normalize_id, PackageConfig},
+        restore_report::{DependencyReport, RestoreReport},
+        shared_package::{ChangeKind, SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    hooks::{self, Stage},
+    network,
+    repository::{Repository, RepositoryChain},
+    symbols,
+    utils::{fs::format_size, hash::fnv1a_hex},
+};
+
+/// Restores from qpm.shared.json, resolving it first when it is missing or out of date.
+/// `--frozen` refuses to resolve, for CI where the lock file must be committed.
+/// `--symbols` also fetches the unstripped binaries for debugging crashes, `--dry-run`
+/// only prints what a restore would fetch. `--report <file>` writes what was restored from
+/// where as json, `--build-info` generates qpm_build_info.hpp like the `buildInfo` setting.
+/// `--ci github` turns warnings and errors into annotations and sets step outputs.
+/// `--update <id>`, repeatable, resolves only those dependencies and what they pull in
+/// again, keeping every other version of qpm.shared.json. On a terminal a version conflict
+/// offers fixes to apply to qpm.json, unless `--no-interactive`. The `preRestore` and
+/// `postRestore` hooks of qpm.json run around restoring, unless `--no-hooks`.
+/// Binaries a dependency doesn't publish for a targeted ABI are looked for in the
+/// `binaryCache`, then built from source with `--build-missing` or `buildFromSource`.
+pub fn execute(mut args: Args) -> Result<()> {
+    let ci = args
+        .value("--ci")?
+        .map(|provider| CiProvider::parse(&provider))
+        .transpose()?;
+    let restored = restore(args, ci);
+    if let (Some(ci), Err(e)) = (ci, &restored) {
+        ci.error(&e.to_string());
+    }
+    restored
+}
+
+fn restore(mut args: Args, ci: Option<CiProvider>) -> Result<()> {
+    let include_prerelease = args.flag("--include-prerelease");
+    let build_missing = args.flag("--build-missing");
+    let frozen = args.flag("--frozen");
+    let with_symbols = args.flag("--symbols");
+    let dry_run = args.flag("--dry-run");
+    let report_path = args.value("--report")?;
+    let build_info = args.flag("--build-info");
+    let no_interactive = args.flag("--no-interactive");
+    let no_hooks = args.flag("--no-hooks");
+    let update: Vec<String> = args
+        .values("--update")?
+        .iter()
+        .map(|id| normalize_id(id))
+        .collect();
+    args.finish()?;
+    if !update.is_empty() && (frozen || dry_run) {
+        return Err(Error::Usage(
+            "--update rewrites qpm.shared.json, it can't be combined with --frozen or --dry-run"
+                .into(),
+        ));
+    }
+    let started = Instant::now();
+    let dir = env::current_dir()?;
+    let mut config = Config::read()?;
+    config.include_prerelease |= include_prerelease;
+    config.build_from_source |= build_missing;
+
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    if dry_run {
+        return preview(&dir, &package, &repository, &config, frozen);
+    }
+    // held through the garbage collection after the restore as well
+    let _lock = cache::lock::lock(&config)?;
+    let (shared, resolved) = if update.is_empty() {
+        let interactive = !frozen && conflicts::is_interactive(no_interactive);
+        conflicts::resolve_with_prompt(&dir, &repository, &config, interactive, || {
+            let package = PackageConfig::read(&dir)?;
+            SharedPackageConfig::resolve_fresh(&dir, &package, &repository, &config, frozen)
+        })?
+    } else {
+        if !dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+            return Err(Error::Usage(format!(
+                "there is no {} to update yet, run qpm restore first",
+                SHARED_PACKAGE_FILE_NAME
+            )));
+        }
+        let locked = SharedPackageConfig::read(&dir)?;
+        let package = FileRepository::read()?.apply_links(&dir, &package);
+        let updated =
+            SharedPackageConfig::update(&package, &locked, &update, &repository, &config)?;
+        print_changes(&locked, &updated);
+        (updated, true)
+    };
+    if resolved {
+        shared.write(&dir)?;
+    }
+    // only the project's own hooks run, never those of a dependency
+    let hooks = if no_hooks {
+        Default::default()
+    } else {
+        package.hooks.clone()
+    };
+    hooks::run(&hooks, Stage::PreRestore, &dir, &shared, resolved, None)?;
+    let mut report = shared.restore(&dir, &repository, &config)?;
+    report.resolved = resolved;
+    if build_info || package.build_info {
+        build_files::write_build_info(&dir, &shared)?;
+    }
+    hooks::run(
+        &hooks,
+        Stage::PostRestore,
+        &dir,
+        &shared,
+        resolved,
+        Some(&report),
+    )?;
+    if with_symbols {
+        let found = symbols::restore_symbols(&dir, &shared, &repository, &config)?;
+        outln!("Restored symbols of {} dependencies", found);
+    }
+
+    if config.gc.auto_gc {
+        let keep = shared
+            .restored_dependencies
+            .iter()
+            .map(|d| (d.dependency.id.clone(), d.version.clone()))
+            .collect();
+        let evicted = gc::run(&config, &GcPolicy::from(&config.gc), &keep, false)?;
+        if !evicted.is_empty() {
+            outln!("Garbage collected {} cached versions", evicted.len());
+        }
+    }
+    print_timings(&report);
+    let unchanged = report.dependencies.iter().filter(|d| d.up_to_date).count();
+    outln!(
+        "Restored {} dependencies, {} unchanged",
+        shared.restored_dependencies.len(),
+        unchanged
+    );
+    if let Some(ci) = ci {
+        for warning in &report.warnings {
+            ci.warning(warning);
+        }
+        let extern_dir = dir.join(&shared.config.dependencies_dir);
+        let layout = &shared.config.extern_layout;
+        let lock = fs::read(dir.join(SHARED_PACKAGE_FILE_NAME))?;
+        ci.set_outputs(&[
+            ("id", shared.config.info.id.clone()),
+            ("version", shared.config.info.version.clone()),
+            ("extern-dir", extern_dir.display().to_string()),
+            (
+                "includes-dir",
+                extern_dir.join(&layout.includes_dir).display().to_string(),
+            ),
+            (
+                "libs-dir",
+                extern_dir.join(&layout.libs_dir).display().to_string(),
+            ),
+            ("cache-dir", config.cache_dir().display().to_string()),
+            // changes exactly when the resolved dependencies do
+            ("cache-key", format!("qpm-{}", fnv1a_hex(&lock))),
+        ])?;
+    }
+    if let Some(report_path) = report_path {
+        report.duration_ms = started.elapsed().as_millis() as u64;
+        report.write(&dir.join(report_path))?;
+    }
+    Ok(())
+}
+
+/// Prints which resolved versions differ between `before` and `after`
+fn print_changes(before: &SharedPackageConfig, after: &SharedPackageConfig) {
+    let changes = before.changes(after);
+    for change in &changes {
+        let before = change.before.as_deref().unwrap_or_default();
+        let after = change.after.as_deref().unwrap_or_default();
+        match change.kind {
+            ChangeKind::Added => outln!("Added {} {}", change.id, after),
+            ChangeKind::Removed => outln!("Removed {} {}", change.id, before),
+            ChangeKind::Upgraded | ChangeKind::Downgraded => {
+                outln!("Updated {} {} -> {}", change.id, before, after)
+            }
+        }
+    }
+    if changes.is_empty() {
+        outln!("Already at the newest versions allowed, nothing changed");
+    }
+}
+
+/// Prints what each dependency took, slowest first, so slow hosts and regressions stand out
+fn print_timings(report: &RestoreReport) {
+    let mut dependencies: Vec<&DependencyReport> = report.dependencies.iter().collect();
+    dependencies.sort_by_key(|d| Reverse(d.duration_ms));
+    let rows: Vec<[String; 5]> = dependencies
+        .iter()
+        .map(|d| {
+            [
+                format!("{} {}", d.id, d.version),
+                d.source.clone(),
+                format_duration(d.duration_ms),
+                if d.bytes_downloaded == 0 {
+                    "-".to_string()
+                } else {
+                    format_size(d.bytes_downloaded)
+                },
+                if d.up_to_date {
+                    "unchanged"
+                } else {
+                    "restored"
+                }
+                .to_string(),
+            ]
+        })
+        .collect();
+    let header = ["dependency", "source", "time", "downloaded", "files"].map(String::from);
+    let mut widths = header.clone().map(|column| column.len());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        outln!(
+            "{:w0$}  {:w1$}  {:>w2$}  {:>w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+    }
+}
+
+fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    }
+}
+
+/// Resolves like a restore would and prints where every source and binary would come from,
+/// without writing anything
+fn preview(
+    dir: &Path,
+    package: &PackageConfig,
+    repository: &dyn Repository,
+    config: &Config,
+    frozen: bool,
+) -> Result<()> {
+    let (shared, resolved) =
+        SharedPackageConfig::resolve_fresh(dir, package, repository, config, frozen)?;
+    if resolved {
+        outln!("Would write a newly resolved qpm.shared.json");
+    }
+
+    let mut dependencies: Vec<_> = shared.restored_dependencies.iter().collect();
+    dependencies.sort_by(|a, b| a.dependency.id.cmp(&b.dependency.id));
+    let (mut hits, mut downloads, mut known_bytes) = (0, 0, 0);
+    for dependency in dependencies {
+        let mut dependency_shared = dependency.get_shared_package(repository, config)?;
+        package.apply_sources(&mut dependency_shared);
+        let info = &dependency_shared.config.info;
+        outln!("{} {}", info.id, info.version);
+        let data = &dependency.dependency.additional_data;
+        if let Some(local_path) = &data.local_path {
+            outln!("    local: {}", local_path);
+            continue;
+        }
+
+        if cache::src_dir(config, &info.id, &info.version).exists() {
+            outln!("    source: cached");
+            hits += 1;
+        } else {
+            outln!(
+                "    source: download from {}",
+                info.url.as_deref().unwrap_or("an unknown url")
+            );
+            downloads += 1;
+        }
+        if cache::is_headers_only(&dependency_shared.config) {
+            continue;
+        }
+        for abi in package.abis() {
+            if cache::cached_binary(config, &dependency_shared.config, &abi).is_some() {
+                outln!("    {} binary: cached", abi);
+                hits += 1;
+            } else {
+                match cache::so_link(&dependency_shared.config, data, &abi) {
+                    Some(link) => {
+                        downloads += 1;
+                        let size = network::content_length(link, config).ok().flatten();
+                        known_bytes += size.unwrap_or(0);
+                        let size = size
+                            .map(format_size)
+                            .unwrap_or_else(|| "size unknown".into());
+                        outln!("    {} binary: download from {} ({})", abi, link, size);
+                    }
+                    None => match (&config.binary_cache.url, config.build_from_source) {
+                        (Some(_), true) => outln!(
+                            "    {} binary: from the binary cache, else build from source",
+                            abi
+                        ),
+                        (Some(_), false) => outln!("    {} binary: from the binary cache", abi),
+                        (None, true) => outln!("    {} binary: build from source", abi),
+                        (None, false) => outln!("    {} binary: none published", abi),
+                    },
+                }
+            }
+            for binary in cache::extra_binaries(&dependency_shared.config) {
+                if cache::cached_extra_binary(config, &dependency_shared.config, binary, &abi)
+                    .is_some()
+                {
+                    outln!("    {} {}: cached", abi, binary.name);
+                    hits += 1;
+                    continue;
+                }
+                match binary.link_for(&abi) {
+                    Some(link) => {
+                        downloads += 1;
+                        let size = network::content_length(link, config).ok().flatten();
+                        known_bytes += size.unwrap_or(0);
+                        let size = size
+                            .map(format_size)
+                            .unwrap_or_else(|| "size unknown".into());
+                        outln!(
+                            "    {} {}: download from {} ({})",
+                            abi,
+                            binary.name,
+                            link,
+                            size
+                        );
+                    }
+                    None => outln!("    {} {}: none published", abi, binary.name),
+                }
+            }
+        }
+    }
+    outln!(
+        "{} cache hits, {} downloads totalling at least {}",
+        hits,
+        downloads,
+        format_size(known_bytes)
+    );
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::fs;
+
+use crate::{
+    commands::Args,
+    error::{Error, Result},
+    schema,
+};
+
+pub fn execute(mut args: Args) -> Result<()> {
+    let out = args.value("--out")?;
+    let kind = args.positional();
+    args.finish()?;
+
+    let schema = match kind.as_deref() {
+        Some("package") | None => schema::package_config(),
+        Some("shared") => schema::shared_package_config(),
+        Some(other) => {
+            return Err(Error::Usage(format!(
+                "unknown schema '{}', expected package or shared",
+                other
+            )))
+        }
+    };
+    let text = serde_json::to_string_pretty(&schema)?;
+    match out {
+        Some(out) => fs::write(out, text)?,
+        None => outln!("{}", text),
+    }
+    Ok(())
+}
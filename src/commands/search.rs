@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+use crate::{
+    commands::Args,
+    data::config::Config,
+    error::{Error, Result},
+    repository::qpackages,
+    version::VersionReq,
+};
+
+#[derive(Serialize, Debug)]
+pub struct SearchResult {
+    pub id: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+}
+
+pub fn execute(mut args: Args) -> Result<()> {
+    let json = args.flag("--json");
+    let limit = match args.value("--limit")? {
+        Some(limit) => limit
+            .parse::<usize>()
+            .map_err(|_| Error::Usage(format!("invalid limit '{}'", limit)))?,
+        None => 10,
+    };
+    let query = args
+        .positional()
+        .ok_or_else(|| Error::Usage("usage: qpm search <query> [--limit n] [--json]".into()))?;
+    args.finish()?;
+
+    let config = Config::read()?;
+    let query = query.to_lowercase();
+    let mut ids: Vec<String> = qpackages::get_package_ids(&config)?
+        .into_iter()
+        .filter(|id| id.to_lowercase().contains(&query))
+        .collect();
+    // exact and prefix matches first, then alphabetical
+    ids.sort_by_key(|id| {
+        let lower = id.to_lowercase();
+        (lower != query, !lower.starts_with(&query), lower)
+    });
+    ids.truncate(limit);
+
+    let mut results = Vec::new();
+    for id in ids {
+        let latest = qpackages::get_latest_matching(&id, &VersionReq::STAR, &config)?;
+        let info = match &latest {
+            Some(version) => qpackages::get_shared_package(&id, &version.to_string(), &config)?
+                .map(|shared| shared.config.info),
+            None => None,
+        };
+        results.push(SearchResult {
+            id,
+            version: latest.map(|v| v.to_string()),
+            description: info.as_ref().and_then(|i| i.description.clone()),
+            url: info.and_then(|i| i.url),
+        });
+    }
+
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+    if results.is_empty() {
+        outln!("No packages matching '{}'", query);
+    }
+    for result in results {
+        outln!(
+            "{} {}",
+            result.id,
+            result.version.as_deref().unwrap_or("(no stable version)")
+        );
+        if let Some(description) = result.description {
+            outln!("    {}", description);
+        }
+        if let Some(url) = result.url {
+            outln!("    {}", url);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use crate::{
+    commands::Args,
+    data::config::Config,
+    error::Result,
+    self_update::{self, CURRENT_VERSION},
+};
+
+/// Installs the latest qpm release over the running executable when it is newer, after
+/// checking the download against its published checksum. `--check` only reports whether
+/// there is one.
+pub fn execute(mut args: Args) -> Result<()> {
+    let check = args.flag("--check");
+    args.finish()?;
+    let config = Config::read()?;
+
+    let release = self_update::latest_release(&config)?;
+    let version = release.version()?;
+    if !self_update::is_newer(&release)? {
+        outln!("qpm {} is up to date", CURRENT_VERSION);
+        return Ok(());
+    }
+    if check {
+        outln!("qpm {} is available, you have {}", version, CURRENT_VERSION);
+        return Ok(());
+    }
+    outln!("Updating qpm {} to {}", CURRENT_VERSION, version);
+    let exe = self_update::install(&release, &config)?;
+    outln!("Installed qpm {} at {}", version, exe.display());
+    Ok(())
+}
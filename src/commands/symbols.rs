@@ -0,0 +1,78 @@
+use std::{env, fs, io::Read};
+
+use crate::{
+    commands::Args,
+    data::{config::Config, package::PackageConfig},
+    error::{Error, Result},
+    ndk,
+    symbols::{self, Frame},
+};
+
+/// `qpm symbols <tombstone|->` symbolizes a backtrace, `qpm symbols --lib <so> <address>...`
+/// looks up single addresses
+pub fn execute(mut args: Args) -> Result<()> {
+    let library = args.value("--lib")?;
+    let mut inputs = Vec::new();
+    while let Some(input) = args.positional() {
+        inputs.push(input);
+    }
+    args.finish()?;
+
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+
+    let frames = match library {
+        Some(library) => inputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, pc)| Frame {
+                index: format!("#{:02}", i),
+                pc,
+                library: library.clone(),
+            })
+            .collect(),
+        None => {
+            let text =
+                match inputs.as_slice() {
+                    [path] if path != "-" => fs::read_to_string(path)?,
+                    [] | [_] => {
+                        let mut text = String::new();
+                        std::io::stdin().read_to_string(&mut text)?;
+                        text
+                    }
+                    _ => return Err(Error::Usage(
+                        "usage: qpm symbols <tombstone|-> or qpm symbols --lib <so> <address>..."
+                            .into(),
+                    )),
+                };
+            symbols::parse_backtrace(&text)
+        }
+    };
+    if frames.is_empty() {
+        return Err(Error::Usage("no backtrace frames found".into()));
+    }
+
+    let tool = ndk::resolve(&package, &config)?.and_then(|ndk| symbols::addr2line_path(&ndk.path));
+    for frame in frames {
+        let symbols = symbols::find_symbols(&dir, &package, &frame.library);
+        let location = match (&tool, &symbols) {
+            (Some(tool), Some(symbols)) => {
+                symbols::addr2line(tool, symbols, std::slice::from_ref(&frame.pc))?.join("; ")
+            }
+            (None, Some(symbols)) => format!("symbols in {}", symbols.display()),
+            (_, None) => "no symbols".to_string(),
+        };
+        outln!(
+            "{} {} {}: {}",
+            frame.index,
+            frame.pc,
+            frame.library,
+            location
+        );
+    }
+    if tool.is_none() {
+        eprintln!("warning: no NDK with llvm-addr2line found, only locating symbol files");
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::{
+    commands::Args,
+    data::config::Config,
+    error::{Error, Result},
+};
+
+pub fn execute(mut args: Args) -> Result<()> {
+    let mut config = Config::read_global()?;
+    match args.subcommand().as_deref() {
+        Some("add") => {
+            let name = args.positional();
+            let url = args.positional();
+            args.finish()?;
+            let (name, url) = name
+                .zip(url)
+                .ok_or_else(|| Error::Usage("usage: qpm template add <name> <git-url>".into()))?;
+            config.templates.insert(name.clone(), url);
+            config.write()?;
+            outln!("Registered template {}", name);
+        }
+        Some("remove") => {
+            let name = args
+                .positional()
+                .ok_or_else(|| Error::Usage("usage: qpm template remove <name>".into()))?;
+            args.finish()?;
+            if config.templates.remove(&name).is_none() {
+                return Err(Error::Usage(format!("no template named {}", name)));
+            }
+            config.write()?;
+            outln!("Removed template {}", name);
+        }
+        Some("list") | None => {
+            args.finish()?;
+            for (name, url) in &config.templates {
+                outln!("{}: {}", name, url);
+            }
+        }
+        Some(other) => {
+            return Err(Error::Usage(format!(
+                "unknown template command '{}'",
+                other
+            )));
+        }
+    }
+    Ok(())
+}
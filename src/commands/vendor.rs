@@ -0,0 +1,95 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache,
+    commands::Args,
+    data::{
+        config::Config, dependency::DependencyOverride, package::PackageConfig,
+        shared_package::SharedPackageConfig,
+    },
+    error::Result,
+    repository::RepositoryChain,
+    utils::{
+        copy::{self, copy_tree, CopyOptions, SymlinkPolicy},
+        fs::{format_size, remove_dir_if_exists},
+        ignore::IgnoreRules,
+    },
+};
+
+pub const VENDOR_DIR: &str = "vendor";
+
+/// Copies every resolved dependency into `vendor/<id>` and overrides them to those folders,
+/// so the project restores without touching the network or the global cache
+pub fn execute(args: Args) -> Result<()> {
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let mut package = PackageConfig::read(&dir)?;
+
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    for dependency in &shared.restored_dependencies {
+        let id = &dependency.dependency.id;
+        if dependency.dependency.additional_data.local_path.is_some() {
+            outln!("Skipping {}, it is already a local package", id);
+            continue;
+        }
+        let mut dependency_shared = dependency.get_shared_package(&repository, &config)?;
+        package.apply_sources(&mut dependency_shared);
+        cache::ensure_cached(
+            &dependency_shared,
+            &dependency.dependency.additional_data,
+            &package.abis(),
+            &config,
+        )?;
+
+        let relative = PathBuf::from(VENDOR_DIR).join(id);
+        let vendored = dir.join(&relative);
+        remove_dir_if_exists(&vendored)?;
+        let src = cache::src_dir(&config, id, &dependency.version);
+        let ignore = IgnoreRules::load(&src)?;
+        let filter = copy::ignoring(&ignore, Path::new(""));
+        let mut copied = copy_tree(
+            &src,
+            &vendored,
+            &CopyOptions::new()
+                .filter(&filter)
+                .preserve_mtime(true)
+                .symlinks(SymlinkPolicy::Preserve),
+        )?;
+        remove_dir_if_exists(&vendored.join(".git"))?;
+        let lib = cache::lib_dir(&config, id, &dependency.version);
+        if lib.exists() {
+            let binaries = copy_tree(&lib, &vendored.join("lib"), &CopyOptions::new())?;
+            copied.files += binaries.files;
+            copied.bytes += binaries.bytes;
+        }
+        dependency_shared.write(&vendored)?;
+
+        package.overrides.insert(
+            id.clone(),
+            DependencyOverride {
+                local_path: Some(relative.to_string_lossy().replace('\\', "/")),
+                ..Default::default()
+            },
+        );
+        outln!(
+            "Vendored {} {} ({} files, {})",
+            id,
+            dependency.version,
+            copied.files,
+            format_size(copied.bytes)
+        );
+    }
+    fs::create_dir_all(dir.join(VENDOR_DIR))?;
+    package.write(&dir)?;
+
+    let shared = SharedPackageConfig::from_package(&package, &repository, &config)?;
+    shared.write(&dir)?;
+    shared.restore(&dir, &repository, &config)?;
+    Ok(())
+}
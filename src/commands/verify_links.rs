@@ -0,0 +1,145 @@
+use std::env;
+
+use serde::Serialize;
+
+use crate::{
+    cancel,
+    commands::Args,
+    data::{config::Config, package::PackageConfig, shared_package::SharedPackageConfig},
+    error::{Error, Result},
+    network,
+    repository::RepositoryChain,
+};
+
+/// A link of published metadata that doesn't lead to a file
+#[derive(Serialize, Debug)]
+pub struct DeadLink {
+    pub id: String,
+    pub version: String,
+    /// The field holding it, like `soLink` or `abiSoLinks.armeabi-v7a`
+    pub field: String,
+    pub url: String,
+    /// The status the server answered with, or why it couldn't be reached
+    pub problem: String,
+}
+
+/// Checks that every download link of the project is alive: the source url, binaries, debug
+/// binaries and the mod. `--tree` checks those of every restored dependency as well,
+/// `--json` prints the dead links for tooling.
+pub fn execute(mut args: Args) -> Result<()> {
+    let tree = args.flag("--tree");
+    let json = args.flag("--json");
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+
+    let mut packages = vec![package.clone()];
+    if tree {
+        let repository = RepositoryChain::new(&config)?;
+        let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+        for restored in &shared.restored_dependencies {
+            if restored.dependency.additional_data.local_path.is_none() {
+                // the links the project fetches it from, hotfixes included
+                let mut dependency_shared = restored.get_shared_package(&repository, &config)?;
+                package.apply_sources(&mut dependency_shared);
+                packages.push(dependency_shared.config);
+            }
+        }
+    }
+    let mut dead = Vec::new();
+    let mut checked = 0;
+    for package in &packages {
+        checked += links(package).len();
+        dead.extend(dead_links(package, &config)?);
+    }
+
+    if json {
+        outln!("{}", serde_json::to_string_pretty(&dead)?);
+    } else {
+        for link in &dead {
+            outln!(
+                "{} {} {}: {} ({})",
+                link.id,
+                link.version,
+                link.field,
+                link.url,
+                link.problem
+            );
+        }
+        outln!("Checked {} links, {} dead", checked, dead.len());
+    }
+    if dead.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(format!(
+            "{} of {} links are dead",
+            dead.len(),
+            checked
+        )))
+    }
+}
+
+/// Every url a package's metadata points dependents at, by the field holding it
+pub fn links(package: &PackageConfig) -> Vec<(String, String)> {
+    let info = &package.info;
+    let data = &info.additional_data;
+    let mut links = Vec::new();
+    let mut push = |field: String, url: &Option<String>| {
+        if let Some(url) = url {
+            links.push((field, url.clone()));
+        }
+    };
+    push("url".into(), &info.url);
+    push("soLink".into(), &data.so_link);
+    push("debugSoLink".into(), &data.debug_so_link);
+    push("staticLink".into(), &data.static_link);
+    push("modLink".into(), &data.mod_link);
+    for (field, abi_links) in [
+        ("abiSoLinks", &data.abi_so_links),
+        ("abiStaticLinks", &data.abi_static_links),
+    ] {
+        for (abi, url) in abi_links.iter().flatten() {
+            push(format!("{}.{}", field, abi), &Some(url.clone()));
+        }
+    }
+    for binary in data.extra_binaries.iter().flatten() {
+        push(
+            format!("extraBinaries.{}.soLink", binary.name),
+            &binary.so_link,
+        );
+        for (abi, url) in binary.abi_so_links.iter().flatten() {
+            push(
+                format!("extraBinaries.{}.abiSoLinks.{}", binary.name, abi),
+                &Some(url.clone()),
+            );
+        }
+    }
+    // ssh and git urls can't be checked with a request
+    links
+        .into_iter()
+        .filter(|(_, url)| url.starts_with("http://") || url.starts_with("https://"))
+        .collect()
+}
+
+/// The links of `package` that answer with anything but success, or not at all
+pub fn dead_links(package: &PackageConfig, config: &Config) -> Result<Vec<DeadLink>> {
+    let mut dead = Vec::new();
+    for (field, url) in links(package) {
+        let problem = match network::head_status(&url, config) {
+            Ok(status) if (200..400).contains(&status) => continue,
+            Ok(status) => format!("status {}", status),
+            Err(Error::Network(_)) if cancel::is_cancelled() => return Err(Error::Cancelled),
+            Err(Error::Network(message)) => message,
+            Err(e) => return Err(e),
+        };
+        dead.push(DeadLink {
+            id: package.info.id.clone(),
+            version: package.info.version.clone(),
+            field,
+            url,
+            problem,
+        });
+    }
+    Ok(dead)
+}
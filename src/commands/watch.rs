@@ -0,0 +1,183 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    build::BuildOptions,
+    cache,
+    commands::{install, Args},
+    data::{
+        config::Config,
+        extra_file::ExtraFile,
+        package::{PackageConfig, PACKAGE_FILE_NAME},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::RepositoryChain,
+    utils::fs::files_below,
+};
+
+/// How often the files are checked, in milliseconds
+const DEFAULT_INTERVAL: u64 = 500;
+
+/// What a watched file looked like: modification time and size
+type Fingerprint = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Installs the current project into the local repository whenever its headers, extra
+/// files, assets, qpm.json or binary change, then restores every `--consumer <dir>` so
+/// they pick up the new files. Runs until interrupted.
+pub fn execute(mut args: Args) -> Result<()> {
+    let consumers: Vec<PathBuf> = args
+        .values("--consumer")?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let binary = args.value("--binary")?.map(PathBuf::from);
+    let interval = match args.value("--interval")? {
+        Some(interval) => interval.parse().map_err(|_| {
+            Error::Usage(format!("--interval takes milliseconds, not {}", interval))
+        })?,
+        None => DEFAULT_INTERVAL,
+    };
+    args.finish()?;
+    let dir = env::current_dir()?;
+    for consumer in &consumers {
+        if !consumer.join(PACKAGE_FILE_NAME).exists() {
+            return Err(Error::Usage(format!(
+                "{} has no {}",
+                consumer.display(),
+                PACKAGE_FILE_NAME
+            )));
+        }
+    }
+
+    let interval = Duration::from_millis(interval);
+    let mut last = Fingerprint::new();
+    outln!(
+        "Watching {} for changes, {} consumers",
+        dir.display(),
+        consumers.len()
+    );
+    loop {
+        let current = fingerprint(&dir, binary.as_deref());
+        if current != last {
+            // wait for a build or an editor to finish writing
+            thread::sleep(interval);
+            let settled = fingerprint(&dir, binary.as_deref());
+            if settled != current {
+                continue;
+            }
+            // a failed cycle is reported and retried on the next change
+            if let Err(e) = sync(&dir, binary.as_deref(), &consumers) {
+                eprintln!("error: {}", e);
+            }
+            last = settled;
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Reinstalls the project and restores the consumers
+fn sync(dir: &Path, binary: Option<&Path>, consumers: &[PathBuf]) -> Result<()> {
+    let config = Config::read()?;
+    let package = PackageConfig::read(dir)?;
+    let abi = BuildOptions::for_package(&package).abi;
+    let binary = match binary {
+        Some(binary) => Some(binary.to_path_buf()),
+        None if cache::is_headers_only(&package) => None,
+        None => cache::local_binary(dir, &abi, &cache::so_name(&package)),
+    };
+    install::install(dir, &package, binary.as_deref(), &abi, &config, false)?;
+    outln!(
+        "Installed {} {}{}",
+        package.info.id,
+        package.info.version,
+        if binary.is_none() && !cache::is_headers_only(&package) {
+            ", without a binary since none is built yet"
+        } else {
+            ""
+        }
+    );
+
+    let repository = RepositoryChain::new(&config)?;
+    for consumer in consumers {
+        let consumer_package = PackageConfig::read(consumer)?;
+        let (shared, resolved) = SharedPackageConfig::resolve_fresh(
+            consumer,
+            &consumer_package,
+            &repository,
+            &config,
+            false,
+        )?;
+        if !shared
+            .restored_dependencies
+            .iter()
+            .any(|restored| restored.dependency.id == package.info.id)
+        {
+            eprintln!(
+                "warning: {} doesn't depend on {}",
+                consumer.display(),
+                package.info.id
+            );
+            continue;
+        }
+        if resolved {
+            shared.write(consumer)?;
+        }
+        shared.restore(consumer, &repository, &config)?;
+        outln!("Restored {}", consumer.display());
+    }
+    Ok(())
+}
+
+/// The state of everything an install copies from `dir`
+fn fingerprint(dir: &Path, binary: Option<&Path>) -> Fingerprint {
+    let mut paths = vec![dir.join(PACKAGE_FILE_NAME)];
+    // a broken qpm.json mid-edit still counts as a change, install reports it
+    if let Ok(package) = PackageConfig::read(dir) {
+        let mut roots = vec![PathBuf::from(&package.shared_dir)];
+        for extra in package.info.additional_data.extra_files.iter().flatten() {
+            if let Ok(extra) = ExtraFile::parse(extra) {
+                roots.push(PathBuf::from(extra.base()));
+            }
+        }
+        roots.extend(
+            package
+                .assets
+                .iter()
+                .map(|asset| PathBuf::from(&asset.path)),
+        );
+        for root in roots {
+            let root = dir.join(root);
+            if root.is_dir() {
+                paths.extend(
+                    files_below(&root)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|file| root.join(file)),
+                );
+            } else {
+                paths.push(root);
+            }
+        }
+        let abi = BuildOptions::for_package(&package).abi;
+        paths.extend(match binary {
+            Some(binary) => Some(binary.to_path_buf()),
+            None => cache::local_binary(dir, &abi, &cache::so_name(&package)),
+        });
+    }
+    let mut fingerprint = Fingerprint::new();
+    for path in paths {
+        // files may vanish while they are listed, that is a change too
+        let state = match path.metadata() {
+            Ok(metadata) => (metadata.modified().ok(), metadata.len()),
+            Err(_) => (None, 0),
+        };
+        fingerprint.insert(path, state);
+    }
+    fingerprint
+}
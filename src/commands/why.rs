@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, env};
+
+use crate::{
+    commands::Args,
+    data::{
+        config::Config,
+        dependency::{feature_dependencies, Dependency, Visibility},
+        package::{normalize_id, PackageConfig, PACKAGE_FILE_NAME},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::RepositoryChain,
+};
+
+/// Most chains printed before the rest are only counted
+const MAX_CHAINS: usize = 50;
+
+/// Explains why a package is restored: every chain of dependencies from the project to
+/// it, with the range each step asked for
+pub fn execute(mut args: Args) -> Result<()> {
+    let id = args
+        .positional()
+        .map(|id| normalize_id(&id))
+        .ok_or_else(|| Error::Usage("usage: qpm why <id>".into()))?;
+    args.finish()?;
+    let dir = env::current_dir()?;
+    let config = Config::read()?;
+    let package = PackageConfig::read(&dir)?;
+    let repository = RepositoryChain::new(&config)?;
+    let shared = SharedPackageConfig::read_fresh(&dir, &package, &repository, &config, false)?;
+
+    let target = shared
+        .restored_dependencies
+        .iter()
+        .find(|restored| restored.dependency.id == id)
+        .ok_or_else(|| Error::Usage(format!("{} is not a dependency of this project", id)))?;
+
+    // what every resolved package asks for in turn, by id
+    let mut requires: BTreeMap<&str, Vec<Dependency>> = BTreeMap::new();
+    for restored in &shared.restored_dependencies {
+        let resolved = restored.get_shared_package(&repository, &config)?;
+        let mut children: Vec<Dependency> = resolved
+            .restored_dependencies
+            .iter()
+            .filter(|child| child.dependency.visibility() == Visibility::Public)
+            .map(|child| child.dependency.clone())
+            .collect();
+        children.extend(feature_dependencies(&restored.dependency, &resolved)?);
+        requires.insert(&restored.dependency.id, children);
+    }
+
+    let mut chains = Vec::new();
+    let mut stack: Vec<Vec<&Dependency>> = package.dependencies.iter().map(|d| vec![d]).collect();
+    while let Some(chain) = stack.pop() {
+        let last = chain[chain.len() - 1];
+        if last.id == id {
+            chains.push(chain);
+            continue;
+        }
+        for child in requires.get(last.id.as_str()).into_iter().flatten() {
+            // a cycle never leads anywhere new
+            if chain.iter().all(|step| step.id != child.id) {
+                let mut longer = chain.clone();
+                longer.push(child);
+                stack.push(longer);
+            }
+        }
+    }
+    chains.sort_by_key(|chain| {
+        chain
+            .iter()
+            .map(|step| (step.id.clone(), step.version_range.clone()))
+            .collect::<Vec<_>>()
+    });
+
+    outln!(
+        "{} {} is restored because of:",
+        target.dependency.id,
+        target.version
+    );
+    for chain in chains.iter().take(MAX_CHAINS) {
+        let steps: Vec<String> = chain
+            .iter()
+            .map(|step| format!("{}@{}", step.id, step.version_range))
+            .collect();
+        outln!("  {} -> {}", package.info.id, steps.join(" -> "));
+    }
+    if chains.len() > MAX_CHAINS {
+        outln!("  and {} more chains", chains.len() - MAX_CHAINS);
+    }
+    if let Some(dependency_override) = package.overrides.get(&id) {
+        if let Some(range) = &dependency_override.version_range {
+            outln!("overridden in {} to {}", PACKAGE_FILE_NAME, range);
+        }
+    }
+    Ok(())
+}
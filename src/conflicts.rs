@@ -0,0 +1,73 @@
+use std::{
+    env,
+    io::{stdin, stdout, IsTerminal},
+    path::Path,
+};
+
+use crate::{
+    data::{
+        config::Config, file_repository::FileRepository, package::PackageConfig,
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    repository::Repository,
+    utils::prompt::Prompt,
+};
+
+/// Whether a version conflict may be asked about: on a terminal, outside of CI and
+/// without `--no-interactive`
+pub fn is_interactive(no_interactive: bool) -> bool {
+    !no_interactive
+        && env::var_os("CI").is_none()
+        && stdin().is_terminal()
+        && stdout().is_terminal()
+}
+
+/// Runs `resolve`. When it fails on a version conflict and `interactive`, lists the fixes
+/// qpm can make, applies the picked one to the qpm.json in `dir` and runs it again.
+pub fn resolve_with_prompt<T>(
+    dir: &Path,
+    repository: &dyn Repository,
+    config: &Config,
+    interactive: bool,
+    mut resolve: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    loop {
+        let message = match resolve() {
+            Err(Error::Resolution(message)) if interactive => message,
+            resolved => return resolved,
+        };
+        let package = FileRepository::read()?.apply_links(dir, &PackageConfig::read(dir)?);
+        let conflict = match SharedPackageConfig::find_conflict(&package, repository, config)? {
+            Some(conflict) => conflict,
+            // failed for another reason, like a range nothing was published for
+            None => return Err(Error::Resolution(message)),
+        };
+        let fixes: Vec<_> = conflict
+            .fixes
+            .iter()
+            .filter(|fix| fix.is_automatic())
+            .collect();
+        if fixes.is_empty() {
+            return Err(Error::Resolution(message));
+        }
+
+        outln!("{}", conflict.explain());
+        outln!("Which fix should be applied to qpm.json?");
+        for (i, fix) in fixes.iter().enumerate() {
+            outln!("  {}) {}", i + 1, fix);
+        }
+        outln!("  0) none, stop here");
+        let answer = Prompt { assume_yes: false }.ask("Fix", "1")?;
+        let fix = match answer.parse::<usize>() {
+            Ok(0) => return Err(Error::Resolution(message)),
+            Ok(n) if n <= fixes.len() => fixes[n - 1],
+            _ => return Err(Error::Usage(format!("{} is not one of the fixes", answer))),
+        };
+        // links only exist for this machine, they mustn't end up in qpm.json
+        let mut package = PackageConfig::read(dir)?;
+        fix.apply(&mut package)?;
+        package.write(dir)?;
+        outln!("Applied: {}", fix);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    data::config::Config,
+    error::{Error, Result},
+    network,
+    utils::fs::write_atomic,
+};
+
+/// The community list of the mods every Beat Saber install loads, by game version
+pub const CORE_MODS_URL: &str =
+    "https://raw.githubusercontent.com/QuestPackageManager/bs-coremods/main/core_mods.json";
+
+/// The core mods of one game version
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CoreMods {
+    pub last_updated: Option<String>,
+    pub mods: Vec<CoreMod>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreMod {
+    pub id: String,
+    pub version: String,
+}
+
+/// Where the last fetched list is kept, for when the list can't be fetched
+fn cached_path(config: &Config) -> PathBuf {
+    config.cache_dir().join("core_mods.json")
+}
+
+/// Every game version's core mods, by game version like `1.37.0_9064817954`. Falls back
+/// to the copy fetched last time when offline.
+pub fn fetch(config: &Config) -> Result<BTreeMap<String, CoreMods>> {
+    let cached = cached_path(config);
+    let body = match network::get(CORE_MODS_URL, config) {
+        Ok(Some(body)) => {
+            write_atomic(&cached, &body)?;
+            body
+        }
+        Ok(None) => return Err(Error::Network(format!("{} does not exist", CORE_MODS_URL))),
+        Err(e) => match fs::read(&cached) {
+            Ok(body) => {
+                eprintln!("warning: {}, using the core mods fetched before", e);
+                body
+            }
+            Err(_) => return Err(e),
+        },
+    };
+    serde_json::from_slice(&body)
+        .map_err(|e| Error::Network(format!("invalid core mods list: {}", e)))
+}
+
+/// The entry for `game_version`, which may leave out the build number after the `_`
+pub fn for_game_version<'a>(
+    all: &'a BTreeMap<String, CoreMods>,
+    game_version: &str,
+) -> Option<(&'a String, &'a CoreMods)> {
+    all.get_key_value(game_version).or_else(|| {
+        all.iter()
+            .find(|(version, _)| version.split('_').next() == Some(game_version))
+    })
+}
@@ -0,0 +1,1066 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    cache,
+    data::{
+        config::Config,
+        interpolation::resolve_local_path,
+        package::{normalize_id, PackageConfig, Target, PACKAGE_FILE_NAME},
+        shared_package::{SharedDependency, SharedPackageConfig, SHARED_PACKAGE_FILE_NAME},
+    },
+    error::{Error, Result},
+    logging,
+    repository::{self, Repository},
+    version::{Version, VersionReq},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    #[serde(deserialize_with = "crate::data::package::deserialize_id")]
+    pub id: String,
+    /// The name of the dependency's folders under extern and of its CMake target, in
+    /// place of the id. Only read from the project's own qpm.json.
+    #[serde(rename = "as", default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    pub version_range: String,
+    #[serde(default)]
+    pub additional_data: AdditionalDependencyData,
+}
+
+/// Whether a dependency is part of a package's API, from its `isPrivate` flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// Re-exported: dependents resolve it too and get its headers
+    Public,
+    /// An implementation detail: dependents never see it, so the package's own headers
+    /// mustn't include its headers
+    Private,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct AdditionalDependencyData {
+    /// See [`Visibility::Private`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_path: Option<String>,
+    /// `local_path` resolved against the folder of the manifest it was read from. Never
+    /// written, manifests keep the path as it was written, see [`Self::local_dir`].
+    #[serde(skip)]
+    pub resolved_local_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_files: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shallow_clone: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse_checkout: Option<bool>,
+    /// Binaries per ABI, taking precedence over the ones the package publishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi_so_links: Option<BTreeMap<String, String>>,
+    /// Features of the dependency to enable, adding their optional dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Only depend on this for game versions in this range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    /// Only depend on this on these devices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platforms: Option<Vec<String>>,
+    /// Only resolve this from the named source: `local` or the name of a registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The range a dependency had before `qpm dependency pin` locked it to one version.
+    /// A pin in the project's qpm.json wins over the ranges of its dependencies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unpinned_range: Option<String>,
+    /// Fetch the sources from this repository or archive instead of the package's url.
+    /// Like `branchName` and `soLink` it only changes where files come from, never which
+    /// version resolves, and only counts in the project's own qpm.json.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Fetch the sources from this branch instead of the version's tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_name: Option<String>,
+    /// The arm64-v8a binary to download instead of the published one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub so_link: Option<String>,
+}
+
+impl AdditionalDependencyData {
+    /// The folder of a local dependency. Its `localPath` is relative to the manifest it
+    /// was read from, or to the working directory when it wasn't read from one.
+    pub fn local_dir(&self) -> Option<PathBuf> {
+        let local_path = self.local_path.as_ref()?;
+        Some(
+            self.resolved_local_path
+                .clone()
+                .unwrap_or_else(|| resolve_local_path(local_path, Path::new("."))),
+        )
+    }
+
+    /// Resolves `local_path` against `dir`, the folder of the manifest it was read from
+    pub fn resolve_local_path(&mut self, dir: &Path) {
+        self.resolved_local_path = self
+            .local_path
+            .as_ref()
+            .map(|local_path| resolve_local_path(local_path, dir));
+    }
+}
+
+/// Replaces a dependency wherever it appears in the tree, including transitively.
+/// Declared in the `overrides` of the root qpm.json.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_range: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_path: Option<String>,
+    /// See [`AdditionalDependencyData::resolved_local_path`]
+    #[serde(skip)]
+    pub resolved_local_path: Option<PathBuf>,
+}
+
+pub type Overrides = BTreeMap<String, DependencyOverride>;
+
+/// Reads overrides keyed by the [`normalize_id`] form of the ids they replace
+pub fn deserialize_overrides<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Overrides, D::Error> {
+    Ok(Overrides::deserialize(deserializer)?
+        .into_iter()
+        .map(|(id, dependency_override)| (normalize_id(&id), dependency_override))
+        .collect())
+}
+
+impl DependencyOverride {
+    /// Whether the override changes which version gets resolved, rather than just the source
+    pub fn changes_resolution(&self) -> bool {
+        self.version_range.is_some() || self.local_path.is_some()
+    }
+
+    pub fn apply(&self, dependency: &Dependency) -> Dependency {
+        let mut dependency = dependency.clone();
+        if let Some(version_range) = &self.version_range {
+            dependency.version_range = version_range.clone();
+        }
+        if let Some(local_path) = &self.local_path {
+            dependency.additional_data.local_path = Some(local_path.clone());
+            dependency.additional_data.resolved_local_path = self.resolved_local_path.clone();
+        }
+        dependency
+    }
+
+    pub fn apply_to_package(&self, shared: &mut SharedPackageConfig) {
+        if let Some(url) = &self.url {
+            shared.config.info.url = Some(url.clone());
+        }
+    }
+}
+
+impl AdditionalDependencyData {
+    /// Points `shared` at the sources and binary these settings fetch from instead
+    pub fn apply_sources(&self, shared: &mut SharedPackageConfig) {
+        let is_static = cache::is_static(&shared.config);
+        let info = &mut shared.config.info;
+        if let Some(url) = &self.url {
+            info.url = Some(url.clone());
+        }
+        if let Some(branch_name) = &self.branch_name {
+            info.additional_data.branch_name = Some(branch_name.clone());
+        }
+        if let Some(so_link) = &self.so_link {
+            let link = if is_static {
+                &mut info.additional_data.static_link
+            } else {
+                &mut info.additional_data.so_link
+            };
+            *link = Some(so_link.clone());
+        }
+    }
+
+    /// Whether [`AdditionalDependencyData::apply_sources`] changes anything
+    pub fn overrides_sources(&self) -> bool {
+        self.url.is_some() || self.branch_name.is_some() || self.so_link.is_some()
+    }
+}
+
+impl Dependency {
+    pub fn visibility(&self) -> Visibility {
+        if self.additional_data.is_private.unwrap_or(false) {
+            Visibility::Private
+        } else {
+            Visibility::Public
+        }
+    }
+
+    /// Whether this dependency's conditions hold for `target`
+    pub fn applies_to(&self, target: &Target) -> Result<bool> {
+        target.allows(
+            self.additional_data.game_version.as_deref(),
+            self.additional_data.platforms.as_deref(),
+        )
+    }
+
+    /// Whether `qpm dependency pin` locked this to one version
+    pub fn is_pinned(&self) -> bool {
+        self.additional_data.unpinned_range.is_some()
+    }
+
+    pub fn version_req(&self) -> Result<VersionReq> {
+        VersionReq::parse(&self.version_range)
+    }
+
+    /// The newest package satisfying this dependency, from its local path or `repository`
+    pub fn get_shared_package(
+        &self,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<SharedPackageConfig> {
+        self.get_compatible_package(repository, config, &Target::default())
+    }
+
+    /// The newest package satisfying this dependency that works with the game version of
+    /// `target`, or the newest one when none declares it does
+    pub fn get_compatible_package(
+        &self,
+        repository: &dyn Repository,
+        config: &Config,
+        target: &Target,
+    ) -> Result<SharedPackageConfig> {
+        if let Some(dir) = self.additional_data.local_dir() {
+            let dir = dir.as_path();
+            if !dir.join(PACKAGE_FILE_NAME).exists() {
+                return Err(Error::Resolution(format!(
+                    "the localPath of {} resolves to {}, which has no {}",
+                    self.id,
+                    dir.display(),
+                    PACKAGE_FILE_NAME
+                )));
+            }
+            logging::debug(
+                "resolve",
+                format!("{} uses the local package at {}", self.id, dir.display()),
+            );
+            return read_local_package(dir, repository, config);
+        }
+
+        let repository = match &self.additional_data.source {
+            Some(source) => repository::pinned(repository, source)?,
+            None => repository,
+        };
+        let mut req = self.version_req()?;
+        let mut newest = None;
+        // walks down from the newest version until one supports the game version
+        while let Some(version) = repository.get_latest_matching(&self.id, &req)? {
+            let shared = SharedDependency {
+                dependency: self.clone(),
+                version: version.to_string(),
+            }
+            .get_shared_package(repository, config)?;
+            if target.supports(shared.config.info.additional_data.game_versions.as_deref())? {
+                logging::debug(
+                    "resolve",
+                    format!("{} {} picked for {}", self.id, version, self.version_range),
+                );
+                return Ok(shared);
+            }
+            logging::debug(
+                "resolve",
+                format!(
+                    "{} {} skipped, it doesn't support the target game version",
+                    self.id, version
+                ),
+            );
+            let below = VersionReq::parse(&format!("<{}", version))?;
+            newest.get_or_insert(shared);
+            match VersionReq::intersect(&[req, below]) {
+                Some(lower) => req = lower,
+                None => break,
+            }
+        }
+        if let Some(newest) = newest {
+            return Ok(newest);
+        }
+        if repository.is_yanked_only(&self.id, &self.version_req()?)? {
+            return Err(Error::Resolution(format!(
+                "every version of {} matching {} was yanked",
+                self.id, self.version_range
+            )));
+        }
+        Err(Error::Resolution(format!(
+            "no version of {} matches {}",
+            self.id, self.version_range
+        )))
+    }
+
+    /// Resolves this dependency and everything it pulls in, with `overrides` replacing
+    /// matching dependencies anywhere in the tree
+    pub fn collect(
+        &self,
+        repository: &dyn Repository,
+        config: &Config,
+        overrides: &Overrides,
+    ) -> Result<BTreeMap<SharedDependency, SharedPackageConfig>> {
+        collect_all(
+            vec![Pending::Range(self.clone())],
+            repository,
+            config,
+            overrides,
+            &Target::default(),
+        )
+    }
+}
+
+impl SharedDependency {
+    /// The exact package version this was restored as, from the cache when possible
+    pub fn get_shared_package(
+        &self,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<SharedPackageConfig> {
+        if let Some(dir) = self.dependency.additional_data.local_dir() {
+            return read_local_package(&dir, repository, config);
+        }
+        if let Some(shared) =
+            cache::read_shared_package(config, &self.dependency.id, &self.version)?
+        {
+            return Ok(shared);
+        }
+        let repository = match &self.dependency.additional_data.source {
+            Some(source) => repository::pinned(repository, source)?,
+            None => repository,
+        };
+        repository
+            .get_shared_package(&self.dependency.id, &self.version)?
+            .ok_or_else(|| {
+                Error::Resolution(format!(
+                    "{} {} does not exist in any repository",
+                    self.dependency.id, self.version
+                ))
+            })
+    }
+
+    /// Resolves everything this locked dependency pulls in, see [`Dependency::collect`]
+    pub fn collect(
+        &self,
+        repository: &dyn Repository,
+        config: &Config,
+        overrides: &Overrides,
+    ) -> Result<BTreeMap<SharedDependency, SharedPackageConfig>> {
+        collect_all(
+            vec![Pending::Locked(self.clone())],
+            repository,
+            config,
+            overrides,
+            &Target::default(),
+        )
+    }
+}
+
+/// A dependency waiting to be collected
+#[derive(Clone)]
+pub enum Pending {
+    /// Resolved from its range, like the root's dependencies and those behind features
+    Range(Dependency),
+    /// Locked to the version the package depending on it was published with
+    Locked(SharedDependency),
+}
+
+/// Resolves `roots` and everything they pull in, with `overrides` replacing matching
+/// dependencies anywhere in the tree. Ranges resolve to the newest version supporting
+/// the game version of `target` when there is one.
+///
+/// Works through an explicit stack rather than recursing, so deep trees can't overflow it,
+/// and expands every id and version only once. A package depending on itself, directly
+/// or further down, is reported and not followed again.
+pub fn collect_all(
+    roots: Vec<Pending>,
+    repository: &dyn Repository,
+    config: &Config,
+    overrides: &Overrides,
+    target: &Target,
+) -> Result<BTreeMap<SharedDependency, SharedPackageConfig>> {
+    resolve_tree(roots, repository, config, overrides, target)?
+        .map_err(|conflict| Error::Resolution(conflict.to_string()))
+}
+
+/// The conflict resolving `roots` runs into, `None` when they resolve, see [`collect_all`]
+pub fn find_conflict(
+    roots: Vec<Pending>,
+    repository: &dyn Repository,
+    config: &Config,
+    overrides: &Overrides,
+    target: &Target,
+) -> Result<Option<Conflict>> {
+    Ok(resolve_tree(roots, repository, config, overrides, target)?.err())
+}
+
+/// Collects and consolidates `roots`. When no collected version of a package satisfies
+/// every range it is required with, but `repository` has one in between, collects again
+/// with that version asked for as well.
+fn resolve_tree(
+    mut roots: Vec<Pending>,
+    repository: &dyn Repository,
+    config: &Config,
+    overrides: &Overrides,
+    target: &Target,
+) -> Result<std::result::Result<Collected, Conflict>> {
+    let mut narrowed = BTreeSet::new();
+    loop {
+        let (collected, required_by) =
+            collect_tree(roots.clone(), repository, config, overrides, target)?;
+        let conflict = match consolidate(collected, &required_by, config, target)? {
+            Ok(consolidated) => return Ok(Ok(consolidated)),
+            Err(conflict) => conflict,
+        };
+        match satisfying_all(&conflict, repository)? {
+            Some(dependency) if narrowed.insert(conflict.id.clone()) => {
+                logging::debug(
+                    "resolve",
+                    format!(
+                        "no collected version of {} satisfies every range, trying {}",
+                        conflict.id, dependency.version_range
+                    ),
+                );
+                roots.push(Pending::Range(dependency));
+            }
+            _ => return Ok(Err(conflict)),
+        }
+    }
+}
+
+/// The dependency on the intersection of the ranges behind `conflict`, when `repository`
+/// has a version in it
+fn satisfying_all(conflict: &Conflict, repository: &dyn Repository) -> Result<Option<Dependency>> {
+    let reqs = conflict
+        .requirements
+        .iter()
+        .map(|(d, _)| d.dependency.version_req())
+        .collect::<Result<Vec<_>>>()?;
+    let req = match VersionReq::intersect(&reqs) {
+        Some(req) => req,
+        None => return Ok(None),
+    };
+    let (first, _) = &conflict.requirements[0];
+    let mut dependency = first.dependency.clone();
+    dependency.version_range = req.to_string();
+    // a dependency's pin is a range like any other, not one of the project's
+    dependency.additional_data.unpinned_range = None;
+    let source = match &dependency.additional_data.source {
+        Some(source) => repository::pinned(repository, source)?,
+        None => repository,
+    };
+    Ok(source
+        .get_latest_matching(&dependency.id, &req)?
+        .map(|_| dependency))
+}
+
+/// The packages behind each spec anywhere in the tree
+type Collected = BTreeMap<SharedDependency, SharedPackageConfig>;
+/// The first chain of packages that asked for each spec, empty for the project's own
+type RequiredBy = BTreeMap<SharedDependency, Vec<String>>;
+
+/// Every spec `roots` pull in before they are consolidated to one per package
+fn collect_tree(
+    roots: Vec<Pending>,
+    repository: &dyn Repository,
+    config: &Config,
+    overrides: &Overrides,
+    target: &Target,
+) -> Result<(Collected, RequiredBy)> {
+    let mut collected = BTreeMap::new();
+    // the first chain of packages that asked for each entry, to explain conflicts with
+    let mut required_by = BTreeMap::new();
+    let mut expanded = BTreeSet::new();
+    // specs of one package version may each enable other features of it
+    let mut expanded_features = BTreeSet::new();
+    // each entry carries the packages that pulled it in, to name the cycle when there is one
+    let mut stack: Vec<(Pending, Vec<String>)> = roots
+        .into_iter()
+        .rev()
+        .map(|root| (root, Vec::new()))
+        .collect();
+    while let Some((pending, path)) = stack.pop() {
+        let (dependency, shared) = match pending {
+            Pending::Range(dependency) => {
+                let dependency_override = overrides.get(&dependency.id);
+                let dependency = match dependency_override {
+                    Some(dependency_override) => dependency_override.apply(&dependency),
+                    None => dependency,
+                };
+                let mut shared = dependency.get_compatible_package(repository, config, target)?;
+                if let Some(dependency_override) = dependency_override {
+                    dependency_override.apply_to_package(&mut shared);
+                }
+                (dependency, shared)
+            }
+            Pending::Locked(locked) => {
+                let dependency_override = overrides.get(&locked.dependency.id);
+                if dependency_override.is_some_and(DependencyOverride::changes_resolution) {
+                    // the version locked upstream no longer applies, resolve from scratch
+                    stack.push((Pending::Range(locked.dependency), path));
+                    continue;
+                }
+                let mut shared = locked.get_shared_package(repository, config)?;
+                if let Some(dependency_override) = dependency_override {
+                    dependency_override.apply_to_package(&mut shared);
+                }
+                (locked.dependency, shared)
+            }
+        };
+
+        let info = &shared.config.info;
+        let node = format!("{} {}", info.id, info.version);
+        if path.contains(&node) {
+            eprintln!(
+                "warning: dependency cycle {} -> {}, not following it again",
+                path.join(" -> "),
+                node
+            );
+            continue;
+        }
+        let mut children = Vec::new();
+        if expanded.insert((info.id.clone(), info.version.clone())) {
+            // only what the package re-exports reaches its dependents
+            for restored in shared
+                .restored_dependencies
+                .iter()
+                .filter(|restored| restored.dependency.visibility() == Visibility::Public)
+            {
+                children.push(Pending::Locked(restored.clone()));
+            }
+        }
+        let mut new_features = dependency.clone();
+        new_features.additional_data.features =
+            dependency
+                .additional_data
+                .features
+                .as_ref()
+                .map(|features| {
+                    features
+                        .iter()
+                        .filter(|feature| {
+                            expanded_features.insert((
+                                info.id.clone(),
+                                info.version.clone(),
+                                (*feature).clone(),
+                            ))
+                        })
+                        .cloned()
+                        .collect()
+                });
+        for optional in feature_dependencies(&new_features, &shared)? {
+            children.push(Pending::Range(optional));
+        }
+        let mut child_path = path.clone();
+        child_path.push(node);
+        for child in children.into_iter().rev() {
+            stack.push((child, child_path.clone()));
+        }
+        let key = SharedDependency {
+            dependency,
+            version: shared.config.info.version.clone(),
+        };
+        required_by.entry(key.clone()).or_insert(path);
+        collected.insert(key, shared);
+    }
+    Ok((collected, required_by))
+}
+
+/// A range a package is required with somewhere in a dependency tree
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Requirement {
+    pub version_range: String,
+    /// The packages from the project down to the one asking, empty for the project itself
+    pub required_by: Vec<String>,
+}
+
+/// Every range a package is required with across a dependency tree, and the one range
+/// they leave
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Constraint {
+    pub id: String,
+    pub requirements: Vec<Requirement>,
+    /// `None` when no version can satisfy every requirement
+    pub intersection: Option<String>,
+}
+
+/// What each package `roots` pull in must satisfy across the whole tree, by id. Locked
+/// dependencies of packages count with the range they were published with.
+pub fn constraints(
+    roots: Vec<Pending>,
+    repository: &dyn Repository,
+    config: &Config,
+    overrides: &Overrides,
+    target: &Target,
+) -> Result<Vec<Constraint>> {
+    let (collected, required_by) = collect_tree(roots, repository, config, overrides, target)?;
+    let mut by_id: BTreeMap<String, Vec<Requirement>> = BTreeMap::new();
+    for dependency in collected.keys() {
+        let requirement = Requirement {
+            version_range: dependency.dependency.version_range.clone(),
+            required_by: required_by.get(dependency).cloned().unwrap_or_default(),
+        };
+        let requirements = by_id.entry(dependency.dependency.id.clone()).or_default();
+        if !requirements.contains(&requirement) {
+            requirements.push(requirement);
+        }
+    }
+    by_id
+        .into_iter()
+        .map(|(id, requirements)| {
+            let reqs = requirements
+                .iter()
+                .map(|requirement| VersionReq::parse(&requirement.version_range))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Constraint {
+                id,
+                requirements,
+                intersection: VersionReq::intersect(&reqs).map(|req| req.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Leaves one entry per package id when branches of the tree pulled in several versions
+/// or specs of it: the highest collected version satisfying every range, with the
+/// dependency specs merged, preferring versions supporting the game version of `target`.
+/// The conflict when no collected version satisfies them all, see [`resolve_tree`].
+fn consolidate(
+    collected: BTreeMap<SharedDependency, SharedPackageConfig>,
+    required_by: &BTreeMap<SharedDependency, Vec<String>>,
+    config: &Config,
+    target: &Target,
+) -> Result<std::result::Result<Collected, Conflict>> {
+    let mut by_id: BTreeMap<String, Vec<(SharedDependency, SharedPackageConfig)>> = BTreeMap::new();
+    for (dependency, shared) in collected {
+        by_id
+            .entry(dependency.dependency.id.clone())
+            .or_default()
+            .push((dependency, shared));
+    }
+
+    let mut consolidated = BTreeMap::new();
+    for (id, mut entries) in by_id {
+        if entries.len() == 1 {
+            let (dependency, shared) = entries.remove(0);
+            consolidated.insert(dependency, shared);
+            continue;
+        }
+        let dependencies: Vec<_> = entries.iter().map(|(d, _)| d.dependency.clone()).collect();
+        let mut merged = merge_dependencies(&id, &dependencies)?;
+        // only the project's own pins count, a dependency's pins are ranges like any other
+        let pinned = entries.iter().position(|(d, _)| {
+            d.dependency.is_pinned() && required_by.get(d).is_none_or(Vec::is_empty)
+        });
+
+        // a local checkout replaces the package everywhere, whatever its version
+        let local = entries
+            .iter()
+            .position(|(d, _)| d.dependency.additional_data.local_path.is_some());
+        let chosen = match (local, pinned) {
+            (Some(local), _) => local,
+            (None, Some(pinned)) => {
+                let (pin, _) = &entries[pinned];
+                let version = Version::parse(&pin.version)?;
+                for (dependency, _) in &entries {
+                    if !dependency
+                        .dependency
+                        .version_req()?
+                        .matches_with_prerelease(&version, true)
+                    {
+                        eprintln!(
+                            "warning: {} is pinned to {}, which is outside of the {} a dependency asks for",
+                            id, version, dependency.dependency.version_range
+                        );
+                    }
+                }
+                merged.version_range = pin.dependency.version_range.clone();
+                pinned
+            }
+            (None, None) => {
+                let reqs = dependencies
+                    .iter()
+                    .map(Dependency::version_req)
+                    .collect::<Result<Vec<_>>>()?;
+                let satisfies_all = |version: &str| {
+                    Version::parse(version).is_ok_and(|version| {
+                        reqs.iter().all(|req| {
+                            req.matches_with_prerelease(&version, config.include_prerelease)
+                        })
+                    })
+                };
+                let satisfying = entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (d, _))| satisfies_all(&d.version))
+                    .max_by_key(|(_, (d, shared))| {
+                        let game_versions =
+                            shared.config.info.additional_data.game_versions.as_deref();
+                        (
+                            target.supports(game_versions).unwrap_or(true),
+                            Version::parse(&d.version).ok(),
+                        )
+                    })
+                    .map(|(i, _)| i);
+                match satisfying {
+                    Some(i) => i,
+                    None => {
+                        let constraints: Vec<_> = entries
+                            .iter()
+                            .map(|(d, _)| (d, required_by.get(d).map_or(&[][..], Vec::as_slice)))
+                            .collect();
+                        return Ok(Err(Conflict::new(&id, &constraints, &reqs)));
+                    }
+                }
+            }
+        };
+        let (chosen, shared) = entries.swap_remove(chosen);
+        logging::debug(
+            "resolve",
+            format!(
+                "{} was collected as {}, settled on {} for {}",
+                id,
+                dependencies
+                    .iter()
+                    .map(|d| d.version_range.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                chosen.version,
+                merged.version_range
+            ),
+        );
+        consolidated.insert(
+            SharedDependency {
+                dependency: merged,
+                version: chosen.version,
+            },
+            shared,
+        );
+    }
+    Ok(Ok(consolidated))
+}
+
+/// No version of a package satisfying every range it is required with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub id: String,
+    /// Each spec with the version it was resolved or locked to and the chain of packages
+    /// that asked for it, empty for the project's own
+    pub requirements: Vec<(SharedDependency, Vec<String>)>,
+    /// Edits resolving it, most targeted first
+    pub fixes: Vec<ConflictFix>,
+}
+
+/// A way out of a [`Conflict`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConflictFix {
+    /// Change the project's own range so it allows the version the others need
+    Widen {
+        id: String,
+        from: String,
+        to: String,
+    },
+    /// Move to a version of a dependency in between that allows the version, by hand
+    UpdateRequirer {
+        requirer: String,
+        id: String,
+        version: Version,
+    },
+    /// Force one range on every package with an override
+    Override { id: String, version_range: String },
+}
+
+impl ConflictFix {
+    /// Whether [`ConflictFix::apply`] can make it, rather than someone by hand
+    pub fn is_automatic(&self) -> bool {
+        !matches!(self, ConflictFix::UpdateRequirer { .. })
+    }
+
+    /// Makes the fix in `package`, the project's qpm.json
+    pub fn apply(&self, package: &mut PackageConfig) -> Result<()> {
+        match self {
+            ConflictFix::Widen { id, to, .. } => {
+                let dependency = package
+                    .dependencies
+                    .iter_mut()
+                    .find(|dependency| dependency.id == *id)
+                    .ok_or_else(|| {
+                        Error::Usage(format!("{} is not a dependency of the project", id))
+                    })?;
+                dependency.version_range = to.clone();
+            }
+            ConflictFix::Override { id, version_range } => {
+                package
+                    .overrides
+                    .entry(id.clone())
+                    .or_default()
+                    .version_range = Some(version_range.clone());
+            }
+            ConflictFix::UpdateRequirer { .. } => {
+                return Err(Error::Usage(format!("{} needs to be done by hand", self)))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ConflictFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictFix::Widen { id, from, to } => write!(
+                f,
+                "change the range of {} in {} from {} to {}",
+                id, PACKAGE_FILE_NAME, from, to
+            ),
+            ConflictFix::UpdateRequirer {
+                requirer,
+                id,
+                version,
+            } => write!(
+                f,
+                "update {} to a version that allows {} {}",
+                requirer, id, version
+            ),
+            ConflictFix::Override { id, version_range } => write!(
+                f,
+                "force {} {} on every package with an override in {}: \"overrides\": {{ \"{}\": {{ \"versionRange\": \"{}\" }} }}",
+                id, version_range, PACKAGE_FILE_NAME, id, version_range
+            ),
+        }
+    }
+}
+
+impl Conflict {
+    /// The conflict of `id` between `constraints`, which `reqs` are the ranges of, with
+    /// fixes allowing the newest version anyone asked for, then overrides to each other
+    fn new(
+        id: &str,
+        constraints: &[(&SharedDependency, &[String])],
+        reqs: &[VersionReq],
+    ) -> Conflict {
+        let mut versions: Vec<Version> = constraints
+            .iter()
+            .filter_map(|(dependency, _)| Version::parse(&dependency.version).ok())
+            .collect();
+        versions.sort();
+        versions.dedup();
+        let mut fixes = Vec::new();
+        // the newest version anyone asked for is the one the other constraints should allow
+        if let Some(newest) = versions.last() {
+            for ((dependency, chain), req) in constraints.iter().zip(reqs) {
+                if req.matches(newest) {
+                    continue;
+                }
+                let fix = match chain.last() {
+                    None => ConflictFix::Widen {
+                        id: id.to_string(),
+                        from: dependency.dependency.version_range.clone(),
+                        to: VersionReq::caret(newest).to_string(),
+                    },
+                    Some(requirer) => ConflictFix::UpdateRequirer {
+                        requirer: requirer.clone(),
+                        id: id.to_string(),
+                        version: newest.clone(),
+                    },
+                };
+                if !fixes.contains(&fix) {
+                    fixes.push(fix);
+                }
+            }
+        }
+        for version in versions.iter().rev() {
+            fixes.push(ConflictFix::Override {
+                id: id.to_string(),
+                version_range: VersionReq::caret(version).to_string(),
+            });
+        }
+        Conflict {
+            id: id.to_string(),
+            requirements: constraints
+                .iter()
+                .map(|(dependency, chain)| ((*dependency).clone(), chain.to_vec()))
+                .collect(),
+            fixes,
+        }
+    }
+}
+
+impl Conflict {
+    /// Why no version satisfies every range, each with the chain of packages that asked
+    /// for it
+    pub fn explain(&self) -> String {
+        let mut message = format!(
+            "conflicting requirements on {}, no version satisfies all of them:",
+            self.id
+        );
+        for (dependency, chain) in &self.requirements {
+            let mut from = vec![PACKAGE_FILE_NAME];
+            from.extend(chain.iter().map(String::as_str));
+            message.push_str(&format!(
+                "\n  {} ({} {}) from {}",
+                dependency.dependency.version_range,
+                // packages lock their dependencies when published, the project's own are resolved
+                if chain.is_empty() {
+                    "resolved to"
+                } else {
+                    "locked to"
+                },
+                dependency.version,
+                from.join(" -> ")
+            ));
+        }
+        message
+    }
+}
+
+/// The explanation with the edits to qpm.json that resolve it
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.explain())?;
+        // overrides to older versions are only offered when picking one interactively
+        let is_override = |fix: &&ConflictFix| matches!(fix, ConflictFix::Override { .. });
+        let fixes: Vec<&ConflictFix> = self
+            .fixes
+            .iter()
+            .filter(|fix| !is_override(fix))
+            .chain(self.fixes.iter().find(is_override))
+            .collect();
+        if !fixes.is_empty() {
+            write!(f, "\npossible fixes:")?;
+        }
+        for fix in fixes {
+            write!(f, "\n  - {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// One spec for a package required several times: every distinct range, public if
+/// any spec is, the lists combined and settings that can't both hold rejected
+fn merge_dependencies(id: &str, dependencies: &[Dependency]) -> Result<Dependency> {
+    fn union(lists: impl Iterator<Item = Option<Vec<String>>>) -> Option<Vec<String>> {
+        let mut merged: Vec<String> = Vec::new();
+        for item in lists.flatten().flatten() {
+            if !merged.contains(&item) {
+                merged.push(item);
+            }
+        }
+        Some(merged).filter(|merged| !merged.is_empty())
+    }
+    fn same<T: Clone + PartialEq>(
+        id: &str,
+        name: &str,
+        values: impl Iterator<Item = Option<T>>,
+    ) -> Result<Option<T>> {
+        let mut values = values.flatten();
+        let first = values.next();
+        if let Some(first) = &first {
+            if values.any(|value| value != *first) {
+                return Err(Error::Resolution(format!(
+                    "{} is required with different {} settings",
+                    id, name
+                )));
+            }
+        }
+        Ok(first)
+    }
+
+    let mut ranges: Vec<&str> = Vec::new();
+    for dependency in dependencies {
+        let range = dependency.version_range.as_str();
+        if !ranges.contains(&range) {
+            ranges.push(range);
+        }
+    }
+    let data = dependencies.iter().map(|d| &d.additional_data);
+    let mut abi_so_links = BTreeMap::new();
+    for links in data.clone().flat_map(|data| data.abi_so_links.iter()) {
+        for (abi, link) in links {
+            abi_so_links
+                .entry(abi.clone())
+                .or_insert_with(|| link.clone());
+        }
+    }
+    Ok(Dependency {
+        id: id.to_string(),
+        // names extern folders, which only the project's qpm.json decides
+        alias: None,
+        version_range: ranges.join(", "),
+        additional_data: AdditionalDependencyData {
+            is_private: Some(true)
+                .filter(|_| data.clone().all(|data| data.is_private.unwrap_or(false))),
+            local_path: same(id, "localPath", data.clone().map(|d| d.local_path.clone()))?,
+            resolved_local_path: data.clone().find_map(|d| d.resolved_local_path.clone()),
+            extra_files: union(data.clone().map(|d| d.extra_files.clone())),
+            shallow_clone: Some(true)
+                .filter(|_| data.clone().all(|data| data.shallow_clone.unwrap_or(false))),
+            sparse_checkout: Some(true).filter(|_| {
+                data.clone()
+                    .all(|data| data.sparse_checkout.unwrap_or(false))
+            }),
+            abi_so_links: Some(abi_so_links).filter(|links| !links.is_empty()),
+            features: union(data.clone().map(|d| d.features.clone())),
+            game_version: same(
+                id,
+                "gameVersion",
+                data.clone().map(|d| d.game_version.clone()),
+            )?,
+            platforms: union(data.clone().map(|d| d.platforms.clone())),
+            source: same(id, "source", data.clone().map(|d| d.source.clone()))?,
+            unpinned_range: data.clone().find_map(|d| d.unpinned_range.clone()),
+            // where files come from, which only the project's qpm.json decides
+            url: None,
+            branch_name: None,
+            so_link: None,
+        },
+    })
+}
+
+/// The optional dependencies behind the features `dependency` enables on `shared`. They
+/// aren't part of the package's own lock, so they are resolved from their ranges.
+pub fn feature_dependencies(
+    dependency: &Dependency,
+    shared: &SharedPackageConfig,
+) -> Result<Vec<Dependency>> {
+    let mut optional_dependencies = Vec::new();
+    for feature in dependency.additional_data.features.iter().flatten() {
+        let optional = shared.config.features.get(feature).ok_or_else(|| {
+            Error::Resolution(format!(
+                "{} has no feature named {}",
+                dependency.id, feature
+            ))
+        })?;
+        optional_dependencies.extend(
+            optional
+                .iter()
+                .filter(|optional| optional.visibility() == Visibility::Public)
+                .cloned(),
+        );
+    }
+    Ok(optional_dependencies)
+}
+
+/// Reads a package from a local checkout, resolving it first if it has no shared config yet
+fn read_local_package(
+    dir: &Path,
+    repository: &dyn Repository,
+    config: &Config,
+) -> Result<SharedPackageConfig> {
+    if dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+        return SharedPackageConfig::read(dir);
+    }
+    SharedPackageConfig::from_package(&PackageConfig::read(dir)?, repository, config)
+}
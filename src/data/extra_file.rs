@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{Error, Result},
+    utils::{
+        fs::{escapes_root, files_below},
+        ignore::glob_matches,
+    },
+};
+
+/// Separates the source of an `extraFiles` entry from where it goes
+pub const MAPPING_SEPARATOR: &str = "->";
+
+/// An `extraFiles` entry: a path or glob in the package like `shared/inline/**/*.hpp`,
+/// optionally followed by `-> dest` to place what it matches somewhere else below the
+/// package's includes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtraFile {
+    pub source: String,
+    pub dest: Option<String>,
+}
+
+impl ExtraFile {
+    pub fn parse(entry: &str) -> Result<ExtraFile> {
+        let (source, dest) = match entry.split_once(MAPPING_SEPARATOR) {
+            Some((source, dest)) => (source, Some(clean(dest))),
+            None => (entry, None),
+        };
+        let extra = ExtraFile {
+            source: clean(source),
+            dest,
+        };
+        let paths = std::iter::once(&extra.source).chain(extra.dest.iter());
+        for path in paths {
+            if path.is_empty() || escapes_root(path) {
+                return Err(Error::Validation(format!(
+                    "the extra file {} must map a relative path inside the package to one inside its includes",
+                    entry
+                )));
+            }
+        }
+        Ok(extra)
+    }
+
+    pub fn is_glob(&self) -> bool {
+        self.source.contains(['*', '?', '['])
+    }
+
+    /// The folders of the source before its first wildcard, below which a glob can match
+    pub fn base(&self) -> &str {
+        if !self.is_glob() {
+            return &self.source;
+        }
+        let wildcard = self.source.find(['*', '?', '[']).unwrap_or(0);
+        match self.source[..wildcard].rfind('/') {
+            Some(end) => &self.source[..end],
+            None => "",
+        }
+    }
+
+    /// What the entry copies from `root`, as (in the package, in its includes) pairs. A
+    /// plain path may be a folder, a glob only matches files. Empty when nothing matches.
+    pub fn resolve(&self, root: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+        if !self.is_glob() {
+            if !root.join(&self.source).exists() {
+                return Ok(Vec::new());
+            }
+            let dest = self.dest.as_ref().unwrap_or(&self.source);
+            return Ok(vec![(PathBuf::from(&self.source), PathBuf::from(dest))]);
+        }
+        let base = Path::new(self.base());
+        if !root.join(base).is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut matched = Vec::new();
+        for file in files_below(&root.join(base))? {
+            let path = base.join(&file);
+            if !glob_matches(&self.source, &path.to_string_lossy().replace('\\', "/")) {
+                continue;
+            }
+            // a mapped glob keeps the folders below its base
+            let dest = match &self.dest {
+                Some(dest) => Path::new(dest).join(&file),
+                None => path.clone(),
+            };
+            matched.push((path, dest));
+        }
+        Ok(matched)
+    }
+}
+
+/// Trims whitespace and a leading `./`, and uses forward slashes
+fn clean(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    path.trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache,
+    data::{
+        config::{get_config_dir, Config},
+        extra_file::ExtraFile,
+        package::{normalize_id, PackageConfig, DEFAULT_ABI, PACKAGE_FILE_NAME},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    utils::{
+        copy::{self, copy_tree, CopyOptions},
+        fs::{remove_dir_if_exists, write_atomic},
+        ignore::IgnoreRules,
+    },
+    version::{Version, VersionReq},
+};
+
+pub const FILE_REPOSITORY_FILE_NAME: &str = "qpm.repository.json";
+
+/// Packages installed from local projects rather than the registry, by id and version
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FileRepository {
+    pub artifacts: BTreeMap<String, BTreeMap<String, SharedPackageConfig>>,
+    /// Working copies registered with `qpm link`, by id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, PathBuf>,
+    /// The linked ids each project uses instead of a version, by the project's folder
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub linked: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl FileRepository {
+    pub fn path() -> PathBuf {
+        get_config_dir().join(FILE_REPOSITORY_FILE_NAME)
+    }
+
+    /// The copy of the previous repository kept by every write
+    pub fn backup_path() -> PathBuf {
+        get_config_dir().join(format!("{}.bak", FILE_REPOSITORY_FILE_NAME))
+    }
+
+    fn read_from(path: &Path) -> Result<FileRepository> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Reads the repository. A corrupt file is set aside as `.corrupt` and the backup taken
+    /// instead, so one bad write doesn't lose every local install. Packages registered
+    /// under ids that aren't in their normalized form are moved to it and the file is
+    /// rewritten.
+    pub fn read() -> Result<FileRepository> {
+        let path = FileRepository::path();
+        if !path.exists() {
+            return Ok(FileRepository::default());
+        }
+        let error = match FileRepository::read_from(&path) {
+            Ok(mut repository) => {
+                if repository.normalize_keys() {
+                    eprintln!(
+                        "note: moved locally installed packages to lower case ids in {}",
+                        path.display()
+                    );
+                    repository.write()?;
+                }
+                return Ok(repository);
+            }
+            Err(e) => e,
+        };
+        let backup = FileRepository::backup_path();
+        let recovered = match FileRepository::read_from(&backup) {
+            Ok(recovered) => recovered,
+            Err(_) => {
+                return Err(Error::Cache(format!(
+                    "{} is corrupt ({}) and there is no usable backup",
+                    path.display(),
+                    error
+                )))
+            }
+        };
+        let corrupt = get_config_dir().join(format!("{}.corrupt", FILE_REPOSITORY_FILE_NAME));
+        fs::rename(&path, &corrupt)?;
+        fs::copy(&backup, &path)?;
+        eprintln!(
+            "warning: {} was corrupt ({}), recovered it from the backup and kept the broken file as {}",
+            path.display(),
+            error,
+            corrupt.display()
+        );
+        Ok(recovered)
+    }
+
+    /// Replaces the repository atomically, first rotating the current one into the backup
+    pub fn write(&self) -> Result<()> {
+        let path = FileRepository::path();
+        // a corrupt file mustn't replace a good backup
+        if FileRepository::read_from(&path).is_ok() {
+            fs::copy(&path, FileRepository::backup_path())?;
+        }
+        write_atomic(&path, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Re-keys every package by its id, which reading normalized, merging versions that
+    /// were registered under ids differing only in case. Returns whether anything moved.
+    fn normalize_keys(&mut self) -> bool {
+        if self.artifacts.keys().all(|id| *id == normalize_id(id)) {
+            return false;
+        }
+        let packages: Vec<_> = std::mem::take(&mut self.artifacts)
+            .into_values()
+            .flat_map(BTreeMap::into_values)
+            .collect();
+        for package in packages {
+            self.add_artifact_to_map(package, false);
+        }
+        true
+    }
+
+    /// The working copies the project in `dir` links, by id
+    pub fn links_of(&self, dir: &Path) -> BTreeMap<&str, &Path> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        self.linked
+            .get(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| Some((id.as_str(), self.links.get(id)?.as_path())))
+            .collect()
+    }
+
+    /// `package` of the project in `dir` with its linked dependencies pointing at their
+    /// working copies
+    pub fn apply_links(&self, dir: &Path, package: &PackageConfig) -> PackageConfig {
+        let mut package = package.clone();
+        for (id, path) in self.links_of(dir) {
+            if let Some(dependency) = package.dependencies.iter_mut().find(|d| d.id == id) {
+                dependency.additional_data.local_path = Some(path.display().to_string());
+            }
+        }
+        package
+    }
+
+    pub fn get_artifact(&self, id: &str, version: &str) -> Option<&SharedPackageConfig> {
+        self.artifacts
+            .get(&normalize_id(id))
+            .and_then(|versions| versions.get(version))
+    }
+
+    /// Every locally installed version of `id`
+    pub fn get_artifacts(&self, id: &str) -> Vec<&SharedPackageConfig> {
+        self.artifacts
+            .get(&normalize_id(id))
+            .map(|versions| versions.values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Installed versions of `id` that parse, highest first
+    fn sorted_artifacts(&self, id: &str) -> Vec<(Version, &SharedPackageConfig)> {
+        let mut artifacts: Vec<_> = self
+            .get_artifacts(id)
+            .into_iter()
+            .filter_map(|shared| Some((Version::parse(&shared.config.info.version).ok()?, shared)))
+            .collect();
+        artifacts.sort_by(|a, b| b.0.cmp(&a.0));
+        artifacts
+    }
+
+    /// The highest installed version of `id` satisfying `req` that isn't yanked
+    pub fn get_artifact_matching(
+        &self,
+        id: &str,
+        req: &VersionReq,
+    ) -> Option<&SharedPackageConfig> {
+        self.sorted_artifacts(id)
+            .into_iter()
+            .find(|(version, shared)| !shared.yanked && req.matches(version))
+            .map(|(_, shared)| shared)
+    }
+
+    /// The highest installed version of `id`
+    pub fn get_latest(&self, id: &str) -> Option<&SharedPackageConfig> {
+        self.sorted_artifacts(id)
+            .into_iter()
+            .next()
+            .map(|(_, shared)| shared)
+    }
+
+    /// Unregisters a locally installed version, leaving its cached files alone
+    pub fn remove_artifact(&mut self, id: &str, version: &str) -> Option<SharedPackageConfig> {
+        let id = normalize_id(id);
+        let versions = self.artifacts.get_mut(&id)?;
+        let removed = versions.remove(version);
+        if versions.is_empty() {
+            self.artifacts.remove(&id);
+        }
+        removed
+    }
+
+    /// Registers a package built from `project_folder` and copies its files into the cache.
+    /// Re-adding an installed version is an error unless `overwrite` is set, in which case
+    /// its cached files are replaced, like after rebuilding a local package.
+    pub fn add_artifact(
+        &mut self,
+        package: SharedPackageConfig,
+        project_folder: &Path,
+        binary_path: Option<&Path>,
+        config: &Config,
+        overwrite: bool,
+    ) -> Result<()> {
+        let info = &package.config.info;
+        if self.get_artifact(&info.id, &info.version).is_some() {
+            if !overwrite {
+                return Err(Error::Validation(format!(
+                    "{} {} is already installed locally",
+                    info.id, info.version
+                )));
+            }
+            remove_dir_if_exists(&cache::src_dir(config, &info.id, &info.version))?;
+            // binaries of other ABIs stay, they are installed separately
+            if binary_path.is_some() {
+                remove_dir_if_exists(&cache::abi_lib_dir(
+                    config,
+                    &info.id,
+                    &info.version,
+                    DEFAULT_ABI,
+                ))?;
+            }
+        }
+        cache_artifact(&package, project_folder, binary_path, config)?;
+        self.add_artifact_to_map(package, true);
+        Ok(())
+    }
+
+    /// Indexes a package without touching the cache. Returns whether it was added, which
+    /// it isn't when the version is already known and `overwrite` is unset.
+    pub fn add_artifact_to_map(&mut self, package: SharedPackageConfig, overwrite: bool) -> bool {
+        let info = &package.config.info;
+        let versions = self.artifacts.entry(info.id.clone()).or_default();
+        if !overwrite && versions.contains_key(&info.version) {
+            return false;
+        }
+        versions.insert(info.version.clone(), package);
+        true
+    }
+}
+
+/// Copies the shared dir, extra files, assets and binary of a local project into the cache layout
+/// restore reads from
+pub fn cache_artifact(
+    package: &SharedPackageConfig,
+    project_folder: &Path,
+    binary_path: Option<&Path>,
+    config: &Config,
+) -> Result<()> {
+    let info = &package.config.info;
+    let src = cache::src_dir(config, &info.id, &info.version);
+
+    if src.join(PACKAGE_FILE_NAME).exists() {
+        let cached = PackageConfig::read_fetched(&src)?;
+        if cached.info.version != info.version {
+            return Err(Error::Validation(format!(
+                "cached {} claims version {} but is stored as {}",
+                info.id, cached.info.version, info.version
+            )));
+        }
+    }
+
+    // tests, build outputs and the like the project's .qpmignore leaves out
+    let ignore = IgnoreRules::load(project_folder)?;
+    // extra files are cached where they are in the project, restore maps them
+    let mut copied = vec![PathBuf::from(&package.config.shared_dir)];
+    for extra in info.additional_data.extra_files.iter().flatten() {
+        for (from, _) in ExtraFile::parse(extra)?.resolve(project_folder)? {
+            if !ignore.is_ignored(&from, false) {
+                copied.push(from);
+            }
+        }
+    }
+    for relative in &copied {
+        let filter = copy::ignoring(&ignore, relative);
+        copy_tree(
+            &project_folder.join(relative),
+            &src.join(relative),
+            &CopyOptions::new().filter(&filter).preserve_mtime(true),
+        )?;
+    }
+    fs::copy(
+        project_folder.join(PACKAGE_FILE_NAME),
+        src.join(PACKAGE_FILE_NAME),
+    )?;
+
+    cache::store_assets(config, &package.config, project_folder)?;
+
+    if let Some(binary_path) = binary_path {
+        let lib = cache::abi_lib_dir(config, &info.id, &info.version, DEFAULT_ABI);
+        fs::create_dir_all(&lib)?;
+        fs::copy(binary_path, lib.join(cache::so_name(&package.config)))?;
+    }
+    package.write(&cache::package_dir(config, &info.id, &info.version))?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    data::{config::Config, package::PackageConfig},
+    error::{Error, Result},
+    ndk,
+    utils::fs::decode_path_component,
+};
+
+/// Fields whose strings may use `${NAME}`, anywhere in a manifest
+pub const INTERPOLATED_KEYS: &[&str] = &[
+    "localPath",
+    "url",
+    "soLink",
+    "debugSoLink",
+    "staticLink",
+    "modLink",
+];
+/// Fields holding maps whose values may use `${NAME}`
+pub const INTERPOLATED_MAP_KEYS: &[&str] = &["abiSoLinks", "abiStaticLinks"];
+
+/// The strings of a manifest that were rewritten while reading it, variables expanded, by
+/// what they became. Writing it back puts the originals
+/// back instead of this machine's values. Not part of the manifest's contents: any two
+/// compare equal.
+#[derive(Clone, Debug, Default)]
+pub struct Interpolations(BTreeMap<String, String>);
+
+impl PartialEq for Interpolations {
+    fn eq(&self, _: &Interpolations) -> bool {
+        true
+    }
+}
+
+impl Interpolations {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Puts the variables back into the strings of `value` they were expanded in
+    pub fn restore(&self, value: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        visit(value, &mut |_, text| {
+            if let Some(raw) = self.0.get(text.as_str()) {
+                *text = raw.clone();
+            }
+            Ok(())
+        })
+        .unwrap();
+    }
+}
+
+/// Expands `${NAME}` in the fields of [`INTERPOLATED_KEYS`] of the manifest `value`, read
+/// from `path`. `NAME` is one of `QPM_CACHE` (the cache folder), `NDK_PATH` (the NDK the
+/// project builds with), `VERSION` (the package's version) or an environment variable;
+/// `$$` is a literal `$`. Local paths stay relative, they are resolved against the
+/// manifest's folder apart from the manifest, see [`resolve_local_path`].
+pub fn interpolate(value: &mut Value, path: &Path) -> Result<Interpolations> {
+    // qpm.shared.json nests the manifest under config
+    let package = if value.get("config").is_some() {
+        value["config"].clone()
+    } else {
+        value.clone()
+    };
+    let mut interpolations = BTreeMap::new();
+    visit(value, &mut |_, text| {
+        let expanded = if text.contains('$') {
+            expand(text, &|name| variable(name, &package))
+                .map_err(|e| Error::Validation(format!("{} in {}: {}", text, path.display(), e)))?
+        } else {
+            text.clone()
+        };
+        if expanded != *text {
+            interpolations.insert(expanded.clone(), text.clone());
+            *text = expanded;
+        }
+        Ok(())
+    })?;
+    Ok(Interpolations(interpolations))
+}
+
+/// Calls `f` on every string that may be interpolated, with the key it is under
+fn visit(value: &mut Value, f: &mut dyn FnMut(&str, &mut String) -> Result<()>) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(text) if INTERPOLATED_KEYS.contains(&key.as_str()) => {
+                        f(key, text)?
+                    }
+                    Value::Object(links) if INTERPOLATED_MAP_KEYS.contains(&key.as_str()) => {
+                        for link in links.values_mut() {
+                            if let Value::String(text) = link {
+                                f(key, text)?;
+                            }
+                        }
+                    }
+                    _ => visit(value, f)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                visit(item, f)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand(text: &str, lookup: &dyn Fn(&str) -> Result<String>) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| Error::Validation("unclosed ${".into()))?;
+            expanded.push_str(&lookup(&after[..end])?);
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn variable(name: &str, package: &Value) -> Result<String> {
+    match name {
+        "VERSION" => package["info"]["version"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Validation("the package has no version".into())),
+        "QPM_CACHE" => Ok(Config::read()?.cache_dir().display().to_string()),
+        "NDK_PATH" => {
+            let config = Config::read()?;
+            let pin = PackageConfig {
+                ndk: package["ndk"].as_str().map(str::to_string),
+                ..Default::default()
+            };
+            ndk::resolve(&pin, &config)?
+                .map(|ndk| ndk.path.display().to_string())
+                .ok_or_else(|| Error::Validation("no NDK is installed".into()))
+        }
+        _ => env::var(name).map_err(|_| {
+            Error::Validation(format!(
+                "unknown variable {}, it is neither QPM_CACHE, NDK_PATH, VERSION nor set in the environment",
+                name
+            ))
+        }),
+    }
+}
+
+/// The absolute folder a `localPath` of a manifest in `dir` points at. Relative paths
+/// are relative to the manifest rather than wherever qpm runs, and `file://` urls are
+/// accepted too.
+pub fn resolve_local_path(local_path: &str, dir: &Path) -> PathBuf {
+    let local_path = match local_path.strip_prefix("file://") {
+        // file:///C:/x on Windows
+        Some(url) => {
+            let url = decode_path_component(url);
+            let bytes = url.as_bytes();
+            if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+                url[1..].to_string()
+            } else {
+                url
+            }
+        }
+        None => local_path.to_string(),
+    };
+    let path = dir.join(local_path);
+    path.canonicalize().unwrap_or_else(|_| {
+        // a missing folder can't be canonicalized, at least drop the `..`s for errors
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                Component::CurDir => {}
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    })
+}
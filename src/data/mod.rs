@@ -0,0 +1,9 @@
+pub mod config;
+pub mod dependency;
+pub mod extra_file;
+pub mod file_repository;
+pub mod interpolation;
+pub mod package;
+pub mod restore_report;
+pub mod restore_state;
+pub mod shared_package;
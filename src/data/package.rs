@@ -0,0 +1,766 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    data::{
+        dependency::{Dependency, Overrides},
+        interpolation::{self, resolve_local_path, Interpolations},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    migration, schema,
+    utils::hash::fnv1a_hex,
+    version::{Version, VersionReq},
+};
+
+pub const PACKAGE_FILE_NAME: &str = "qpm.json";
+/// The ABI of Quest devices, targeted when a project doesn't list any
+pub const DEFAULT_ABI: &str = "arm64-v8a";
+/// Longest package id `qpm init` and `qpm publish` accept
+pub const MAX_ID_LENGTH: usize = 64;
+
+/// Package ids are case-insensitive, so everything that keys, compares or names folders
+/// by them uses this form: trimmed and in lower case
+pub fn normalize_id(id: &str) -> String {
+    id.trim().to_ascii_lowercase()
+}
+
+/// Why `so_name` breaks the `lib<name>.so` convention, or `lib<name>.a` for static
+/// packages, None if it follows it
+pub fn so_name_problem(so_name: &str, is_static: bool) -> Option<String> {
+    let extension = if is_static { ".a" } else { ".so" };
+    let stem = so_name
+        .strip_prefix("lib")
+        .and_then(|name| name.strip_suffix(extension));
+    if stem.is_none_or(|stem| stem.is_empty() || stem.contains(['/', '\\'])) {
+        return Some(format!(
+            "overrideSoName {} must be a file name like lib<name>{}",
+            so_name, extension
+        ));
+    }
+    None
+}
+
+/// Checks an id new packages may be created or published with: lower case letters,
+/// digits, `-`, `_` and `.`, starting with a letter or digit
+pub fn validate_id(id: &str) -> Result<()> {
+    match name_problem(id) {
+        Some(problem) => Err(Error::Validation(format!(
+            "package id {:?} {}",
+            id, problem
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Checks the `as` of a dependency, which follows the rules of ids since it names folders
+/// and CMake targets the same way
+pub fn validate_alias(alias: &str) -> Result<()> {
+    match name_problem(alias) {
+        Some(problem) => Err(Error::Validation(format!(
+            "dependency alias {:?} {}",
+            alias, problem
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn name_problem(id: &str) -> Option<String> {
+    if id.is_empty() {
+        Some("is empty".to_string())
+    } else if id.len() > MAX_ID_LENGTH {
+        Some(format!("is longer than {} characters", MAX_ID_LENGTH))
+    } else if !id.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+        Some("must start with a lower case letter or digit".to_string())
+    } else {
+        id.chars()
+            .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || "-_.".contains(*c)))
+            .map(|c| format!("contains {:?}, only a-z, 0-9, -, _ and . are allowed", c))
+    }
+}
+
+/// Reads an id field in its [`normalize_id`] form
+pub fn deserialize_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    Ok(normalize_id(&String::deserialize(deserializer)?))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageConfig {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub shared_dir: String,
+    pub dependencies_dir: String,
+    pub info: PackageInfo,
+    pub dependencies: Vec<Dependency>,
+    /// Forced replacements for dependencies anywhere in the tree, by id
+    #[serde(
+        default,
+        skip_serializing_if = "Overrides::is_empty",
+        deserialize_with = "crate::data::dependency::deserialize_overrides"
+    )]
+    pub overrides: Overrides,
+    /// Optional dependencies by feature name, pulled in when a dependent enables the feature
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, Vec<Dependency>>,
+    /// NDK revision the project builds with, may leave off trailing components
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ndk: Option<String>,
+    /// The game version and devices the project is made for
+    #[serde(default, skip_serializing_if = "Target::is_empty")]
+    pub target: Target,
+    /// ABIs the project builds for and restores binaries of, see [`PackageConfig::abis`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub abis: Vec<String>,
+    /// How dependency headers are laid out in the includes folder
+    #[serde(default, skip_serializing_if = "IncludeLayout::is_flat")]
+    pub include_layout: IncludeLayout,
+    /// Folder names and binary arrangement within the dependencies dir
+    #[serde(default, skip_serializing_if = "ExternLayout::is_default")]
+    pub extern_layout: ExternLayout,
+    /// Generate qpm_build_info.hpp on every restore and build
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub build_info: bool,
+    /// Non-code files like textures or config templates that mods depending on this
+    /// package have to ship, `qpm qmod build` adds them to the .qmod
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<Asset>,
+    /// QMOD manifest version `qpm qmod build` writes, like 1.2.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qmod_schema_version: Option<String>,
+    /// Commands `qpm restore` runs around restoring, only the project's own
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// The files `qpm qmod zip` packs, instead of those mod.json lists
+    #[serde(default, skip_serializing_if = "QmodFiles::is_empty")]
+    pub qmod: QmodFiles,
+    #[serde(default)]
+    pub additional_data: AdditionalPackageData,
+    /// The `${NAME}` variables expanded while reading, put back when writing
+    #[serde(skip)]
+    pub interpolations: Interpolations,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_id")]
+    pub id: String,
+    pub version: String,
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// SPDX identifier or name of the license the package is published under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Link to the package's changelog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    /// What changed in this version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    #[serde(default)]
+    pub additional_data: AdditionalPackageData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdditionalPackageData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_linking: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub so_link: Option<String>,
+    /// Binaries for other ABIs than arm64-v8a, which `so_link` is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi_so_links: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug_so_link: Option<String>,
+    /// The `.a` of packages with `static_linking`, for arm64-v8a
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_link: Option<String>,
+    /// Static libraries for other ABIs than arm64-v8a
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi_static_links: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_so_name: Option<String>,
+    /// Binaries shipped besides the main one, like a loader stub or plugins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_binaries: Option<Vec<ExtraBinary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mod_link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_files: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_folder: Option<String>,
+    /// What everything including the package's headers has to be compiled with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compile_options: Option<CompileOptions>,
+    /// Game versions the package works with, exact versions or ranges. Resolution prefers
+    /// versions supporting the project's target game version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_versions: Option<Vec<String>>,
+}
+
+/// A binary a package ships besides its main one, cached and restored next to it
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraBinary {
+    /// The file name it is restored as, like `libloader.so`
+    pub name: String,
+    /// The binary for arm64-v8a
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub so_link: Option<String>,
+    /// Binaries for other ABIs than arm64-v8a
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi_so_links: Option<BTreeMap<String, String>>,
+    /// Whether dependents link against it, `false` for plugins only loaded at runtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<bool>,
+    /// Flags dependents linking against it need
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link_options: Vec<String>,
+}
+
+impl ExtraBinary {
+    pub fn is_linked(&self) -> bool {
+        self.link.unwrap_or(true)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.name.ends_with(".a")
+    }
+
+    /// Where it can be downloaded from for `abi`
+    pub fn link_for(&self, abi: &str) -> Option<&String> {
+        self.abi_so_links
+            .as_ref()
+            .and_then(|links| links.get(abi))
+            .or_else(|| self.so_link.as_ref().filter(|_| abi == DEFAULT_ABI))
+    }
+
+    /// Its part of the `qpm::<package>::<target>` CMake target: the file name without the
+    /// `lib` prefix and the extension
+    pub fn target_name(&self) -> &str {
+        let name = self.name.strip_prefix("lib").unwrap_or(&self.name);
+        name.split('.').next().unwrap_or(name)
+    }
+}
+
+/// Compiler settings a package requires from its dependents, restore merges them across
+/// the dependency tree into the generated build files
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompileOptions {
+    /// Folders of the package's headers to include as system headers, relative like `extraFiles`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub system_includes: Vec<String>,
+    /// ndk-build style C++ features, like `rtti` or `exceptions`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cpp_features: Vec<String>,
+    /// Extra compiler flags, like `-fdeclspec`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cpp_flags: Vec<String>,
+    /// Preprocessor defines, `NAME` or `NAME=value`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub defines: Vec<String>,
+    /// The oldest C++ standard the headers compile with, like 20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpp_standard: Option<u32>,
+}
+
+/// Where restore puts the headers of each dependency
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IncludeLayout {
+    /// Everything merged into `includes/`, headers with the same path overwrite each other
+    #[default]
+    Flat,
+    /// Each dependency in `includes/<id>/`, all of them on the include path
+    Namespaced,
+}
+
+impl IncludeLayout {
+    pub fn is_flat(&self) -> bool {
+        *self == IncludeLayout::Flat
+    }
+}
+
+/// What restore puts where in the dependencies dir. The generated build files follow it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExternLayout {
+    /// Folder the headers are restored to
+    pub includes_dir: String,
+    /// Folder the binaries are restored to
+    pub libs_dir: String,
+    pub binary_layout: BinaryLayout,
+}
+
+impl Default for ExternLayout {
+    fn default() -> ExternLayout {
+        ExternLayout {
+            includes_dir: "includes".to_string(),
+            libs_dir: "libs".to_string(),
+            binary_layout: BinaryLayout::default(),
+        }
+    }
+}
+
+/// Where restore puts the binaries of each dependency
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BinaryLayout {
+    /// Everything in `libs/<abi>/`
+    #[default]
+    Shared,
+    /// Each dependency in `libs/<id>/<abi>/`
+    PerPackage,
+}
+
+impl ExternLayout {
+    pub fn is_default(&self) -> bool {
+        *self == ExternLayout::default()
+    }
+
+    /// The folder holding the headers of `id`, relative to the dependencies dir
+    pub fn include_dir(&self, include_layout: IncludeLayout, id: &str) -> String {
+        match include_layout {
+            IncludeLayout::Flat => self.includes_dir.clone(),
+            IncludeLayout::Namespaced => format!("{}/{}", self.includes_dir, id),
+        }
+    }
+
+    /// The folder holding the binary of `id` for `abi`, relative to the dependencies dir.
+    /// `abi` may be a build system variable like `${ANDROID_ABI}`.
+    pub fn binary_dir(&self, id: &str, abi: &str) -> String {
+        match self.binary_layout {
+            BinaryLayout::Shared => format!("{}/{}", self.libs_dir, abi),
+            BinaryLayout::PerPackage => format!("{}/{}/{}", self.libs_dir, id, abi),
+        }
+    }
+
+    /// Rejects folder names that would leave the dependencies dir or overlap each other
+    pub fn validate(&self) -> Result<()> {
+        for (name, folder) in [
+            ("includesDir", &self.includes_dir),
+            ("libsDir", &self.libs_dir),
+        ] {
+            let path = Path::new(folder);
+            if folder.is_empty()
+                || path.is_absolute()
+                || path
+                    .components()
+                    .any(|c| !matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(Error::Validation(format!(
+                    "externLayout.{} must be a relative folder inside the dependencies dir, not {:?}",
+                    name, folder
+                )));
+            }
+        }
+        if self.includes_dir == self.libs_dir {
+            return Err(Error::Validation(
+                "externLayout.includesDir and libsDir must differ".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A file or folder of a package the mod manager copies onto the device
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    /// Relative to the package, a folder includes everything inside
+    pub path: String,
+    /// Where it ends up on the device, the folder of everything inside for a folder
+    pub destination: String,
+}
+
+/// Shell commands run in the project folder, with the resolved dependencies in `QPM_*`
+/// environment variables
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Hooks {
+    /// Run once qpm.shared.json is resolved, before the dependencies are restored
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pre_restore: Vec<String>,
+    /// Run after the dependencies and build files are restored
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_restore: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_restore.is_empty() && self.post_restore.is_empty()
+    }
+}
+
+/// The files of a .qmod, paths relative to the project. Each ends up in the .qmod under
+/// its file name.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QmodFiles {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mod_files: Vec<String>,
+    /// Loaded after the game's own libraries, by scotland2
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub late_mod_files: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub library_files: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_copies: Vec<QmodFileCopy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_image: Option<String>,
+}
+
+/// A file the mod manager copies to `destination` on the device
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QmodFileCopy {
+    pub path: String,
+    pub destination: String,
+}
+
+impl QmodFiles {
+    pub fn is_empty(&self) -> bool {
+        *self == QmodFiles::default()
+    }
+}
+
+/// What a project runs on, which decides the conditional dependencies it gets
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    /// The exact game version, like `1.28.0_4124311467`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    /// Devices like `quest1`, `quest2` or `quest3`, all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+}
+
+impl Target {
+    pub fn is_empty(&self) -> bool {
+        self.game_version.is_none() && self.platforms.is_empty()
+    }
+
+    /// The game version as semver, dropping the build number after `_`
+    pub fn parsed_game_version(&self) -> Option<Version> {
+        let game_version = self.game_version.as_ref()?;
+        let release = game_version.split('_').next().unwrap_or(game_version);
+        Version::parse(release).ok()
+    }
+
+    /// Whether a package declaring `game_versions`, exact versions or ranges, works with
+    /// the target's game version. Anything goes when either side leaves it open.
+    pub fn supports(&self, game_versions: Option<&[String]>) -> Result<bool> {
+        let (game_versions, version) = match (game_versions, self.parsed_game_version()) {
+            (Some(game_versions), Some(version)) if !game_versions.is_empty() => {
+                (game_versions, version)
+            }
+            _ => return Ok(true),
+        };
+        for supported in game_versions {
+            let release = supported.split('_').next().unwrap_or(supported);
+            let matches = match Version::parse(release) {
+                Ok(exact) => exact == version,
+                Err(_) => VersionReq::parse(supported)?.matches(&version),
+            };
+            if matches {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether a dependency restricted to `game_version` (a range) and `platforms` applies
+    /// to this target. Anything the target leaves open is allowed.
+    pub fn allows(&self, game_version: Option<&str>, platforms: Option<&[String]>) -> Result<bool> {
+        if let (Some(range), Some(version)) = (game_version, self.parsed_game_version()) {
+            if !VersionReq::parse(range)?.matches(&version) {
+                return Ok(false);
+            }
+        }
+        if let Some(platforms) = platforms.filter(|_| !self.platforms.is_empty()) {
+            if !platforms
+                .iter()
+                .any(|p| self.platforms.iter().any(|t| t.eq_ignore_ascii_case(p)))
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl PackageConfig {
+    /// The ABIs this project targets, only arm64-v8a unless it lists others
+    pub fn abis(&self) -> Vec<String> {
+        if self.abis.is_empty() {
+            vec![DEFAULT_ABI.to_string()]
+        } else {
+            self.abis.clone()
+        }
+    }
+
+    pub fn read(dir: &Path) -> Result<PackageConfig> {
+        PackageConfig::read_with(dir, true)
+    }
+
+    /// Reads a manifest downloaded from elsewhere, like a cached package's, leaving any
+    /// `${NAME}` in it as is. A published package could otherwise point its urls at
+    /// secrets from the environment.
+    pub fn read_fetched(dir: &Path) -> Result<PackageConfig> {
+        PackageConfig::read_with(dir, false)
+    }
+
+    fn read_with(dir: &Path, interpolate: bool) -> Result<PackageConfig> {
+        let path = dir.join(PACKAGE_FILE_NAME);
+        let mut value = schema::read_value(&path)?;
+        let migrated = migration::migrate_package(&mut value)?;
+        let interpolations = if interpolate {
+            interpolation::interpolate(&mut value, &path)?
+        } else {
+            Interpolations::default()
+        };
+        let mut package: PackageConfig =
+            schema::from_value(&path, value, &schema::package_config())?;
+        package.interpolations = interpolations;
+        package.resolve_local_paths(dir);
+        package.extern_layout.validate()?;
+        package.validate_aliases()?;
+        if migrated {
+            migration::write_upgraded(&path, &package.file_value()?)?;
+        }
+        Ok(package)
+    }
+
+    /// What the dependency `id` is called under extern and in extern.cmake: its `as` when
+    /// this package declares one, else the id
+    pub fn dependency_name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.dependencies
+            .iter()
+            .find(|d| d.id == id)
+            .and_then(|d| d.alias.as_deref())
+            .unwrap_or(id)
+    }
+
+    /// Points the package a dependency resolved to at the sources and binary this package
+    /// fetches it from instead: the `url` of its override, then the download settings of
+    /// its own spec for it
+    pub fn apply_sources(&self, shared: &mut SharedPackageConfig) {
+        let id = &shared.config.info.id;
+        let dependency = self.dependencies.iter().find(|d| d.id == *id);
+        if let Some(dependency_override) = self.overrides.get(id) {
+            dependency_override.apply_to_package(shared);
+        }
+        if let Some(dependency) = dependency {
+            dependency.additional_data.apply_sources(shared);
+        }
+    }
+
+    /// Rejects aliases that aren't valid names or that name two dependencies the same
+    pub fn validate_aliases(&self) -> Result<()> {
+        for dependency in &self.dependencies {
+            let alias = match &dependency.alias {
+                Some(alias) => alias,
+                None => continue,
+            };
+            validate_alias(alias)?;
+            if let Some(other) = self
+                .dependencies
+                .iter()
+                .find(|d| d.id != dependency.id && self.dependency_name(&d.id) == alias)
+            {
+                return Err(Error::Validation(format!(
+                    "{} is aliased as {}, which {} is already called",
+                    dependency.id, alias, other.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The manifest as written to disk, with its variables
+    pub fn file_value(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        self.interpolations.restore(&mut value);
+        Ok(value)
+    }
+
+    /// Resolves the local paths of the dependencies and overrides against `dir`, the
+    /// folder this manifest was read from
+    pub fn resolve_local_paths(&mut self, dir: &Path) {
+        let dependencies = self
+            .dependencies
+            .iter_mut()
+            .chain(self.features.values_mut().flatten());
+        for dependency in dependencies {
+            dependency.additional_data.resolve_local_path(dir);
+        }
+        for dependency_override in self.overrides.values_mut() {
+            dependency_override.resolved_local_path = dependency_override
+                .local_path
+                .as_ref()
+                .map(|local_path| resolve_local_path(local_path, dir));
+        }
+    }
+
+    /// A hash of the manifest's contents, independent of formatting. Taken with its
+    /// variables rather than what they expanded to, so it is the same on every machine.
+    pub fn content_hash(&self) -> String {
+        // back through the struct to keep its field order, which earlier hashes used
+        let written: PackageConfig = serde_json::from_value(self.file_value().unwrap()).unwrap();
+        fnv1a_hex(&serde_json::to_vec(&written).unwrap())
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        schema::write_value(&dir.join(PACKAGE_FILE_NAME), &self.file_value()?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{PackageConfig, PackageInfo, PACKAGE_FILE_NAME};
+    use crate::data::dependency::Dependency;
+
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qpm-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub fn sample() -> PackageConfig {
+        PackageConfig {
+            schema_version: 1,
+            shared_dir: "shared".to_string(),
+            dependencies_dir: "extern".to_string(),
+            info: PackageInfo {
+                name: "Sample".to_string(),
+                id: "sample".to_string(),
+                version: "1.2.0".to_string(),
+                ..Default::default()
+            },
+            dependencies: vec![
+                Dependency {
+                    id: "zeta".to_string(),
+                    version_range: "^0.3.0".to_string(),
+                    ..Default::default()
+                },
+                Dependency {
+                    id: "alpha".to_string(),
+                    version_range: "^1.0.0".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Keys sorted, two space indents and a trailing newline, dependencies in the order
+    /// they were written in
+    const SAMPLE_JSON: &str = r#"{
+  "additionalData": {},
+  "dependencies": [
+    {
+      "additionalData": {},
+      "id": "zeta",
+      "versionRange": "^0.3.0"
+    },
+    {
+      "additionalData": {},
+      "id": "alpha",
+      "versionRange": "^1.0.0"
+    }
+  ],
+  "dependenciesDir": "extern",
+  "info": {
+    "additionalData": {},
+    "id": "sample",
+    "name": "Sample",
+    "url": null,
+    "version": "1.2.0"
+  },
+  "schemaVersion": 1,
+  "sharedDir": "shared"
+}
+"#;
+
+    #[test]
+    fn qpm_json_is_byte_stable() {
+        let dir = temp_dir("package-write");
+        sample().write(&dir).unwrap();
+        let written = fs::read_to_string(dir.join(PACKAGE_FILE_NAME)).unwrap();
+        assert_eq!(written, SAMPLE_JSON);
+
+        // reading it back and writing it again changes nothing
+        PackageConfig::read(&dir).unwrap().write(&dir).unwrap();
+        let rewritten = fs::read_to_string(dir.join(PACKAGE_FILE_NAME)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(rewritten, SAMPLE_JSON);
+    }
+
+    #[test]
+    fn local_paths_stay_relative() {
+        let root = temp_dir("local-path");
+        let dir = root.join("app");
+        let mut package = sample();
+        package.dependencies[0].additional_data.local_path = Some("../lib".to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        package.write(&dir).unwrap();
+
+        let read = PackageConfig::read(&dir).unwrap();
+        let data = &read.dependencies[0].additional_data;
+        assert_eq!(data.local_path.as_deref(), Some("../lib"));
+        assert_eq!(
+            data.local_dir(),
+            Some(root.join("lib").canonicalize().unwrap())
+        );
+        let value = serde_json::to_value(&read).unwrap();
+        assert_eq!(
+            value["dependencies"][0]["additionalData"]["localPath"],
+            "../lib"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn content_hash_ignores_expanded_variables() {
+        let root = temp_dir("content-hash");
+        let dir = root.join("app");
+        fs::create_dir_all(&dir).unwrap();
+        let mut package = sample();
+        package.info.url = Some("${QPM_CACHE}/sample".to_string());
+        package.write(&dir).unwrap();
+
+        let hashes: Vec<_> = ["a", "b"]
+            .iter()
+            .map(|config_dir| {
+                std::env::set_var("QPM_CONFIG_DIR", root.join(config_dir));
+                let read = PackageConfig::read(&dir).unwrap();
+                assert!(read.info.url.as_ref().unwrap().contains(config_dir));
+                read.content_hash()
+            })
+            .collect();
+        std::env::remove_var("QPM_CONFIG_DIR");
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0], package.content_hash());
+    }
+}
@@ -0,0 +1,55 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{diagnostics, error::Result};
+
+/// What a restore did, written by `qpm restore --report` for CI to archive and compare
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub id: String,
+    pub version: String,
+    /// Whether qpm.shared.json had to be resolved again
+    pub resolved: bool,
+    pub dependencies: Vec<DependencyReport>,
+    pub warnings: Vec<String>,
+    pub bytes_downloaded: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyReport {
+    pub id: String,
+    pub version: String,
+    pub version_range: String,
+    /// `local`, `cache`, how the sources were downloaded (`git`, `zip` or `tar.gz`), or
+    /// `download` when only binaries were
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub cache_hit: bool,
+    /// Whether the files of the last restore were kept since nothing changed
+    #[serde(default)]
+    pub up_to_date: bool,
+    pub bytes_downloaded: u64,
+    pub duration_ms: u64,
+    /// Whether a binary was restored, by ABI
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub binaries: BTreeMap<String, bool>,
+}
+
+impl RestoreReport {
+    /// Prints a warning and keeps it for the report
+    pub fn warn(&mut self, warning: String) {
+        eprintln!("warning: {}", warning);
+        diagnostics::record(format!("warning: {}", warning));
+        self.warnings.push(warning);
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
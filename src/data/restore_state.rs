@@ -0,0 +1,185 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::config::LOCAL_CONFIG_DIR,
+    error::Result,
+    utils::{
+        fs::{files_below, write_atomic},
+        hash::fnv1a_hex,
+    },
+};
+
+pub const RESTORE_STATE_FILE_NAME: &str = "state.json";
+
+/// What the last restore put into the dependencies dir, kept in `.qpm/state.json` so the
+/// next one can leave the dependencies alone whose sources didn't change
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreState {
+    /// Fingerprint of the settings deciding where restored files go, the rest of the state
+    /// is void when it differs
+    pub layout: String,
+    pub dependencies: BTreeMap<String, RestoredFiles>,
+    /// How the restore left each file, relative to the dependencies dir. Kept by file
+    /// since dependencies in a flat includes folder can restore the same one.
+    pub files: BTreeMap<String, FileStamp>,
+}
+
+/// The files restored for one dependency
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredFiles {
+    /// Fingerprint of everything the files were copied from
+    pub sources: String,
+    /// Relative to the dependencies dir
+    pub files: BTreeSet<String>,
+}
+
+/// Size and modification time of a file, which change whenever its contents do in practice
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Milliseconds since the epoch
+    pub modified: u64,
+}
+
+impl FileStamp {
+    /// `None` when the file is missing
+    pub fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_millis() as u64);
+        Some(FileStamp {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+impl RestoreState {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(LOCAL_CONFIG_DIR).join(RESTORE_STATE_FILE_NAME)
+    }
+
+    /// An empty state when there is none or it can't be read, which restores everything
+    pub fn read(dir: &Path) -> RestoreState {
+        fs::read(RestoreState::path(dir))
+            .ok()
+            .and_then(|state| serde_json::from_slice(&state).ok())
+            .unwrap_or_default()
+    }
+
+    /// Stamps the files of all dependencies as they are now, once all of them are restored
+    pub fn stamp(&mut self, extern_dir: &Path) {
+        self.files = self
+            .dependencies
+            .values()
+            .flat_map(|restored| &restored.files)
+            .filter_map(|file| Some((file.clone(), FileStamp::of(&extern_dir.join(file))?)))
+            .collect();
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        write_atomic(&RestoreState::path(dir), &serde_json::to_vec_pretty(self)?)
+    }
+}
+
+impl RestoredFiles {
+    /// Records `file`, relative to the dependencies dir
+    pub fn record(&mut self, file: &Path) {
+        self.files.insert(key(file));
+    }
+
+    /// Whether all files are still there as `stamps` says the last restore left them
+    pub fn is_intact(&self, stamps: &BTreeMap<String, FileStamp>, extern_dir: &Path) -> bool {
+        self.files.iter().all(|file| {
+            stamps
+                .get(file)
+                .is_some_and(|stamp| FileStamp::of(&extern_dir.join(file)) == Some(*stamp))
+        })
+    }
+
+    /// Deletes the files, except those in `keep`, and the folders they leave empty
+    pub fn remove(&self, extern_dir: &Path, keep: &dyn Fn(&str) -> bool) -> Result<()> {
+        for file in self.files.iter().filter(|file| !keep(file)) {
+            let path = extern_dir.join(file);
+            if fs::symlink_metadata(&path).is_ok() {
+                fs::remove_file(&path)?;
+            }
+            let mut parent = path.parent();
+            while let Some(dir) = parent.filter(|dir| *dir != extern_dir) {
+                // fails on the first folder that still has something in it
+                if fs::remove_dir(dir).is_err() {
+                    break;
+                }
+                parent = dir.parent();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint of the files below each of `paths`, by path, size and modification time
+pub fn fingerprint(paths: &[PathBuf]) -> Result<String> {
+    let mut stamps = String::new();
+    for path in paths {
+        let files = if path.is_dir() {
+            files_below(path)?
+                .into_iter()
+                .map(|file| path.join(file))
+                .collect()
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            let stamp = FileStamp::of(&file);
+            stamps.push_str(&format!("{} {:?}\n", file.display(), stamp));
+        }
+    }
+    Ok(fnv1a_hex(stamps.as_bytes()))
+}
+
+/// Map keys use forward slashes on every platform
+pub fn key(file: &Path) -> String {
+    file.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{RestoreState, RestoredFiles};
+    use crate::data::package::tests::temp_dir;
+
+    #[test]
+    fn shared_file_stays_intact_for_both() {
+        let extern_dir = temp_dir("restore-state");
+        let mut state = RestoreState::default();
+        for (id, contents) in [("first", "a"), ("second", "bb")] {
+            fs::write(extern_dir.join("shared.hpp"), contents).unwrap();
+            let mut files = RestoredFiles::default();
+            files.record(Path::new("shared.hpp"));
+            state.dependencies.insert(id.to_string(), files);
+        }
+        state.stamp(&extern_dir);
+        for files in state.dependencies.values() {
+            assert!(files.is_intact(&state.files, &extern_dir));
+        }
+
+        fs::write(extern_dir.join("shared.hpp"), "changed").unwrap();
+        for files in state.dependencies.values() {
+            assert!(!files.is_intact(&state.files, &extern_dir));
+        }
+        fs::remove_dir_all(&extern_dir).unwrap();
+    }
+}
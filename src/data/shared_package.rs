@@ -0,0 +1,1011 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    build, build_files,
+    cache::{self, remote},
+    cancel,
+    data::{
+        config::Config,
+        dependency::{self, collect_all, Conflict, Constraint, Dependency, Pending},
+        extra_file::ExtraFile,
+        file_repository::FileRepository,
+        interpolation,
+        package::{IncludeLayout, PackageConfig, PACKAGE_FILE_NAME},
+        restore_report::{DependencyReport, RestoreReport},
+        restore_state::{self, RestoreState, RestoredFiles},
+    },
+    error::{Error, Result},
+    migration, ndk,
+    repository::Repository,
+    schema, signing, symbols,
+    utils::{
+        copy::{self, copy_tree, CopyOptions, CopyProgress},
+        fs::{dir_size, link_or_copy, remove_dir_if_exists},
+        hash::fnv1a_hex,
+        ignore::{IgnoreRules, IGNORE_FILE_NAME},
+    },
+    version::Version,
+};
+
+pub const SHARED_PACKAGE_FILE_NAME: &str = "qpm.shared.json";
+
+/// A package together with the exact versions its dependency tree resolved to
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPackageConfig {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub config: PackageConfig,
+    pub restored_dependencies: Vec<SharedDependency>,
+    /// Hash of the qpm.json this was resolved from, to notice when it went stale
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_hash: Option<String>,
+    /// Set by the registry on versions that must not be picked for new ranges anymore.
+    /// Lockfiles that already use them keep working.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
+    /// ssh signature over the rest of this config but the yanked and deprecated marks, made
+    /// by `qpm publish --sign`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Why a package shouldn't be used anymore and what to use instead
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Deprecation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Id of the package replacing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl SharedPackageConfig {
+    /// Warnings about this package version being yanked or deprecated
+    pub fn deprecation_warnings(&self) -> Vec<String> {
+        let info = &self.config.info;
+        let mut warnings = Vec::new();
+        if self.yanked {
+            warnings.push(format!(
+                "{} {} was yanked, upgrade to another version",
+                info.id, info.version
+            ));
+        }
+        if let Some(deprecation) = &self.deprecated {
+            let mut warning = format!("{} is deprecated", info.id);
+            if let Some(message) = &deprecation.message {
+                warning.push_str(&format!(": {}", message));
+            }
+            if let Some(replacement) = &deprecation.replacement {
+                warning.push_str(&format!(", use {} instead", replacement));
+            }
+            warnings.push(warning);
+        }
+        warnings
+    }
+}
+
+/// How the resolved version of a dependency differs between two shared configs
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyChange {
+    pub id: String,
+    /// `None` when the dependency was added
+    pub before: Option<String>,
+    /// `None` when the dependency was removed
+    pub after: Option<String>,
+    pub kind: ChangeKind,
+    /// The most significant version component that changed, like `minor`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<&'static str>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+impl SharedPackageConfig {
+    /// The dependencies resolved differently in `after`, by id
+    pub fn changes(&self, after: &SharedPackageConfig) -> Vec<DependencyChange> {
+        let versions = |shared: &SharedPackageConfig| -> BTreeMap<String, String> {
+            shared
+                .restored_dependencies
+                .iter()
+                .map(|d| (d.dependency.id.clone(), d.version.clone()))
+                .collect()
+        };
+        let (before, after) = (versions(self), versions(after));
+        let mut ids: Vec<&String> = before.keys().chain(after.keys()).collect();
+        ids.sort();
+        ids.dedup();
+        ids.into_iter()
+            .filter_map(|id| {
+                let (old, new) = (before.get(id), after.get(id));
+                let (kind, delta) = match (old, new) {
+                    (Some(old), Some(new)) if old == new => return None,
+                    (Some(old), Some(new)) => match (Version::parse(old), Version::parse(new)) {
+                        (Ok(old), Ok(new)) if new < old => {
+                            (ChangeKind::Downgraded, Some(version_delta(&new, &old)))
+                        }
+                        (Ok(old), Ok(new)) => {
+                            (ChangeKind::Upgraded, Some(version_delta(&old, &new)))
+                        }
+                        _ => (ChangeKind::Upgraded, None),
+                    },
+                    (None, _) => (ChangeKind::Added, None),
+                    (_, None) => (ChangeKind::Removed, None),
+                };
+                Some(DependencyChange {
+                    id: id.clone(),
+                    before: old.cloned(),
+                    after: new.cloned(),
+                    kind,
+                    delta,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The most significant component differing between `lower` and `higher`
+fn version_delta(lower: &Version, higher: &Version) -> &'static str {
+    if lower.major != higher.major {
+        "major"
+    } else if lower.minor != higher.minor {
+        "minor"
+    } else if lower.patch != higher.patch {
+        "patch"
+    } else if lower.pre != higher.pre {
+        "prerelease"
+    } else {
+        "build"
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedDependency {
+    pub dependency: Dependency,
+    pub version: String,
+}
+
+/// A dependency that is fetched and knows what to restore where
+struct Prepared {
+    shared: SharedPackageConfig,
+    entry: DependencyReport,
+    src: PathBuf,
+    package_includes: PathBuf,
+    /// (in the package, in its includes)
+    copies: Vec<(PathBuf, PathBuf)>,
+    /// (binary, in the dependencies dir)
+    binaries: Vec<(PathBuf, PathBuf)>,
+    /// Fingerprint of everything the files are copied from
+    sources: String,
+    /// Spent fetching it
+    elapsed: Duration,
+}
+
+/// Records that `id` restores `file` into the flat includes folder, warning when another
+/// dependency already put a file there
+fn claim_include(
+    owners: &mut BTreeMap<PathBuf, String>,
+    file: PathBuf,
+    id: &str,
+    report: &mut RestoreReport,
+) {
+    let display = file.display().to_string();
+    if let Some(previous) = owners.insert(file, id.to_string()) {
+        if previous != id {
+            report.warn(format!(
+                "{} of {} overwrites the one of {}, set includeLayout to namespaced to keep both",
+                display, id, previous
+            ));
+        }
+    }
+}
+
+/// The dependencies of `package` that apply to its target, to resolve from their ranges
+fn roots(package: &PackageConfig) -> Result<Vec<Pending>> {
+    Ok(applicable_dependencies(package)?
+        .into_iter()
+        .map(Pending::Range)
+        .collect())
+}
+
+fn applicable_dependencies(package: &PackageConfig) -> Result<Vec<Dependency>> {
+    let mut applicable = Vec::new();
+    for dependency in &package.dependencies {
+        if !dependency.applies_to(&package.target)? {
+            outln!(
+                "Skipping {}, it doesn't apply to this target",
+                dependency.id
+            );
+            continue;
+        }
+        applicable.push(dependency.clone());
+    }
+    Ok(applicable)
+}
+
+/// Deletes what the last restore put into the dependencies dir for `id`, except files
+/// another dependency restored as well
+fn remove_restored(
+    previous: &mut RestoreState,
+    next: &RestoreState,
+    id: &str,
+    extern_dir: &Path,
+) -> Result<()> {
+    if let Some(files) = previous.dependencies.remove(id) {
+        let restored_by_others = |file: &str| {
+            [&*previous, next].iter().any(|state| {
+                state
+                    .dependencies
+                    .values()
+                    .any(|other| other.files.contains(file))
+            })
+        };
+        files.remove(extern_dir, &restored_by_others)?;
+        // a file kept for another dependency may still have these contents, forgetting how
+        // it was left makes that one restore it again
+        for file in &files.files {
+            previous.files.remove(file);
+        }
+    }
+    Ok(())
+}
+
+/// Warns about dependencies made for another game version than the project targets
+fn warn_incompatible<'a>(
+    package: &PackageConfig,
+    collected: impl Iterator<Item = &'a SharedPackageConfig>,
+) {
+    let game_version = match &package.target.game_version {
+        Some(game_version) => game_version,
+        None => return,
+    };
+    for shared in collected {
+        let game_versions = shared.config.info.additional_data.game_versions.as_deref();
+        if !package.target.supports(game_versions).unwrap_or(true) {
+            eprintln!(
+                "warning: {} {} supports game versions {}, not {} the project targets",
+                shared.config.info.id,
+                shared.config.info.version,
+                game_versions.unwrap_or_default().join(" or "),
+                game_version
+            );
+            continue;
+        }
+        if let Some(other) = shared
+            .config
+            .target
+            .game_version
+            .as_ref()
+            .filter(|other| *other != game_version)
+        {
+            eprintln!(
+                "warning: {} {} is made for game version {}, the project targets {}",
+                shared.config.info.id, shared.config.info.version, other, game_version
+            );
+        }
+    }
+}
+
+impl SharedPackageConfig {
+    pub fn read(dir: &Path) -> Result<SharedPackageConfig> {
+        SharedPackageConfig::read_with(dir, true)
+    }
+
+    /// Reads a shared config downloaded from a registry, like a cached one, without
+    /// expanding variables, see [`PackageConfig::read_fetched`]
+    pub fn read_fetched(dir: &Path) -> Result<SharedPackageConfig> {
+        SharedPackageConfig::read_with(dir, false)
+    }
+
+    fn read_with(dir: &Path, interpolate: bool) -> Result<SharedPackageConfig> {
+        let path = dir.join(SHARED_PACKAGE_FILE_NAME);
+        let mut value = schema::read_value(&path)?;
+        let migrated = migration::migrate_shared_package(&mut value)?;
+        let interpolations = if interpolate {
+            interpolation::interpolate(&mut value, &path)?
+        } else {
+            Default::default()
+        };
+        let mut shared: SharedPackageConfig =
+            schema::from_value(&path, value, &schema::shared_package_config())?;
+        shared.config.interpolations = interpolations;
+        shared.config.resolve_local_paths(dir);
+        for restored in &mut shared.restored_dependencies {
+            restored.dependency.additional_data.resolve_local_path(dir);
+        }
+        if migrated {
+            migration::write_upgraded(&path, &shared.file_value()?)?;
+        }
+        Ok(shared)
+    }
+
+    /// The config as written to disk, with the variables of its manifest, which also
+    /// cover the local paths of its dependencies
+    pub fn file_value(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        self.config.interpolations.restore(&mut value);
+        Ok(value)
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        schema::write_value(&dir.join(SHARED_PACKAGE_FILE_NAME), &self.file_value()?)
+    }
+}
+
+impl SharedPackageConfig {
+    /// Resolves the dependencies of `package` against `repository` into a new shared config
+    pub fn from_package(
+        package: &PackageConfig,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<SharedPackageConfig> {
+        let collected = collect_all(
+            roots(package)?,
+            repository,
+            config,
+            &package.overrides,
+            &package.target,
+        )?;
+        warn_incompatible(package, collected.values());
+        Ok(SharedPackageConfig {
+            schema_version: migration::SCHEMA_VERSION,
+            config: package.clone(),
+            restored_dependencies: collected.into_keys().collect(),
+            package_hash: Some(package.content_hash()),
+            ..Default::default()
+        })
+    }
+
+    /// Resolves `package` again keeping every dependency at the version `locked` has it at,
+    /// except `ids` and what they pull in, which resolve from their ranges like a fresh
+    /// resolve. Dependencies new to qpm.json resolve too, and locked versions no longer in
+    /// their range. `ids` may also name dependencies of dependencies.
+    pub fn update(
+        package: &PackageConfig,
+        locked: &SharedPackageConfig,
+        ids: &[String],
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<SharedPackageConfig> {
+        for id in ids {
+            let known = package.dependencies.iter().any(|d| d.id == *id)
+                || locked
+                    .restored_dependencies
+                    .iter()
+                    .any(|d| d.dependency.id == *id);
+            if !known {
+                return Err(Error::Usage(format!("{} is not a dependency", id)));
+            }
+        }
+        let mut roots = Vec::new();
+        for dependency in applicable_dependencies(package)? {
+            let lock = locked
+                .restored_dependencies
+                .iter()
+                .find(|d| d.dependency.id == dependency.id)
+                .filter(|_| !ids.contains(&dependency.id))
+                .filter(|lock| {
+                    let version = Version::parse(&lock.version);
+                    dependency.version_req().is_ok_and(|req| {
+                        version.is_ok_and(|v| req.matches_with_prerelease(&v, true))
+                    })
+                });
+            roots.push(match lock {
+                Some(lock) => Pending::Locked(SharedDependency {
+                    dependency,
+                    version: lock.version.clone(),
+                }),
+                None => Pending::Range(dependency),
+            });
+        }
+        // a dependency of a dependency moves within the ranges everything asks for
+        for lock in &locked.restored_dependencies {
+            let id = &lock.dependency.id;
+            if ids.contains(id) && !package.dependencies.iter().any(|d| d.id == *id) {
+                roots.push(Pending::Range(lock.dependency.clone()));
+            }
+        }
+        let collected = collect_all(
+            roots,
+            repository,
+            config,
+            &package.overrides,
+            &package.target,
+        )?;
+        warn_incompatible(package, collected.values());
+        Ok(SharedPackageConfig {
+            schema_version: migration::SCHEMA_VERSION,
+            config: package.clone(),
+            restored_dependencies: collected.into_keys().collect(),
+            package_hash: Some(package.content_hash()),
+            ..Default::default()
+        })
+    }
+
+    /// The ranges every package in the dependency tree of `package` is required with and
+    /// what they intersect to, for tooling deciding which versions to publish or allow
+    pub fn constraints(
+        package: &PackageConfig,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<Vec<Constraint>> {
+        dependency::constraints(
+            roots(package)?,
+            repository,
+            config,
+            &package.overrides,
+            &package.target,
+        )
+    }
+
+    /// The version conflict resolving `package` runs into, `None` when it resolves
+    pub fn find_conflict(
+        package: &PackageConfig,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<Option<Conflict>> {
+        dependency::find_conflict(
+            roots(package)?,
+            repository,
+            config,
+            &package.overrides,
+            &package.target,
+        )
+    }
+
+    /// Whether this was resolved from `package` as it is now
+    pub fn is_fresh(&self, package: &PackageConfig) -> bool {
+        match &self.package_hash {
+            Some(hash) => *hash == package.content_hash(),
+            None => self.config == *package,
+        }
+    }
+
+    /// The shared config in `dir`, re-resolved and rewritten when it is missing or qpm.json
+    /// changed since it was generated. With `frozen` a stale or missing file is an error.
+    pub fn read_fresh(
+        dir: &Path,
+        package: &PackageConfig,
+        repository: &dyn Repository,
+        config: &Config,
+        frozen: bool,
+    ) -> Result<SharedPackageConfig> {
+        let (shared, resolved) =
+            SharedPackageConfig::resolve_fresh(dir, package, repository, config, frozen)?;
+        if resolved {
+            shared.write(dir)?;
+        }
+        Ok(shared)
+    }
+
+    /// Like [`SharedPackageConfig::read_fresh`] but never writes, also telling whether the
+    /// dependencies had to be resolved again
+    pub fn resolve_fresh(
+        dir: &Path,
+        package: &PackageConfig,
+        repository: &dyn Repository,
+        config: &Config,
+        frozen: bool,
+    ) -> Result<(SharedPackageConfig, bool)> {
+        // dependencies linked with `qpm link` resolve to their working copies
+        let package = &FileRepository::read()?.apply_links(dir, package);
+        let existing = if dir.join(SHARED_PACKAGE_FILE_NAME).exists() {
+            Some(SharedPackageConfig::read(dir)?)
+        } else {
+            None
+        };
+        match existing {
+            Some(shared) if shared.is_fresh(package) => return Ok((shared, false)),
+            Some(_) if frozen => {
+                return Err(Error::Validation(format!(
+                    "{} is out of date with {}, run qpm collect",
+                    SHARED_PACKAGE_FILE_NAME, PACKAGE_FILE_NAME
+                )))
+            }
+            None if frozen => {
+                return Err(Error::Validation(format!(
+                    "{} is missing, run qpm collect",
+                    SHARED_PACKAGE_FILE_NAME
+                )))
+            }
+            Some(_) => outln!(
+                "{} changed since {} was generated, resolving again",
+                PACKAGE_FILE_NAME,
+                SHARED_PACKAGE_FILE_NAME
+            ),
+            None => {}
+        }
+        Ok((
+            SharedPackageConfig::from_package(package, repository, config)?,
+            true,
+        ))
+    }
+
+    /// Errors when two restored dependencies would share folders under extern and a CMake
+    /// target, which happens when one is aliased as the id of another
+    fn check_names(&self) -> Result<()> {
+        let mut names = BTreeMap::new();
+        for restored in &self.restored_dependencies {
+            let id = &restored.dependency.id;
+            let name = self.config.dependency_name(id);
+            if let Some(other) = names.insert(name, id) {
+                return Err(Error::Validation(format!(
+                    "{} and {} would both be restored as {}, give one of them another \"as\" in {}",
+                    other, id, name, PACKAGE_FILE_NAME
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the headers and binaries of every restored dependency into the dependencies
+    /// dir and regenerates the build files. A dependency whose sources and restored files
+    /// didn't change since the last restore, as recorded in `.qpm/state.json`, is left as it
+    /// is. The files of any other dependency, and of removed ones, are deleted first so
+    /// nothing of an old version survives.
+    pub fn restore(
+        &self,
+        dir: &Path,
+        repository: &dyn Repository,
+        config: &Config,
+    ) -> Result<RestoreReport> {
+        let mut report = RestoreReport {
+            id: self.config.info.id.clone(),
+            version: self.config.info.version.clone(),
+            ..Default::default()
+        };
+        let extern_dir = dir.join(&self.config.dependencies_dir);
+        let layout = &self.config.extern_layout;
+        let includes = extern_dir.join(&layout.includes_dir);
+        let libs = extern_dir.join(&layout.libs_dir);
+        let abis = self.config.abis();
+        let namespaced = self.config.include_layout == IncludeLayout::Namespaced;
+
+        let mut previous = RestoreState::read(dir);
+        let mut next = RestoreState {
+            layout: fnv1a_hex(
+                serde_json::to_string(&(
+                    &self.config.dependencies_dir,
+                    layout,
+                    namespaced,
+                    &abis,
+                    config.symlink,
+                ))?
+                .as_bytes(),
+            ),
+            ..Default::default()
+        };
+        // files restored with other settings are somewhere the state doesn't know about
+        if previous.layout != next.layout || !includes.exists() {
+            remove_dir_if_exists(&includes)?;
+            remove_dir_if_exists(&libs)?;
+            previous = RestoreState::default();
+        }
+        remove_dir_if_exists(&extern_dir.join(symbols::SYMBOLS_DIR))?;
+        let removed: Vec<String> = previous
+            .dependencies
+            .keys()
+            .filter(|id| {
+                !self
+                    .restored_dependencies
+                    .iter()
+                    .any(|d| d.dependency.id == **id)
+            })
+            .cloned()
+            .collect();
+        for id in removed {
+            remove_restored(&mut previous, &next, &id, &extern_dir)?;
+        }
+        self.check_names()?;
+        // binary folders are only created once a dependency has a binary for them
+        fs::create_dir_all(&includes)?;
+
+        let mut prepared = Vec::new();
+        let mut restored = Vec::new();
+        // which dependency put each file into a flat includes folder
+        let mut header_owners = BTreeMap::new();
+        for dependency in &self.restored_dependencies {
+            cancel::check()?;
+            let started = Instant::now();
+            let mut shared = dependency.get_shared_package(repository, config)?;
+            if dependency.dependency.additional_data.local_path.is_none() {
+                if let Some(warning) = signing::check(&shared, config)? {
+                    report.warn(warning);
+                }
+            }
+            self.config.apply_sources(&mut shared);
+            for warning in shared.deprecation_warnings() {
+                report.warn(warning);
+            }
+            let info = &shared.config.info;
+            let headers_only = cache::is_headers_only(&shared.config);
+            let mut entry = DependencyReport {
+                id: info.id.clone(),
+                version: info.version.clone(),
+                version_range: dependency.dependency.version_range.clone(),
+                url: info.url.clone(),
+                ..Default::default()
+            };
+            let src = match dependency.dependency.additional_data.local_dir() {
+                Some(local_dir) => {
+                    entry.source = "local".into();
+                    local_dir
+                }
+                None => {
+                    let src = cache::src_dir(config, &info.id, &info.version);
+                    let package_dir = cache::package_dir(config, &info.id, &info.version);
+                    entry.cache_hit = src.exists()
+                        && (headers_only
+                            || abis.iter().all(|abi| {
+                                cache::cached_binary(config, &shared.config, abi).is_some()
+                                    && cache::extra_binaries(&shared.config).iter().all(|b| {
+                                        b.link_for(abi).is_none()
+                                            || cache::cached_extra_binary(
+                                                config,
+                                                &shared.config,
+                                                b,
+                                                abi,
+                                            )
+                                            .is_some()
+                                    })
+                            }));
+                    let size_before = dir_size(&package_dir).unwrap_or(0);
+                    let fetched = cache::ensure_cached(
+                        &shared,
+                        &dependency.dependency.additional_data,
+                        &abis,
+                        config,
+                    )?;
+                    // nothing was published for these, the binary cache or opted in
+                    // builds fill the gaps
+                    let (mut from_binary_cache, mut built) = (false, false);
+                    for abi in abis.iter().filter(|_| !headers_only) {
+                        if cache::cached_binary(config, &shared.config, abi).is_some() {
+                            continue;
+                        }
+                        if remote::fetch(config, &shared.config, abi)?.is_some() {
+                            from_binary_cache = true;
+                        } else if config.build_from_source {
+                            let binary =
+                                build::build_from_source(&shared, abi, repository, config)?;
+                            remote::push(config, &shared.config, abi, &binary)?;
+                            built = true;
+                        }
+                    }
+                    entry.source = match fetched {
+                        _ if built => "build",
+                        _ if from_binary_cache => "binary cache",
+                        Some(method) => method,
+                        None if entry.cache_hit => "cache",
+                        None => "download",
+                    }
+                    .into();
+                    entry.bytes_downloaded = dir_size(&package_dir)
+                        .unwrap_or(0)
+                        .saturating_sub(size_before);
+                    src
+                }
+            };
+
+            let name = self.config.dependency_name(&info.id);
+            let package_includes = if namespaced {
+                includes.join(name)
+            } else {
+                includes.clone()
+            };
+            // (in the package, in its includes): the shared dir's contents, then extra files
+            let mut copies = Vec::new();
+            if src.join(&shared.config.shared_dir).exists() {
+                copies.push((PathBuf::from(&shared.config.shared_dir), PathBuf::new()));
+            }
+            let extra_files = info
+                .additional_data
+                .extra_files
+                .iter()
+                .chain(dependency.dependency.additional_data.extra_files.iter())
+                .flatten();
+            for extra in extra_files {
+                let matched = ExtraFile::parse(extra)?.resolve(&src)?;
+                if matched.is_empty() {
+                    report.warn(format!(
+                        "extra file {} of {} does not exist",
+                        extra, info.id
+                    ));
+                }
+                copies.extend(matched);
+            }
+            // (binary, in the dependencies dir)
+            let mut binaries = Vec::new();
+            if !headers_only {
+                let so_name = cache::so_name(&shared.config);
+                for abi in &abis {
+                    let so = match dependency.dependency.additional_data.local_dir() {
+                        Some(local_dir) => cache::local_binary(&local_dir, abi, &so_name),
+                        None => cache::cached_binary(config, &shared.config, abi),
+                    };
+                    entry.binaries.insert(abi.clone(), so.is_some());
+                    match so {
+                        Some(so) => binaries
+                            .push((so, Path::new(&layout.binary_dir(name, abi)).join(&so_name))),
+                        None => report.warn(format!("{} has no {} binary", info.id, abi)),
+                    }
+                }
+                for binary in cache::extra_binaries(&shared.config) {
+                    for abi in &abis {
+                        let found = match dependency.dependency.additional_data.local_dir() {
+                            Some(local_dir) => cache::local_binary(&local_dir, abi, &binary.name),
+                            None => cache::cached_extra_binary(config, &shared.config, binary, abi),
+                        };
+                        match found {
+                            Some(found) => binaries.push((
+                                found,
+                                Path::new(&layout.binary_dir(name, abi)).join(&binary.name),
+                            )),
+                            None => report
+                                .warn(format!("{} has no {} binary {}", info.id, abi, binary.name)),
+                        }
+                    }
+                }
+            }
+
+            let mut sources: Vec<PathBuf> = copies.iter().map(|(from, _)| src.join(from)).collect();
+            sources.push(src.join(IGNORE_FILE_NAME));
+            sources.extend(binaries.iter().map(|(so, _)| so.clone()));
+            // where the files go matters as much as what they are
+            let sources = fnv1a_hex(
+                format!(
+                    "{:?} {:?} {:?} {}",
+                    package_includes,
+                    copies,
+                    binaries,
+                    restore_state::fingerprint(&sources)?
+                )
+                .as_bytes(),
+            );
+            prepared.push(Prepared {
+                shared,
+                entry,
+                src,
+                package_includes,
+                copies,
+                binaries,
+                sources,
+                elapsed: started.elapsed(),
+            });
+        }
+
+        // a file several dependencies restore holds what the last of them copied, so when
+        // one of them is restored again the others have to be too
+        let mut stale: BTreeSet<String> = prepared
+            .iter()
+            .filter(|p| {
+                !previous
+                    .dependencies
+                    .get(&p.shared.config.info.id)
+                    .is_some_and(|files| {
+                        files.sources == p.sources && files.is_intact(&previous.files, &extern_dir)
+                    })
+            })
+            .map(|p| p.shared.config.info.id.clone())
+            .collect();
+        loop {
+            let shares_with_stale = |id: &String| {
+                previous.dependencies.get(id).is_some_and(|files| {
+                    stale.iter().any(|other| {
+                        previous
+                            .dependencies
+                            .get(other)
+                            .is_some_and(|other| !files.files.is_disjoint(&other.files))
+                    })
+                })
+            };
+            let more: Vec<String> = prepared
+                .iter()
+                .map(|p| &p.shared.config.info.id)
+                .filter(|id| !stale.contains(*id) && shares_with_stale(id))
+                .cloned()
+                .collect();
+            if more.is_empty() {
+                break;
+            }
+            stale.extend(more);
+        }
+
+        for Prepared {
+            shared,
+            mut entry,
+            src,
+            package_includes,
+            copies,
+            binaries,
+            sources,
+            elapsed,
+        } in prepared
+        {
+            cancel::check()?;
+            let started = Instant::now();
+            let info = &shared.config.info;
+            let files = if !stale.contains(&info.id) {
+                entry.up_to_date = true;
+                previous.dependencies.remove(&info.id).unwrap_or_default()
+            } else {
+                remove_restored(&mut previous, &next, &info.id, &extern_dir)?;
+                let mut files = RestoredFiles {
+                    sources,
+                    ..Default::default()
+                };
+                let relative_includes = package_includes
+                    .strip_prefix(&extern_dir)
+                    .unwrap_or(&package_includes);
+                let ignore = IgnoreRules::load(&src)?;
+                for (from, to) in copies {
+                    let copied = RefCell::new(Vec::new());
+                    let filter = copy::ignoring(&ignore, &from);
+                    let record =
+                        |progress: CopyProgress| copied.borrow_mut().push(to.join(progress.path));
+                    let options = CopyOptions::new().filter(&filter).progress(&record);
+                    copy_tree(&src.join(&from), &package_includes.join(&to), &options)?;
+                    for file in copied.into_inner() {
+                        files.record(&relative_includes.join(file));
+                    }
+                }
+                for (so, dest) in &binaries {
+                    if let Some(parent) = extern_dir.join(dest).parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    link_or_copy(so, &extern_dir.join(dest), config.symlink)?;
+                    files.record(dest);
+                }
+                files
+            };
+            if !namespaced {
+                for file in &files.files {
+                    if let Ok(header) = Path::new(file).strip_prefix(&layout.includes_dir) {
+                        claim_include(
+                            &mut header_owners,
+                            header.to_path_buf(),
+                            &info.id,
+                            &mut report,
+                        );
+                    }
+                }
+            }
+            next.dependencies.insert(info.id.clone(), files);
+
+            entry.duration_ms = (elapsed + started.elapsed()).as_millis() as u64;
+            report.bytes_downloaded += entry.bytes_downloaded;
+            report.dependencies.push(entry);
+            restored.push(shared);
+        }
+
+        let ndk = match ndk::resolve(&self.config, config) {
+            Ok(ndk) => ndk.map(|ndk| ndk.path),
+            Err(e) => {
+                report.warn(e.to_string());
+                None
+            }
+        };
+        build_files::write_extern_cmake(dir, &self.config, &restored, ndk.as_deref())?;
+        build_files::write_extern_mk(dir, &self.config, &restored)?;
+        build_files::write_ndk_path(dir, ndk.as_deref())?;
+        next.stamp(&extern_dir);
+        next.write(dir)?;
+        // stable order so reports of different runs can be diffed
+        report.dependencies.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{SharedPackageConfig, SHARED_PACKAGE_FILE_NAME};
+    use crate::{
+        data::{
+            config::Config,
+            package::tests::{sample, temp_dir},
+        },
+        repository::memory::MemoryRepository,
+    };
+
+    /// Resolved dependencies sorted by id whatever order qpm.json lists them in
+    const SAMPLE_JSON: &str = r#"{
+  "config": {
+    "additionalData": {},
+    "dependencies": [
+      {
+        "additionalData": {},
+        "id": "zeta",
+        "versionRange": "^0.3.0"
+      },
+      {
+        "additionalData": {},
+        "id": "alpha",
+        "versionRange": "^1.0.0"
+      }
+    ],
+    "dependenciesDir": "extern",
+    "info": {
+      "additionalData": {},
+      "id": "sample",
+      "name": "Sample",
+      "url": null,
+      "version": "1.2.0"
+    },
+    "schemaVersion": 1,
+    "sharedDir": "shared"
+  },
+  "packageHash": "e2e966dc69156ef0",
+  "restoredDependencies": [
+    {
+      "dependency": {
+        "additionalData": {},
+        "id": "alpha",
+        "versionRange": "^1.0.0"
+      },
+      "version": "1.0.0"
+    },
+    {
+      "dependency": {
+        "additionalData": {},
+        "id": "zeta",
+        "versionRange": "^0.3.0"
+      },
+      "version": "0.3.0"
+    }
+  ],
+  "schemaVersion": 1
+}
+"#;
+
+    #[test]
+    fn qpm_shared_json_is_byte_stable() {
+        let dir = temp_dir("shared-package-write");
+        let mut repository = MemoryRepository::new();
+        for (id, version) in [("alpha", "1.0.0"), ("zeta", "0.3.0")] {
+            let mut published = SharedPackageConfig::default();
+            published.config.info.id = id.to_string();
+            published.config.info.version = version.to_string();
+            repository.insert(published);
+        }
+        let config = Config {
+            cache_path: dir.join("cache").display().to_string(),
+            ..Default::default()
+        };
+        let shared = SharedPackageConfig::from_package(&sample(), &repository, &config).unwrap();
+        shared.write(&dir).unwrap();
+        let written = fs::read_to_string(dir.join(SHARED_PACKAGE_FILE_NAME)).unwrap();
+        assert_eq!(written, SAMPLE_JSON);
+
+        // reading it back and writing it again changes nothing
+        SharedPackageConfig::read(&dir)
+            .unwrap()
+            .write(&dir)
+            .unwrap();
+        let rewritten = fs::read_to_string(dir.join(SHARED_PACKAGE_FILE_NAME)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(rewritten, SAMPLE_JSON);
+    }
+}
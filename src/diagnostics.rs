@@ -0,0 +1,127 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    env, fs,
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    process::Command,
+    sync::Mutex,
+};
+
+use crate::{
+    cache,
+    data::{package::PACKAGE_FILE_NAME, shared_package::SHARED_PACKAGE_FILE_NAME},
+    logging,
+    self_update::CURRENT_VERSION,
+};
+
+const ISSUES_URL: &str = "https://github.com/sc2ad/QuestPackageManager-Rust/issues";
+/// How many of the latest messages a crash report includes
+const RECENT_LINES: usize = 50;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Keeps `line` for the report of a crash later in this run
+pub fn record(line: impl Into<String>) {
+    let line = line.into();
+    crate::logging::debug("message", &line);
+    if let Ok(mut recent) = RECENT.lock() {
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+}
+
+/// Makes a panic write a report with everything needed to reproduce it to a temp file and
+/// say where it is, instead of just the panic message
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let backtrace = Backtrace::force_capture();
+        eprintln!("qpm crashed: {}", message(info));
+        match write_report(info, &backtrace) {
+            Ok(path) => eprintln!(
+                "A diagnostic report was written to {}, please attach it when filing an issue at {}",
+                path.display(),
+                ISSUES_URL
+            ),
+            // the report is lost, at least show where it happened
+            Err(e) => eprintln!(
+                "Writing the diagnostic report failed ({}), please include this when filing an issue at {}:\n{}",
+                e, ISSUES_URL, backtrace
+            ),
+        }
+    }));
+}
+
+fn message(info: &PanicHookInfo) -> String {
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into());
+    match info.location() {
+        Some(location) => format!("{} at {}", payload, location),
+        None => payload,
+    }
+}
+
+fn write_report(info: &PanicHookInfo, backtrace: &Backtrace) -> std::io::Result<PathBuf> {
+    let mut report = format!(
+        "qpm {} crashed: {}\n\n## Command\n{}\n\n## System\n{} {}, {}\nworking directory: {}\n",
+        CURRENT_VERSION,
+        message(info),
+        logging::redact_command_line(&env::args().collect::<Vec<_>>()),
+        env::consts::OS,
+        env::consts::ARCH,
+        os_version(),
+        env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default(),
+    );
+    if let Ok(dir) = env::current_dir() {
+        for file in [PACKAGE_FILE_NAME, SHARED_PACKAGE_FILE_NAME] {
+            if let Ok(contents) = fs::read_to_string(dir.join(file)) {
+                report.push_str(&format!("\n## {}\n{}\n", file, contents.trim_end()));
+            }
+        }
+    }
+    report.push_str("\n## Recent messages\n");
+    // the panic may have happened while recording a message
+    match RECENT.try_lock() {
+        Ok(recent) if recent.is_empty() => report.push_str("(none)\n"),
+        Ok(recent) => {
+            for line in recent.iter() {
+                report.push_str(line);
+                report.push('\n');
+            }
+        }
+        Err(_) => report.push_str("(unavailable)\n"),
+    }
+    report.push_str(&format!("\n## Backtrace\n{}\n", backtrace));
+
+    let path = env::temp_dir().join(format!(
+        "qpm-crash-{}-{}.txt",
+        cache::now(),
+        std::process::id()
+    ));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Best effort description of the OS release, empty when there is no way to tell
+fn os_version() -> String {
+    let (program, args): (&str, &[&str]) = if cfg!(windows) {
+        ("cmd", &["/C", "ver"])
+    } else {
+        ("uname", &["-srv"])
+    };
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default()
+}
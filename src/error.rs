@@ -0,0 +1,90 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Network(String),
+    Git(String),
+    Build(String),
+    Adb(String),
+    Resolution(String),
+    Usage(String),
+    Validation(String),
+    /// Something in the cache or the local repository is damaged
+    Cache(String),
+    /// A version was published before with other contents
+    Conflict(String),
+    /// Ctrl-C was pressed
+    Cancelled,
+}
+
+/// What the CLI exits with per failure class, for scripts and CI to branch on
+pub mod exit_code {
+    /// Anything without a class of its own, like io, git or build failures
+    pub const FAILURE: i32 = 1;
+    /// Bad arguments or a command that doesn't apply to the project
+    pub const USAGE: i32 = 2;
+    /// The dependency ranges can't be satisfied together
+    pub const RESOLUTION: i32 = 3;
+    pub const NETWORK: i32 = 4;
+    pub const CACHE_CORRUPTION: i32 = 5;
+    /// A manifest or a setting is invalid
+    pub const VALIDATION: i32 = 6;
+    pub const PUBLISH_CONFLICT: i32 = 7;
+    /// Interrupted with Ctrl-C, like shells report it
+    pub const CANCELLED: i32 = 130;
+}
+
+impl Error {
+    /// The code the CLI exits with for this error, see [`exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => exit_code::USAGE,
+            Error::Resolution(_) => exit_code::RESOLUTION,
+            Error::Network(_) => exit_code::NETWORK,
+            Error::Cache(_) => exit_code::CACHE_CORRUPTION,
+            Error::Validation(_) => exit_code::VALIDATION,
+            Error::Conflict(_) => exit_code::PUBLISH_CONFLICT,
+            Error::Cancelled => exit_code::CANCELLED,
+            Error::Io(_) | Error::Json(_) | Error::Git(_) | Error::Build(_) | Error::Adb(_) => {
+                exit_code::FAILURE
+            }
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Json(e) => write!(f, "json error: {}", e),
+            Error::Network(msg) => write!(f, "network error: {}", msg),
+            Error::Git(msg) => write!(f, "git error: {}", msg),
+            Error::Build(msg) => write!(f, "build error: {}", msg),
+            Error::Adb(msg) => write!(f, "adb error: {}", msg),
+            Error::Resolution(msg) => write!(f, "resolution error: {}", msg),
+            Error::Usage(msg) => write!(f, "{}", msg),
+            Error::Validation(msg) => write!(f, "{}", msg),
+            Error::Cache(msg) => write!(f, "cache error: {}", msg),
+            Error::Conflict(msg) => write!(f, "{}", msg),
+            Error::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -0,0 +1,142 @@
+use std::{path::Path, process::Command};
+
+use crate::{
+    data::{package::Hooks, restore_report::RestoreReport, shared_package::SharedPackageConfig},
+    error::{Error, Result},
+};
+
+/// When a hook runs, passed to it as `QPM_HOOK`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    PreRestore,
+    PostRestore,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::PreRestore => "preRestore",
+            Stage::PostRestore => "postRestore",
+        }
+    }
+}
+
+/// Runs the `hooks` for `stage` in `dir`, in order, stopping at the first failing one.
+/// `report` is only there after restoring.
+pub fn run(
+    hooks: &Hooks,
+    stage: Stage,
+    dir: &Path,
+    shared: &SharedPackageConfig,
+    resolved: bool,
+    report: Option<&RestoreReport>,
+) -> Result<()> {
+    let commands = match stage {
+        Stage::PreRestore => &hooks.pre_restore,
+        Stage::PostRestore => &hooks.post_restore,
+    };
+    if commands.is_empty() {
+        return Ok(());
+    }
+    let env = environment(stage, dir, shared, resolved, report);
+    for command in commands {
+        outln!("Running {} hook: {}", stage.name(), command);
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let status = shell
+            .arg(command)
+            .current_dir(dir)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .status()
+            .map_err(|e| Error::Build(format!("failed to run the {} hook: {}", stage.name(), e)))?;
+        if !status.success() {
+            return Err(Error::Build(format!(
+                "the {} hook `{}` failed with {}",
+                stage.name(),
+                command,
+                status
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// What hooks get to know about the restore
+fn environment(
+    stage: Stage,
+    dir: &Path,
+    shared: &SharedPackageConfig,
+    resolved: bool,
+    report: Option<&RestoreReport>,
+) -> Vec<(String, String)> {
+    let package = &shared.config;
+    let extern_dir = dir.join(&package.dependencies_dir);
+    let layout = &package.extern_layout;
+    let mut env = vec![
+        ("QPM_HOOK".to_string(), stage.name().to_string()),
+        ("QPM_PROJECT_DIR".to_string(), dir.display().to_string()),
+        ("QPM_PACKAGE_ID".to_string(), package.info.id.clone()),
+        (
+            "QPM_PACKAGE_VERSION".to_string(),
+            package.info.version.clone(),
+        ),
+        (
+            "QPM_DEPENDENCIES_DIR".to_string(),
+            extern_dir.display().to_string(),
+        ),
+        (
+            "QPM_INCLUDES_DIR".to_string(),
+            extern_dir.join(&layout.includes_dir).display().to_string(),
+        ),
+        (
+            "QPM_LIBS_DIR".to_string(),
+            extern_dir.join(&layout.libs_dir).display().to_string(),
+        ),
+        (
+            "QPM_RESOLVED".to_string(),
+            if resolved { "1" } else { "0" }.to_string(),
+        ),
+    ];
+    // `id@version` separated by spaces, and the version of each on its own
+    let mut dependencies = Vec::new();
+    for restored in &shared.restored_dependencies {
+        let id = &restored.dependency.id;
+        dependencies.push(format!("{}@{}", id, restored.version));
+        env.push((
+            format!("QPM_DEP_{}_VERSION", env_name(id)),
+            restored.version.clone(),
+        ));
+    }
+    env.push(("QPM_DEPENDENCIES".to_string(), dependencies.join(" ")));
+    if let Some(report) = report {
+        // the dependencies whose files changed in this restore
+        let changed: Vec<&str> = report
+            .dependencies
+            .iter()
+            .filter(|d| !d.up_to_date)
+            .map(|d| d.id.as_str())
+            .collect();
+        env.push(("QPM_CHANGED".to_string(), changed.join(" ")));
+    }
+    env
+}
+
+/// `id` as part of an environment variable name, like `BEATSABER_HOOK` for `beatsaber-hook`
+fn env_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
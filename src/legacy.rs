@@ -0,0 +1,100 @@
+use serde_json::{json, Value};
+
+use crate::{
+    error::Result,
+    migration::{self, camel_case_keys},
+    schema,
+};
+
+/// What converting a legacy manifest did, and what it had to give up on
+#[derive(Clone, Debug, Default)]
+pub struct ConversionReport {
+    pub changes: Vec<String>,
+    pub untranslated: Vec<String>,
+}
+
+/// Translates a dependency's additional data, which the C# QPM keys differently
+fn convert_dependency(dependency: &mut Value, path: &str, report: &mut ConversionReport) {
+    if let Some(data) = dependency
+        .get_mut("additionalData")
+        .and_then(Value::as_object_mut)
+    {
+        if let Some(private) = data.remove("private") {
+            data.insert("isPrivate".to_string(), private);
+            report
+                .changes
+                .push(format!("{}/additionalData: private -> isPrivate", path));
+        }
+    }
+
+    let range = match dependency.get("versionRange").and_then(Value::as_str) {
+        Some(range) => range.trim().to_string(),
+        None => return,
+    };
+    if range.contains("||") {
+        report.untranslated.push(format!(
+            "{}/versionRange: '{}' uses ||, which qpm-rust ranges don't support",
+            path, range
+        ));
+    } else if range.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && range.split('.').count() == 3
+        && !range.contains(' ')
+    {
+        // a bare version was an exact match in the C# QPM, but is a caret range here
+        dependency["versionRange"] = json!(format!("={}", range));
+        report.changes.push(format!(
+            "{}/versionRange: {} -> ={} (bare versions were exact)",
+            path, range, range
+        ));
+    }
+}
+
+fn convert_package_value(value: &mut Value, path: &str, report: &mut ConversionReport) {
+    if let Some(dependencies) = value.get_mut("dependencies").and_then(Value::as_array_mut) {
+        for (i, dependency) in dependencies.iter_mut().enumerate() {
+            convert_dependency(dependency, &format!("{}/dependencies/{}", path, i), report);
+        }
+    }
+}
+
+/// Converts a qpm.json written by the C# QuestPackageManager (or an older qpm-rust) into
+/// the current format
+pub fn convert_package(mut value: Value) -> Result<(Value, ConversionReport)> {
+    let mut report = ConversionReport::default();
+    camel_case_keys(&mut value);
+    convert_package_value(&mut value, "", &mut report);
+    migration::migrate_package(&mut value)?;
+    report.untranslated.extend(
+        schema::unknown_fields(&schema::package_config(), &value)
+            .into_iter()
+            .map(|field| format!("{}: not supported, dropped", field)),
+    );
+    Ok((value, report))
+}
+
+/// Converts a legacy qpm.shared.json, including the dependencies it restored
+pub fn convert_shared_package(mut value: Value) -> Result<(Value, ConversionReport)> {
+    let mut report = ConversionReport::default();
+    camel_case_keys(&mut value);
+    if let Some(config) = value.get_mut("config") {
+        convert_package_value(config, "/config", &mut report);
+    }
+    if let Some(restored) = value
+        .get_mut("restoredDependencies")
+        .and_then(Value::as_array_mut)
+    {
+        for (i, shared) in restored.iter_mut().enumerate() {
+            if let Some(dependency) = shared.get_mut("dependency") {
+                let path = format!("/restoredDependencies/{}/dependency", i);
+                convert_dependency(dependency, &path, &mut report);
+            }
+        }
+    }
+    migration::migrate_shared_package(&mut value)?;
+    report.untranslated.extend(
+        schema::unknown_fields(&schema::shared_package_config(), &value)
+            .into_iter()
+            .map(|field| format!("{}: not supported, dropped", field)),
+    );
+    Ok((value, report))
+}
@@ -0,0 +1,46 @@
+/// `outln!` for command output. Once stdout is closed, like when piped into `head`, it
+/// writes nothing more instead of panicking, see [`utils::output`].
+macro_rules! outln {
+    () => {
+        $crate::utils::output::write(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::utils::output::write(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// `print!` for command output, see [`outln!`]
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::utils::output::write(format_args!($($arg)*))
+    };
+}
+
+pub mod adb;
+pub mod build;
+pub mod build_files;
+pub mod cache;
+pub mod cancel;
+pub mod ci;
+pub mod commands;
+pub mod conflicts;
+pub mod core_mods;
+pub mod data;
+pub mod diagnostics;
+pub mod error;
+pub mod hooks;
+pub mod legacy;
+pub mod logging;
+pub mod migration;
+pub mod ndk;
+pub mod network;
+pub mod qmod;
+pub mod repository;
+pub mod scaffold;
+pub mod schema;
+pub mod self_update;
+pub mod signing;
+pub mod symbols;
+pub mod utils;
+pub mod version;
+pub mod workspace;
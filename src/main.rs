@@ -1,17 +1,15 @@
-use serde::{Serialize, Deserialize};
+use qpm_rust::{cancel, error::Error};
 
 fn main() {
-    let mut cfg = Config {cache_path: "test".to_string(), timeout: 3 };
-    let ser = serde_json::to_string(&cfg).unwrap();
-    println!("serialize = {}", ser);
-    cfg = serde_json::from_str(&ser).unwrap();
-    println!("deser = {:?}", cfg);
+    qpm_rust::diagnostics::install_panic_hook();
+    cancel::install_handler();
+    let args = std::env::args().skip(1).collect();
+    if let Err(mut e) = qpm_rust::commands::run(args) {
+        if cancel::is_cancelled() {
+            // whatever failed only did because it was interrupted
+            e = Error::Cancelled;
+        }
+        eprintln!("error: {}", e);
+        std::process::exit(e.exit_code());
+    }
 }
-
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Config {
-    pub cache_path: String,
-    pub timeout: u32
-}
-
@@ -0,0 +1,116 @@
+use std::{fs, path::Path};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    error::{Error, Result},
+    schema,
+};
+
+/// The manifest layout this build reads and writes. Manifests without a `schemaVersion`
+/// predate it and count as version 0.
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn schema_version(object: &Map<String, Value>) -> Result<u32> {
+    let version = object
+        .get("schemaVersion")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        return Err(Error::Validation(format!(
+            "manifest has schema version {}, but this qpm only understands up to {}; update qpm",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+/// Lower cases the first letter of every key, turning `SharedDir` style keys written by
+/// older tools into the camelCase ones used now. Override maps are keyed by package id
+/// and left alone.
+pub fn camel_case_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            let entries: Vec<(String, Value)> = std::mem::take(object).into_iter().collect();
+            for (key, mut child) in entries {
+                let mut chars = key.chars();
+                let key = match chars.next() {
+                    Some(first) => first.to_lowercase().chain(chars).collect(),
+                    None => key,
+                };
+                if key != "overrides" {
+                    camel_case_keys(&mut child);
+                }
+                object.entry(key).or_insert(child);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(camel_case_keys),
+        _ => {}
+    }
+}
+
+fn default_field(object: &mut Map<String, Value>, key: &str, default: Value) {
+    object.entry(key.to_string()).or_insert(default);
+}
+
+fn migrate_package_object(object: &mut Map<String, Value>) {
+    default_field(object, "sharedDir", json!("shared"));
+    default_field(object, "dependenciesDir", json!("extern"));
+    default_field(object, "dependencies", json!([]));
+    if let Some(info) = object.get_mut("info").and_then(Value::as_object_mut) {
+        default_field(info, "url", Value::Null);
+    }
+    object.insert("schemaVersion".to_string(), json!(SCHEMA_VERSION));
+}
+
+/// Upgrades a qpm.json value to the current schema, returning whether anything changed
+pub fn migrate_package(value: &mut Value) -> Result<bool> {
+    let version = match value.as_object() {
+        Some(object) => schema_version(object)?,
+        None => return Ok(false),
+    };
+    if version == SCHEMA_VERSION {
+        return Ok(false);
+    }
+    camel_case_keys(value);
+    if let Some(object) = value.as_object_mut() {
+        migrate_package_object(object);
+    }
+    Ok(true)
+}
+
+/// Upgrades a qpm.shared.json value to the current schema, returning whether anything changed
+pub fn migrate_shared_package(value: &mut Value) -> Result<bool> {
+    let version = match value.as_object() {
+        Some(object) => schema_version(object)?,
+        None => return Ok(false),
+    };
+    if version == SCHEMA_VERSION {
+        return Ok(false);
+    }
+    camel_case_keys(value);
+    if let Some(object) = value.as_object_mut() {
+        if let Some(config) = object.get_mut("config").and_then(Value::as_object_mut) {
+            migrate_package_object(config);
+        }
+        default_field(object, "restoredDependencies", json!([]));
+        object.insert("schemaVersion".to_string(), json!(SCHEMA_VERSION));
+    }
+    Ok(true)
+}
+
+/// Replaces a manifest with its upgraded contents, keeping the original next to it
+pub fn write_upgraded<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    fs::copy(path, &backup)?;
+    schema::write_value(path, &serde_json::to_value(value)?)?;
+    outln!(
+        "Upgraded {} to schema version {}, the original was kept as {}",
+        path.display(),
+        SCHEMA_VERSION,
+        Path::new(&backup).display()
+    );
+    Ok(())
+}
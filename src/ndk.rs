@@ -0,0 +1,177 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    data::{
+        config::{get_config_dir, Config},
+        package::PackageConfig,
+    },
+    error::{Error, Result},
+    network::{self, archive::ExtractOptions, source::ArchiveKind},
+    utils::fs::remove_dir_if_exists,
+};
+
+const DOWNLOAD_URL: &str = "https://dl.google.com/android/repository";
+
+/// An NDK found on this machine
+#[derive(Clone, Debug)]
+pub struct Ndk {
+    /// `Pkg.Revision` from its source.properties, like `25.2.9519653`
+    pub revision: String,
+    pub path: PathBuf,
+    /// Whether it lives in the directory qpm downloads NDKs into
+    pub managed: bool,
+}
+
+/// Where `qpm ndk download` installs NDKs, one directory per revision
+pub fn managed_dir() -> PathBuf {
+    get_config_dir().join("ndk")
+}
+
+/// The revision of the NDK at `path`, None if it isn't one
+pub fn revision(path: &Path) -> Option<String> {
+    let properties = fs::read_to_string(path.join("source.properties")).ok()?;
+    properties.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "Pkg.Revision").then(|| value.trim().to_string())
+    })
+}
+
+fn ndks_in(dir: &Path, managed: bool, found: &mut Vec<Ndk>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if let Some(revision) = revision(&path) {
+            found.push(Ndk {
+                revision,
+                path,
+                managed,
+            });
+        }
+    }
+}
+
+/// Every NDK qpm manages plus the ones the Android SDK and environment point at
+pub fn installed(config: &Config) -> Vec<Ndk> {
+    let mut found = Vec::new();
+    ndks_in(&managed_dir(), true, &mut found);
+    for var in ["ANDROID_HOME", "ANDROID_SDK_ROOT"] {
+        if let Some(sdk) = env::var_os(var) {
+            ndks_in(&PathBuf::from(sdk).join("ndk"), false, &mut found);
+        }
+    }
+    let singles = ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT"]
+        .iter()
+        .filter_map(|var| env::var_os(var).map(PathBuf::from))
+        .chain(config.ndk_path.iter().map(PathBuf::from));
+    for path in singles {
+        if let Some(revision) = revision(&path) {
+            found.push(Ndk {
+                revision,
+                path,
+                managed: false,
+            });
+        }
+    }
+
+    let mut seen = Vec::new();
+    found.retain(|ndk| {
+        let path = ndk.path.canonicalize().unwrap_or_else(|_| ndk.path.clone());
+        let new = !seen.contains(&path);
+        seen.push(path);
+        new
+    });
+    found.sort_by(|a, b| compare_revisions(&b.revision, &a.revision));
+    found
+}
+
+fn compare_revisions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |r: &str| -> Vec<u64> { r.split('.').filter_map(|p| p.parse().ok()).collect() };
+    parts(a).cmp(&parts(b))
+}
+
+/// Whether `revision` satisfies a pin, which may leave off trailing components
+pub fn matches(pin: &str, revision: &str) -> bool {
+    let pin = pin.trim_start_matches('r');
+    revision == pin || revision.starts_with(&format!("{}.", pin))
+}
+
+/// The NDK a project builds with: its pinned version if any, otherwise the configured
+/// `ndkPath`, otherwise the newest one installed
+pub fn resolve(package: &PackageConfig, config: &Config) -> Result<Option<Ndk>> {
+    let installed = installed(config);
+    if let Some(pin) = &package.ndk {
+        return installed
+            .into_iter()
+            .find(|ndk| matches(pin, &ndk.revision))
+            .map(Some)
+            .ok_or_else(|| {
+                Error::Resolution(format!(
+                    "the project pins NDK {} which isn't installed, see qpm ndk download",
+                    pin
+                ))
+            });
+    }
+    if let Some(path) = &config.ndk_path {
+        let path = PathBuf::from(path);
+        return Ok(revision(&path).map(|revision| Ndk {
+            revision,
+            path,
+            managed: false,
+        }));
+    }
+    Ok(installed.into_iter().next())
+}
+
+fn host() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "darwin"
+    } else {
+        "linux"
+    }
+}
+
+/// The download of a release like `r25c`
+pub fn download_url(release: &str) -> String {
+    format!("{}/android-ndk-{}-{}.zip", DOWNLOAD_URL, release, host())
+}
+
+/// Downloads and unpacks an NDK release into the managed directory, returning it
+pub fn download(release: &str, config: &Config) -> Result<Ndk> {
+    let release = if release.starts_with('r') {
+        release.to_string()
+    } else {
+        format!("r{}", release)
+    };
+    let archive = config
+        .tmp_dir()
+        .join(format!("android-ndk-{}.zip", release));
+    network::download_file(&download_url(&release), &archive, config)?;
+    let staging = managed_dir().join(format!("{}.download", release));
+    let extracted =
+        network::archive::extract(&archive, ArchiveKind::Zip, &staging, &ExtractOptions::new());
+    fs::remove_file(&archive)?;
+    extracted?;
+
+    let revision = revision(&staging).ok_or_else(|| {
+        Error::Validation(format!(
+            "the {} download does not look like an NDK",
+            release
+        ))
+    })?;
+    let path = managed_dir().join(&revision);
+    remove_dir_if_exists(&path)?;
+    fs::rename(&staging, &path)?;
+    Ok(Ndk {
+        revision,
+        path,
+        managed: true,
+    })
+}
@@ -0,0 +1,261 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    cancel,
+    error::{Error, Result},
+    network::source::ArchiveKind,
+    utils::fs::{escapes_root, remove_dir_if_exists},
+};
+
+/// Where an extraction is at, handed to the progress callback after every entry
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractProgress {
+    pub entries: u64,
+    /// Entries in the whole archive
+    pub total: u64,
+}
+
+/// How [`extract`] unpacks
+pub struct ExtractOptions<'a> {
+    /// Unpack the contents of the single folder everything is in, like the
+    /// `<repo>-<ref>/` of GitHub archives, instead of the folder itself
+    pub strip_top_level: bool,
+    /// Only keep this folder of the (stripped) archive, for packages living in a
+    /// subfolder of their repository
+    pub sub_folder: Option<&'a str>,
+    pub progress: Option<&'a dyn Fn(ExtractProgress)>,
+}
+
+impl Default for ExtractOptions<'_> {
+    fn default() -> Self {
+        ExtractOptions {
+            strip_top_level: true,
+            sub_folder: None,
+            progress: None,
+        }
+    }
+}
+
+impl<'a> ExtractOptions<'a> {
+    /// Unpacks everything, stripping a single top level folder
+    pub fn new() -> ExtractOptions<'a> {
+        ExtractOptions::default()
+    }
+
+    pub fn strip_top_level(mut self, strip_top_level: bool) -> ExtractOptions<'a> {
+        self.strip_top_level = strip_top_level;
+        self
+    }
+
+    pub fn sub_folder(mut self, sub_folder: Option<&'a str>) -> ExtractOptions<'a> {
+        self.sub_folder = sub_folder;
+        self
+    }
+
+    pub fn progress(mut self, progress: &'a dyn Fn(ExtractProgress)) -> ExtractOptions<'a> {
+        self.progress = Some(progress);
+        self
+    }
+}
+
+/// Unpacks `archive` into `dest`, which must not exist yet, returning how many entries it
+/// had. Entries that would land outside of `dest` fail the whole extraction before
+/// anything is written.
+pub fn extract(
+    archive: &Path,
+    kind: ArchiveKind,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<u64> {
+    let entries = list(archive, kind)?;
+    for entry in &entries {
+        check_entry(archive, entry)?;
+    }
+    let total = entries.len() as u64;
+
+    let mut staging = dest.as_os_str().to_owned();
+    staging.push(".extracting");
+    let staging = PathBuf::from(staging);
+    remove_dir_if_exists(&staging)?;
+    fs::create_dir_all(&staging)?;
+    let _cleanup = cancel::remove_on_cancel(&staging);
+    let result = unpack(archive, kind, &staging, total, options)
+        .and_then(|_| check_links(archive, &staging))
+        .and_then(|_| {
+            let mut root = staging.clone();
+            if options.strip_top_level {
+                let children = fs::read_dir(&staging)?.collect::<std::io::Result<Vec<_>>>()?;
+                if let [single] = children.as_slice() {
+                    if single.file_type()?.is_dir() {
+                        root = single.path();
+                    }
+                }
+            }
+            let root = select_sub_folder(&root, options.sub_folder)?;
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&root, dest)?;
+            Ok(())
+        });
+    remove_dir_if_exists(&staging)?;
+    result.map(|_| total)
+}
+
+/// The entry names of `archive`, folders included
+pub fn list(archive: &Path, kind: ArchiveKind) -> Result<Vec<String>> {
+    let mut cmd = match kind {
+        ArchiveKind::TarGz => {
+            let mut cmd = Command::new("tar");
+            cmd.arg("-tzf").arg(archive);
+            cmd
+        }
+        ArchiveKind::Zip => {
+            let mut cmd = Command::new("unzip");
+            cmd.arg("-Z1").arg(archive);
+            cmd
+        }
+    };
+    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(Error::Io(std::io::Error::other(format!(
+            "failed to read {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Runs the extractor, counting the entries it reports as it goes
+fn unpack(
+    archive: &Path,
+    kind: ArchiveKind,
+    staging: &Path,
+    total: u64,
+    options: &ExtractOptions,
+) -> Result<()> {
+    let mut cmd = match kind {
+        ArchiveKind::TarGz => {
+            let mut cmd = Command::new("tar");
+            cmd.arg("-xvzf").arg(archive).arg("-C").arg(staging);
+            cmd
+        }
+        ArchiveKind::Zip => {
+            let mut cmd = Command::new("unzip");
+            // -n never overwrites, so a duplicate entry can't replace what was checked
+            cmd.arg("-n").arg(archive).arg("-d").arg(staging);
+            cmd
+        }
+    };
+    let mut child = cmd.stdout(Stdio::piped()).spawn()?;
+    if let Some(stdout) = child.stdout.take() {
+        let mut entries = 0;
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            // unzip starts with an `Archive:` header
+            if line.trim().is_empty() || line.starts_with("Archive:") {
+                continue;
+            }
+            entries = (entries + 1).min(total);
+            if let Some(progress) = options.progress {
+                progress(ExtractProgress { entries, total });
+            }
+        }
+    }
+    if !child.wait()?.success() {
+        return Err(Error::Io(std::io::Error::other(format!(
+            "failed to extract {}",
+            archive.display()
+        ))));
+    }
+    Ok(())
+}
+
+/// Rejects entries that are absolute or climb out of the extraction folder, which would
+/// let an archive overwrite arbitrary files (zip-slip)
+fn check_entry(archive: &Path, entry: &str) -> Result<()> {
+    if escapes_root(entry) {
+        return Err(Error::Validation(format!(
+            "{} contains the entry {}, which points outside of the extraction folder",
+            archive.display(),
+            entry
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects extracted links whose target is outside of `staging`, through which a later
+/// entry or a build could still reach outside of it
+fn check_links(archive: &Path, staging: &Path) -> Result<()> {
+    let mut dirs = vec![staging.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(entry.path())?;
+                let relative = dir.strip_prefix(staging).unwrap_or(&dir);
+                if !stays_inside(relative, &target) {
+                    return Err(Error::Validation(format!(
+                        "{} contains the link {} to {}, which points outside of the extraction folder",
+                        archive.display(),
+                        relative.join(entry.file_name()).display(),
+                        target.display()
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `target`, relative to the folder `dir` the link is in, stays inside the root
+fn stays_inside(dir: &Path, target: &Path) -> bool {
+    let mut depth = dir.components().count();
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// The folder `sub_folder` inside `root`, `root` itself without one
+pub fn select_sub_folder(root: &Path, sub_folder: Option<&str>) -> Result<PathBuf> {
+    let sub_folder = match sub_folder
+        .map(|folder| folder.trim_matches(['/', '\\']))
+        .filter(|folder| !folder.is_empty() && *folder != ".")
+    {
+        Some(sub_folder) => sub_folder,
+        None => return Ok(root.to_path_buf()),
+    };
+    if escapes_root(sub_folder) {
+        return Err(Error::Validation(format!(
+            "the subFolder {} must be a relative path inside the repository",
+            sub_folder
+        )));
+    }
+    let dir = root.join(sub_folder);
+    if !dir.is_dir() {
+        return Err(Error::Validation(format!(
+            "the subFolder {} does not exist in the download",
+            sub_folder
+        )));
+    }
+    Ok(dir)
+}
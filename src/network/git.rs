@@ -0,0 +1,181 @@
+use std::{path::Path, process::Command};
+
+use crate::{
+    data::{
+        config::TlsConfig, dependency::AdditionalDependencyData, extra_file::ExtraFile,
+        package::PackageConfig,
+    },
+    error::{Error, Result},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitRef {
+    Branch(String),
+    Tag(String),
+}
+
+impl GitRef {
+    pub fn name(&self) -> &str {
+        match self {
+            GitRef::Branch(name) | GitRef::Tag(name) => name,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CloneOptions {
+    /// Only fetch the tip commit instead of the full history
+    pub shallow: bool,
+    /// When set, only these paths are checked out
+    pub sparse_paths: Option<Vec<String>>,
+    /// Proxy to clone through, git falls back to the proxy environment variables itself
+    pub proxy: Option<String>,
+    pub tls: TlsConfig,
+}
+
+impl CloneOptions {
+    /// Shallow by default; sparse checkout of the shared dir, extra files and assets is opt-in
+    pub fn for_package(package: &PackageConfig, data: &AdditionalDependencyData) -> CloneOptions {
+        let sparse_paths = if data.sparse_checkout.unwrap_or(false) {
+            let mut paths = vec![package.shared_dir.clone(), "qpm.json".to_string()];
+            paths.extend(
+                package
+                    .info
+                    .additional_data
+                    .extra_files
+                    .iter()
+                    .chain(data.extra_files.iter())
+                    .flatten()
+                    // git matches globs itself, the destination is up to restore
+                    .filter_map(|extra| ExtraFile::parse(extra).ok())
+                    .map(|extra| extra.source),
+            );
+            paths.extend(package.assets.iter().map(|asset| asset.path.clone()));
+            // the paths are relative to the package, which may be in a subfolder
+            if let Some(sub_folder) = &package.info.additional_data.sub_folder {
+                let sub_folder = sub_folder.trim_matches(['/', '\\']);
+                if !sub_folder.is_empty() && sub_folder != "." {
+                    for path in &mut paths {
+                        *path = format!("{}/{}", sub_folder, path.trim_start_matches("./"));
+                    }
+                }
+            }
+            Some(paths)
+        } else {
+            None
+        };
+        CloneOptions {
+            shallow: data.shallow_clone.unwrap_or(true),
+            sparse_paths,
+            proxy: None,
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+/// Clones `url` into `dest`, checking out `git_ref` when one is given
+pub fn clone(
+    url: &str,
+    git_ref: Option<&GitRef>,
+    dest: &Path,
+    options: &CloneOptions,
+) -> Result<()> {
+    let mut cmd = Command::new("git");
+    if let Some(proxy) = &options.proxy {
+        cmd.arg("-c").arg(format!("http.proxy={}", proxy));
+    }
+    if let Some(ca_bundle) = &options.tls.ca_bundle {
+        cmd.arg("-c").arg(format!("http.sslCAInfo={}", ca_bundle));
+    }
+    if options.tls.insecure {
+        cmd.arg("-c").arg("http.sslVerify=false");
+    }
+    cmd.arg("clone").arg("--quiet");
+    if options.shallow {
+        cmd.arg("--depth").arg("1");
+    }
+    if options.sparse_paths.is_some() {
+        cmd.arg("--filter=blob:none").arg("--sparse");
+    }
+    if let Some(git_ref) = git_ref {
+        cmd.arg("--branch").arg(git_ref.name());
+    }
+    cmd.arg(url).arg(dest);
+    let result = run(cmd, &format!("cloning {}", url));
+    crate::logging::debug(
+        "git",
+        format!(
+            "clone {} at {}: {}",
+            crate::logging::redact(url),
+            git_ref.map_or("the default branch", GitRef::name),
+            if result.is_ok() { "ok" } else { "failed" }
+        ),
+    );
+    result?;
+
+    if let Some(paths) = &options.sparse_paths {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C")
+            .arg(dest)
+            .args(["sparse-checkout", "set", "--no-cone"]);
+        for path in paths {
+            cmd.arg(format!(
+                "/{}",
+                path.trim_start_matches("./").trim_start_matches('/')
+            ));
+        }
+        run(cmd, &format!("sparse checkout of {}", url))?;
+    }
+    Ok(())
+}
+
+/// The commit checked out in `dir`, None outside a git repository
+pub fn head_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !commit.is_empty()).then_some(commit)
+}
+
+/// `path` as committed at `rev` in the repository around `dir`, None when it didn't exist
+/// there
+pub fn show_file(dir: &Path, rev: &str, path: &str) -> Result<Option<Vec<u8>>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", rev, path))
+        .output()
+        .map_err(|e| Error::Git(format!("failed to run git: {}", e)))?;
+    if output.status.success() {
+        return Ok(Some(output.stdout));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("does not exist in") || stderr.contains("exists on disk, but not in") {
+        return Ok(None);
+    }
+    Err(Error::Git(format!(
+        "reading {} at {} failed: {}",
+        path,
+        rev,
+        stderr.trim()
+    )))
+}
+
+fn run(mut cmd: Command, action: &str) -> Result<()> {
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Git(format!("failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::Git(format!(
+            "{} failed: {}",
+            action,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
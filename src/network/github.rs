@@ -0,0 +1,42 @@
+use crate::network::git::GitRef;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GithubRepo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl GithubRepo {
+    /// Recognizes `https://github.com/<owner>/<repo>` urls, with or without a `.git` suffix
+    pub fn parse(url: &str) -> Option<GithubRepo> {
+        let rest = url
+            .trim_end_matches('/')
+            .strip_prefix("https://github.com/")
+            .or_else(|| url.strip_prefix("http://github.com/"))?;
+        let mut parts = rest.split('/');
+        let owner = parts.next().filter(|s| !s.is_empty())?;
+        let name = parts.next().filter(|s| !s.is_empty())?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(GithubRepo {
+            owner: owner.to_string(),
+            name: name.trim_end_matches(".git").to_string(),
+        })
+    }
+
+    /// The codeload tarball for `git_ref`, which is much cheaper than cloning the repo
+    pub fn archive_url(&self, git_ref: &GitRef) -> String {
+        let kind = match git_ref {
+            GitRef::Branch(_) => "heads",
+            GitRef::Tag(_) => "tags",
+        };
+        format!(
+            "https://codeload.github.com/{}/{}/tar.gz/refs/{}/{}",
+            self.owner,
+            self.name,
+            kind,
+            git_ref.name()
+        )
+    }
+}
@@ -0,0 +1,531 @@
+pub mod archive;
+pub mod git;
+pub mod github;
+pub mod source;
+
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+use crate::{
+    cancel,
+    data::{
+        config::{Config, TlsConfig},
+        package::{PackageConfig, PackageInfo},
+    },
+    error::{Error, Result},
+    logging,
+    network::{
+        archive::{ExtractOptions, ExtractProgress},
+        git::{CloneOptions, GitRef},
+        source::PackageSource,
+    },
+    utils::{
+        fs::{encode_path_component, remove_dir_if_exists},
+        hash::fnv1a_hex,
+    },
+};
+
+/// The proxy requests to `url` go through: the configured one, otherwise the one from the
+/// standard environment variables for its scheme
+pub fn proxy(url: &str, config: &Config) -> Option<String> {
+    if let Some(proxy) = &config.proxy {
+        return Some(proxy.clone());
+    }
+    let variables: &[&str] = if url.starts_with("http://") {
+        &["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]
+    } else {
+        &["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"]
+    };
+    variables
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+/// The host of `url`, without credentials or port
+pub fn host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// The TLS settings for requests to `url`
+pub fn tls(url: &str, config: &Config) -> TlsConfig {
+    match host(url) {
+        Some(host) => config.tls_for(host),
+        None => TlsConfig::default(),
+    }
+}
+
+/// Hosts reached without the proxy
+fn no_proxy(config: &Config) -> Option<String> {
+    config.no_proxy.clone().or_else(|| {
+        ["no_proxy", "NO_PROXY"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+    })
+}
+
+/// curl for `url` with the proxy, TLS settings, timeouts and speed limit of `config`. `max_time` caps
+/// the whole transfer, registry requests use the regular timeout and downloads the
+/// download timeout.
+fn curl(url: &str, config: &Config, max_time: Option<u32>) -> Command {
+    let mut cmd = Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--location"])
+        .arg("--connect-timeout")
+        .arg(config.connect_timeout.to_string());
+    if let Some(proxy) = proxy(url, config) {
+        cmd.arg("--proxy").arg(proxy);
+        if let Some(no_proxy) = no_proxy(config) {
+            cmd.arg("--noproxy").arg(no_proxy);
+        }
+    }
+    let tls = tls(url, config);
+    if let Some(ca_bundle) = &tls.ca_bundle {
+        cmd.arg("--cacert").arg(ca_bundle);
+    }
+    if tls.insecure {
+        cmd.arg("--insecure");
+    }
+    if let Some(max_time) = max_time {
+        cmd.arg("--max-time").arg(max_time.to_string());
+    }
+    if let Some(limit) = &config.bandwidth_limit {
+        cmd.arg("--limit-rate").arg(limit);
+    }
+    cmd
+}
+
+/// curl's complaint, with a pointer to the TLS settings when a certificate wasn't trusted
+fn curl_error(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // only the first line, curl follows certificate errors with a lengthy explanation
+    let message = stderr.trim().lines().next().unwrap_or_default().to_string();
+    match output.status.code() {
+        Some(60) => format!(
+            "{}, set caBundle or tls.<host> in the config for servers with internal certificates",
+            message
+        ),
+        _ => message,
+    }
+}
+
+/// Logs a request that finished with `outcome`, the status or why curl failed
+fn log_request(method: &str, url: &str, outcome: impl AsRef<str>) {
+    logging::debug(
+        "http",
+        format!("{} {}: {}", method, logging::redact(url), outcome.as_ref()),
+    );
+}
+
+/// The outcome of a `--fail` request for the log
+fn outcome(output: &Output) -> String {
+    if output.status.success() {
+        "ok".to_string()
+    } else {
+        curl_error(output)
+    }
+}
+
+/// How often an interrupted download is resumed before giving up
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Where the partial download of `url` is kept, so a later attempt can resume it
+pub fn partial_path(url: &str, config: &Config) -> PathBuf {
+    config
+        .tmp_dir()
+        .join(format!("{}.part", fnv1a_hex(url.as_bytes())))
+}
+
+/// The `If-Range` value a download with the response headers dumped to `headers` can be
+/// resumed with: its strong ETag or else its Last-Modified date, from the last response
+/// when redirects were followed
+fn resume_validator(headers: &Path) -> Option<String> {
+    let text = fs::read_to_string(headers).ok()?;
+    let last = text.rsplit("HTTP/").next()?;
+    let header = |name: &str| {
+        last.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some(value.trim().to_string()).filter(|_| key.trim().eq_ignore_ascii_case(name))
+        })
+    };
+    // weak ETags aren't allowed in If-Range
+    header("etag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header("last-modified"))
+}
+
+/// Downloads `url` to `dest` using curl, failing on any non-success status.
+///
+/// The download goes to a partial file in the tmp folder first and interrupted transfers
+/// are resumed with range requests, also across runs, so a flaky connection doesn't
+/// start large binaries over. Resuming sends the ETag or Last-Modified date the partial
+/// file was downloaded with as `If-Range`, so a file that changed on the server in the
+/// meantime is downloaded again whole. Downloads without either start over.
+pub fn download_file(url: &str, dest: &Path, config: &Config) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = partial_path(url, config);
+    let validator_file = partial.with_extension("validator");
+    let headers = partial.with_extension("headers");
+    fs::create_dir_all(config.tmp_dir())?;
+    let discard = || {
+        let _ = fs::remove_file(&partial);
+        let _ = fs::remove_file(&validator_file);
+    };
+
+    let mut error = String::new();
+    for _ in 0..DOWNLOAD_ATTEMPTS {
+        let validator = fs::read_to_string(&validator_file).ok();
+        if validator.is_none() {
+            // nothing tells whether the file changed since, resuming could splice two
+            discard();
+        }
+        let mut cmd = curl(url, config, config.download_timeout);
+        cmd.arg("--fail")
+            .arg("--dump-header")
+            .arg(&headers)
+            .args(["--write-out", "%{http_code}"]);
+        let resumed = validator.is_some() && partial.exists();
+        if let Some(validator) = validator.filter(|_| resumed) {
+            cmd.args(["--continue-at", "-"])
+                .arg("--header")
+                .arg(format!("If-Range: {}", validator.trim()));
+        }
+        let output = cmd
+            .arg("--output")
+            .arg(&partial)
+            .arg(url)
+            .output()
+            .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+        match resume_validator(&headers) {
+            Some(validator) => fs::write(&validator_file, validator)?,
+            None => {
+                let _ = fs::remove_file(&validator_file);
+            }
+        }
+        let _ = fs::remove_file(&headers);
+        log_request("GET", url, outcome(&output));
+        if output.status.success() {
+            if fs::rename(&partial, dest).is_err() {
+                // the tmp folder may be on another drive
+                fs::copy(&partial, dest)?;
+                fs::remove_file(&partial)?;
+            }
+            let _ = fs::remove_file(&validator_file);
+            return Ok(());
+        }
+        // curl got the Ctrl-C as well, keep the partial file for the next run
+        cancel::check()?;
+        error = curl_error(&output);
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        match output.status.code() {
+            // the partial file is complete already or longer than the file is now, which
+            // can't be told apart, start over
+            Some(22) if resumed && status == "416" => discard(),
+            // an http error, there is nothing to resume
+            Some(22) => {
+                discard();
+                break;
+            }
+            // the server doesn't support ranges or the file changed, start over
+            Some(33) => discard(),
+            _ if partial.exists() && validator_file.exists() => {
+                eprintln!("Download of {} interrupted, resuming", url)
+            }
+            _ if partial.exists() => eprintln!("Download of {} interrupted, starting over", url),
+            _ => {}
+        }
+    }
+    Err(Error::Network(format!(
+        "downloading {} failed: {}",
+        url, error
+    )))
+}
+
+/// Downloads `url` to `dest` passing curl the extra `args`, like credentials. Returns
+/// `false` when the server answers 404.
+pub fn fetch_file(url: &str, dest: &Path, args: &[String], config: &Config) -> Result<bool> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = partial_path(url, config);
+    fs::create_dir_all(config.tmp_dir())?;
+    let output = curl(url, config, config.download_timeout)
+        .args(args)
+        .args(["--write-out", "%{http_code}"])
+        .arg("--output")
+        .arg(&partial)
+        .arg(url)
+        .output()
+        .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+    let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    log_request(
+        "GET",
+        url,
+        if output.status.success() {
+            status.clone()
+        } else {
+            curl_error(&output)
+        },
+    );
+    if !output.status.success() || !status.starts_with('2') {
+        let _ = fs::remove_file(&partial);
+    }
+    if !output.status.success() {
+        return Err(Error::Network(format!(
+            "downloading {} failed: {}",
+            url,
+            curl_error(&output)
+        )));
+    }
+    match status.as_str() {
+        "404" => Ok(false),
+        s if s.starts_with('2') => {
+            if fs::rename(&partial, dest).is_err() {
+                fs::copy(&partial, dest)?;
+                fs::remove_file(&partial)?;
+            }
+            Ok(true)
+        }
+        s => Err(Error::Network(format!(
+            "downloading {} failed with status {}",
+            url, s
+        ))),
+    }
+}
+
+/// Uploads `file` to `url` with a PUT request, passing curl the extra `args`
+pub fn put_file(url: &str, file: &Path, args: &[String], config: &Config) -> Result<()> {
+    let output = curl(url, config, config.download_timeout)
+        .arg("--fail")
+        .args(args)
+        .arg("--upload-file")
+        .arg(file)
+        .arg(url)
+        .output()
+        .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+    log_request("PUT", url, outcome(&output));
+    if !output.status.success() {
+        return Err(Error::Network(format!(
+            "uploading to {} failed: {}",
+            url,
+            curl_error(&output)
+        )));
+    }
+    Ok(())
+}
+
+/// Performs a GET request, returning `None` when the server answers 404
+pub fn get(url: &str, config: &Config) -> Result<Option<Vec<u8>>> {
+    let output = curl(url, config, Some(config.timeout))
+        .args(["--write-out", "\n%{http_code}"])
+        .arg(url)
+        .output()
+        .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+    if !output.status.success() {
+        log_request("GET", url, curl_error(&output));
+        return Err(Error::Network(format!(
+            "requesting {} failed: {}",
+            url,
+            curl_error(&output)
+        )));
+    }
+
+    let mut body = output.stdout;
+    let split = body.iter().rposition(|b| *b == b'\n').unwrap_or(0);
+    let status = String::from_utf8_lossy(&body[split..]).trim().to_string();
+    body.truncate(split);
+    log_request("GET", url, &status);
+    match status.as_str() {
+        "404" => Ok(None),
+        s if s.starts_with('2') => Ok(Some(body)),
+        s => Err(Error::Network(format!(
+            "requesting {} failed with status {}",
+            url, s
+        ))),
+    }
+}
+
+/// Posts a json body with extra `headers`, failing on any non-success status
+pub fn post_json(url: &str, body: &[u8], headers: &[String], config: &Config) -> Result<()> {
+    let mut cmd = curl(url, config, Some(config.timeout));
+    cmd.arg("--fail")
+        .args(["--header", "Content-Type: application/json"]);
+    for header in headers {
+        cmd.arg("--header").arg(header);
+    }
+    let mut child = cmd
+        .args(["--data-binary", "@-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+    child.stdin.take().unwrap().write_all(body)?;
+    let output = child.wait_with_output()?;
+    log_request("POST", url, outcome(&output));
+    if !output.status.success() {
+        return Err(Error::Network(format!(
+            "posting to {} failed: {}",
+            url,
+            curl_error(&output)
+        )));
+    }
+    Ok(())
+}
+
+/// The status a HEAD request for `url` ends with after redirects. Servers that refuse HEAD
+/// requests are asked for the first byte instead.
+pub fn head_status(url: &str, config: &Config) -> Result<u16> {
+    let status = |method: &[&str]| -> Result<u16> {
+        let output = curl(url, config, Some(config.timeout))
+            .args(method)
+            .args(["--output", if cfg!(windows) { "NUL" } else { "/dev/null" }])
+            .args(["--write-out", "%{http_code}"])
+            .arg(url)
+            .output()
+            .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+        let method = if method[0] == "--head" { "HEAD" } else { "GET" };
+        if !output.status.success() {
+            log_request(method, url, curl_error(&output));
+            return Err(Error::Network(format!(
+                "requesting {} failed: {}",
+                url,
+                curl_error(&output)
+            )));
+        }
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        log_request(method, url, &status);
+        Ok(status.parse().unwrap_or(0))
+    };
+    match status(&["--head"])? {
+        403 | 405 => status(&["--range", "0-0"]),
+        status => Ok(status),
+    }
+}
+
+/// The size a download would have according to the server, None if it doesn't say
+pub fn content_length(url: &str, config: &Config) -> Result<Option<u64>> {
+    let output = curl(url, config, Some(config.timeout))
+        .arg("--head")
+        .arg(url)
+        .output()
+        .map_err(|e| Error::Network(format!("failed to run curl: {}", e)))?;
+    log_request("HEAD", url, outcome(&output));
+    if !output.status.success() {
+        return Err(Error::Network(format!(
+            "requesting {} failed: {}",
+            url,
+            curl_error(&output)
+        )));
+    }
+    // redirects print several header blocks, the last one is the file's
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse().ok())?
+        }))
+}
+
+/// The refs a package version may live under, most specific first
+pub fn candidate_refs(info: &PackageInfo) -> Vec<GitRef> {
+    match &info.additional_data.branch_name {
+        Some(branch) => vec![GitRef::Branch(branch.clone())],
+        None => vec![
+            GitRef::Tag(format!("v{}", info.version)),
+            GitRef::Tag(info.version.clone()),
+        ],
+    }
+}
+
+/// Fetches the sources of a package into `dest`, returning how: `git` or the archive
+/// format, like `zip`.
+///
+/// Packages on known hosts (GitHub, GitLab, Gitea) are downloaded as archives and urls
+/// pointing directly at an archive are used as is, only falling back to a git clone
+/// when no archive exists for any candidate ref or the host isn't recognized.
+pub fn download_package(
+    package: &PackageConfig,
+    options: &CloneOptions,
+    dest: &Path,
+    config: &Config,
+) -> Result<&'static str> {
+    let info = &package.info;
+    let url = info
+        .url
+        .as_deref()
+        .ok_or_else(|| Error::Network(format!("package {} has no url", info.id)))?;
+    let refs = candidate_refs(info);
+    let sub_folder = info.additional_data.sub_folder.as_deref();
+
+    let source = PackageSource::parse(url);
+
+    for (archive_url, kind) in source.archive_urls(&refs) {
+        cancel::check()?;
+        let archive = config.tmp_dir().join(format!(
+            "{}-{}.{}",
+            encode_path_component(&info.id),
+            encode_path_component(&info.version),
+            kind.extension()
+        ));
+        if download_file(&archive_url, &archive, config).is_err() {
+            continue;
+        }
+        let report = |progress: ExtractProgress| {
+            if progress.entries == progress.total {
+                outln!("  extracted {} entries", progress.total);
+            }
+        };
+        let options = ExtractOptions::new()
+            .sub_folder(sub_folder)
+            .progress(&report);
+        let result = archive::extract(&archive, kind, dest, &options);
+        fs::remove_file(&archive)?;
+        return result.map(|_| kind.extension());
+    }
+    if !source.is_cloneable() {
+        return Err(Error::Network(format!("failed to download {}", url)));
+    }
+
+    let options = CloneOptions {
+        proxy: proxy(url, config),
+        tls: tls(url, config),
+        ..options.clone()
+    };
+    if sub_folder.is_none() {
+        return clone_any(url, &refs, dest, &options).map(|_| "git");
+    }
+    // the whole repository is cloned next to dest and only the subfolder kept
+    let mut staging = dest.as_os_str().to_owned();
+    staging.push(".clone");
+    let staging = PathBuf::from(staging);
+    remove_dir_if_exists(&staging)?;
+    let result = clone_any(url, &refs, &staging, &options).and_then(|_| {
+        fs::rename(archive::select_sub_folder(&staging, sub_folder)?, dest)?;
+        Ok(())
+    });
+    remove_dir_if_exists(&staging)?;
+    result.map(|_| "git")
+}
+
+/// Clones the first of `refs` that exists, the default branch when none does
+fn clone_any(url: &str, refs: &[GitRef], dest: &Path, options: &CloneOptions) -> Result<()> {
+    for git_ref in refs {
+        if git::clone(url, Some(git_ref), dest, options).is_ok() {
+            return Ok(());
+        }
+    }
+    git::clone(url, None, dest, options)
+}
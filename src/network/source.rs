@@ -0,0 +1,135 @@
+use crate::network::{git::GitRef, github::GithubRepo};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    pub fn from_url(url: &str) -> Option<ArchiveKind> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if path.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveKind::TarGz => "tar.gz",
+            ArchiveKind::Zip => "zip",
+        }
+    }
+}
+
+/// Where the sources of a package can be fetched from, derived from its url
+#[derive(Clone, Debug, PartialEq)]
+pub enum PackageSource {
+    Github(GithubRepo),
+    /// A GitLab instance, `path` being the (possibly nested) project path
+    Gitlab {
+        host: String,
+        path: String,
+    },
+    /// A Gitea or Forgejo instance, including codeberg.org
+    Gitea {
+        host: String,
+        owner: String,
+        name: String,
+    },
+    /// A direct link to an archive, used as is regardless of version
+    Archive {
+        url: String,
+        kind: ArchiveKind,
+    },
+    /// Anything else, which can only be cloned
+    Git(String),
+}
+
+impl PackageSource {
+    pub fn parse(url: &str) -> PackageSource {
+        if let Some(kind) = ArchiveKind::from_url(url) {
+            return PackageSource::Archive {
+                url: url.to_string(),
+                kind,
+            };
+        }
+        if let Some(repo) = GithubRepo::parse(url) {
+            return PackageSource::Github(repo);
+        }
+
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"));
+        if let Some((host, path)) = rest.and_then(|r| r.split_once('/')) {
+            let path = path.trim_end_matches('/').trim_end_matches(".git");
+            if host.contains("gitlab") && path.contains('/') {
+                return PackageSource::Gitlab {
+                    host: host.to_string(),
+                    path: path.to_string(),
+                };
+            }
+            if host.contains("gitea") || host.contains("forgejo") || host == "codeberg.org" {
+                if let Some((owner, name)) = path.split_once('/').filter(|(_, n)| !n.contains('/'))
+                {
+                    return PackageSource::Gitea {
+                        host: host.to_string(),
+                        owner: owner.to_string(),
+                        name: name.to_string(),
+                    };
+                }
+            }
+        }
+        PackageSource::Git(url.to_string())
+    }
+
+    /// Archive urls to try in order, one per candidate ref for hosted repositories
+    pub fn archive_urls(&self, refs: &[GitRef]) -> Vec<(String, ArchiveKind)> {
+        match self {
+            PackageSource::Github(repo) => refs
+                .iter()
+                .map(|r| (repo.archive_url(r), ArchiveKind::TarGz))
+                .collect(),
+            PackageSource::Gitlab { host, path } => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                refs.iter()
+                    .map(|r| {
+                        let url = format!(
+                            "https://{}/{}/-/archive/{}/{}-{}.tar.gz",
+                            host,
+                            path,
+                            r.name(),
+                            name,
+                            r.name().replace('/', "-")
+                        );
+                        (url, ArchiveKind::TarGz)
+                    })
+                    .collect()
+            }
+            PackageSource::Gitea { host, owner, name } => refs
+                .iter()
+                .map(|r| {
+                    let url = format!(
+                        "https://{}/{}/{}/archive/{}.tar.gz",
+                        host,
+                        owner,
+                        name,
+                        r.name()
+                    );
+                    (url, ArchiveKind::TarGz)
+                })
+                .collect(),
+            PackageSource::Archive { url, kind } => vec![(url.clone(), *kind)],
+            PackageSource::Git(_) => Vec::new(),
+        }
+    }
+
+    /// Whether falling back to `git clone` makes sense for this source
+    pub fn is_cloneable(&self) -> bool {
+        !matches!(self, PackageSource::Archive { .. })
+    }
+}
@@ -0,0 +1,165 @@
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    version::{Version, VersionReq},
+};
+
+/// QMOD manifest versions `qpm qmod build` writes, oldest first
+pub const SCHEMA_VERSIONS: &[&str] = &["1.0.0", "1.1.0", "1.2.0"];
+/// What manifests are written as unless qpm.json pins `qmodSchemaVersion`
+pub const DEFAULT_SCHEMA_VERSION: &str = "1.2.0";
+
+/// Fields every manifest needs, mod managers refuse to install a mod without them
+const REQUIRED: &[&str] = &[
+    "_QPVersion",
+    "name",
+    "id",
+    "author",
+    "version",
+    "packageId",
+    "packageVersion",
+    "coverImage",
+];
+const STRINGS: &[&str] = &[
+    "_QPVersion",
+    "name",
+    "id",
+    "author",
+    "porter",
+    "version",
+    "packageId",
+    "packageVersion",
+    "description",
+    "coverImage",
+    "modloader",
+];
+const STRING_LISTS: &[&str] = &["modFiles", "lateModFiles", "libraryFiles", "copyExtensions"];
+/// Fields added after 1.0.0, by the version they came with
+const ADDED: &[(&str, &str)] = &[("modloader", "1.2.0"), ("lateModFiles", "1.2.0")];
+
+/// The version `manifest` should be written as: `pinned` when qpm.json sets one, else what
+/// the manifest declares, else the default. Errors on versions qpm doesn't know and on
+/// manifests newer than the pin, an older one is upgraded.
+pub fn schema_version(manifest: &Value, pinned: Option<&str>) -> Result<String> {
+    let known = |version: &str, what: &str| {
+        if SCHEMA_VERSIONS.contains(&version) {
+            Ok(())
+        } else {
+            Err(Error::Validation(format!(
+                "{} uses QMOD schema version {}, qpm only writes {}",
+                what,
+                version,
+                SCHEMA_VERSIONS.join(", ")
+            )))
+        }
+    };
+    let declared = manifest["_QPVersion"].as_str();
+    if let Some(declared) = declared {
+        known(declared, "mod.json")?;
+    }
+    let pinned = match pinned {
+        Some(pinned) => pinned,
+        None => return Ok(declared.unwrap_or(DEFAULT_SCHEMA_VERSION).to_string()),
+    };
+    known(pinned, "qpm.json")?;
+    if let Some(declared) = declared.filter(|declared| newer(declared, pinned)) {
+        return Err(Error::Validation(format!(
+            "mod.json uses QMOD schema version {}, newer than the qmodSchemaVersion {} qpm.json pins",
+            declared, pinned
+        )));
+    }
+    Ok(pinned.to_string())
+}
+
+fn newer(version: &str, than: &str) -> bool {
+    match (Version::parse(version), Version::parse(than)) {
+        (Ok(version), Ok(than)) => version > than,
+        _ => false,
+    }
+}
+
+/// Checks `manifest` against its `_QPVersion`, listing every problem in one error
+pub fn validate(manifest: &Value) -> Result<()> {
+    let object = manifest
+        .as_object()
+        .ok_or_else(|| Error::Validation("mod.json must be an object".into()))?;
+    let schema = manifest["_QPVersion"]
+        .as_str()
+        .unwrap_or(DEFAULT_SCHEMA_VERSION);
+    let mut problems = Vec::new();
+    for field in REQUIRED {
+        if object.get(*field).is_none_or(Value::is_null) {
+            problems.push(format!("{} is missing", field));
+        }
+    }
+    for (field, value) in object.iter().filter(|(_, value)| !value.is_null()) {
+        let field = field.as_str();
+        if STRINGS.contains(&field) && !value.is_string() {
+            problems.push(format!("{} must be a string", field));
+        }
+        if STRING_LISTS.contains(&field) && !is_string_list(value) {
+            problems.push(format!("{} must be a list of strings", field));
+        }
+        if let Some((_, since)) = ADDED.iter().find(|(added, _)| *added == field) {
+            if newer(since, schema) {
+                problems.push(format!(
+                    "{} needs QMOD schema version {}, the manifest is {}",
+                    field, since, schema
+                ));
+            }
+        }
+    }
+    if let Some(version) = manifest["version"].as_str() {
+        if Version::parse(version).is_err() {
+            problems.push(format!("version {:?} is not a semantic version", version));
+        }
+    }
+    match &manifest["dependencies"] {
+        Value::Null => {}
+        Value::Array(dependencies) => {
+            for (i, dependency) in dependencies.iter().enumerate() {
+                let id = dependency["id"].as_str();
+                if id.is_none() {
+                    problems.push(format!("dependencies[{}] has no id", i));
+                }
+                let name = id.map_or_else(|| format!("dependencies[{}]", i), str::to_string);
+                match dependency["version"].as_str() {
+                    Some(range) if VersionReq::parse(range).is_err() => problems.push(format!(
+                        "dependency {} has the invalid version range {:?}",
+                        name, range
+                    )),
+                    Some(_) => {}
+                    None => problems.push(format!("dependency {} has no version range", name)),
+                }
+            }
+        }
+        _ => problems.push("dependencies must be a list".into()),
+    }
+    match &manifest["fileCopies"] {
+        Value::Null => {}
+        Value::Array(copies) => {
+            for (i, copy) in copies.iter().enumerate() {
+                if !copy["name"].is_string() || !copy["destination"].is_string() {
+                    problems.push(format!("fileCopies[{}] needs a name and a destination", i));
+                }
+            }
+        }
+        _ => problems.push("fileCopies must be a list".into()),
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Validation(format!(
+        "mod.json is not a valid QMOD {} manifest:\n  {}",
+        schema,
+        problems.join("\n  ")
+    )))
+}
+
+fn is_string_list(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|list| list.iter().all(Value::is_string))
+}
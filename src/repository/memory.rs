@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    data::shared_package::SharedPackageConfig,
+    error::Result,
+    repository::Repository,
+    version::{Version, VersionReq},
+};
+
+/// Packages held in memory, for resolving without the network or the config dir, like
+/// when exercising resolution against a hand built package graph
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRepository {
+    pub packages: BTreeMap<String, BTreeMap<String, SharedPackageConfig>>,
+    pub include_prerelease: bool,
+}
+
+impl MemoryRepository {
+    pub fn new() -> MemoryRepository {
+        MemoryRepository::default()
+    }
+
+    /// Adds a package version, replacing an earlier one with the same id and version
+    pub fn insert(&mut self, shared: SharedPackageConfig) -> &mut MemoryRepository {
+        let info = &shared.config.info;
+        self.packages
+            .entry(info.id.clone())
+            .or_default()
+            .insert(info.version.clone(), shared);
+        self
+    }
+
+    /// The versions of `id` satisfying `req`, with whether each was yanked
+    fn matching<'a>(
+        &'a self,
+        id: &str,
+        req: &'a VersionReq,
+    ) -> impl Iterator<Item = (Version, bool)> + 'a {
+        self.packages
+            .get(id)
+            .into_iter()
+            .flat_map(|versions| versions.values())
+            .filter_map(|shared| {
+                Some((
+                    Version::parse(&shared.config.info.version).ok()?,
+                    shared.yanked,
+                ))
+            })
+            .filter(move |(v, _)| req.matches_with_prerelease(v, self.include_prerelease))
+    }
+}
+
+impl Repository for MemoryRepository {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    /// Behaves like qpackages: prereleases only when asked for, yanked versions never
+    fn get_latest_matching(&self, id: &str, req: &VersionReq) -> Result<Option<Version>> {
+        Ok(self
+            .matching(id, req)
+            .filter(|(_, yanked)| !yanked)
+            .map(|(v, _)| v)
+            .max())
+    }
+
+    fn is_yanked_only(&self, id: &str, req: &VersionReq) -> Result<bool> {
+        let mut matching = self.matching(id, req).peekable();
+        Ok(matching.peek().is_some() && matching.all(|(_, yanked)| yanked))
+    }
+
+    fn get_shared_package(&self, id: &str, version: &str) -> Result<Option<SharedPackageConfig>> {
+        Ok(self
+            .packages
+            .get(id)
+            .and_then(|versions| versions.get(version))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::MemoryRepository;
+    use crate::{
+        data::{
+            config::Config,
+            dependency::Dependency,
+            package::{PackageConfig, PackageInfo},
+            shared_package::{SharedDependency, SharedPackageConfig},
+        },
+        error::{Error, Result},
+        repository::RepositoryChain,
+    };
+
+    fn dependency(id: &str, range: &str) -> Dependency {
+        Dependency {
+            id: id.to_string(),
+            version_range: range.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn package(id: &str, version: &str, dependencies: Vec<Dependency>) -> PackageConfig {
+        PackageConfig {
+            shared_dir: "shared".to_string(),
+            dependencies_dir: "extern".to_string(),
+            info: PackageInfo {
+                name: id.to_string(),
+                id: id.to_string(),
+                version: version.to_string(),
+                ..Default::default()
+            },
+            dependencies,
+            ..Default::default()
+        }
+    }
+
+    /// A published package, its dependencies given as (id, range, locked version)
+    fn published(id: &str, version: &str, locked: &[(&str, &str, &str)]) -> SharedPackageConfig {
+        let restored_dependencies: Vec<_> = locked
+            .iter()
+            .map(|(id, range, version)| SharedDependency {
+                dependency: dependency(id, range),
+                version: version.to_string(),
+            })
+            .collect();
+        let dependencies = restored_dependencies
+            .iter()
+            .map(|d| d.dependency.clone())
+            .collect();
+        SharedPackageConfig {
+            config: package(id, version, dependencies),
+            restored_dependencies,
+            ..Default::default()
+        }
+    }
+
+    /// A config whose cache is empty, so every package comes from the repository
+    fn config() -> Config {
+        Config {
+            cache_path: std::env::temp_dir()
+                .join(format!("qpm-memory-repository-{}", std::process::id()))
+                .display()
+                .to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The version each package of the project's tree resolved to, by id
+    fn resolve(
+        repository: &MemoryRepository,
+        dependencies: Vec<Dependency>,
+    ) -> Result<BTreeMap<String, String>> {
+        let project = package("app", "0.1.0", dependencies);
+        let shared = SharedPackageConfig::from_package(&project, repository, &config())?;
+        Ok(shared
+            .restored_dependencies
+            .into_iter()
+            .map(|d| (d.dependency.id, d.version))
+            .collect())
+    }
+
+    fn resolved(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(id, version)| (id.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_newest_matching_version() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "1.4.2", &[]))
+            .insert(published("c", "2.0.0", &[]));
+        let versions = resolve(&repository, vec![dependency("c", "^1.0.0")]).unwrap();
+        assert_eq!(versions, resolved(&[("c", "1.4.2")]));
+    }
+
+    #[test]
+    fn diamond_settles_on_one_version_satisfying_both_sides() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published("a", "1.0.0", &[("c", "^1.0.0", "1.0.0")]))
+            .insert(published("b", "1.0.0", &[("c", "^1.2.0", "1.2.0")]))
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "1.2.0", &[]));
+        let versions = resolve(
+            &repository,
+            vec![dependency("a", "^1.0.0"), dependency("b", "^1.0.0")],
+        )
+        .unwrap();
+        assert_eq!(
+            versions,
+            resolved(&[("a", "1.0.0"), ("b", "1.0.0"), ("c", "1.2.0")])
+        );
+    }
+
+    #[test]
+    fn incompatible_ranges_conflict() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published("a", "1.0.0", &[("c", "^1.0.0", "1.0.0")]))
+            .insert(published("b", "1.0.0", &[("c", "^2.0.0", "2.0.0")]))
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "2.0.0", &[]));
+        let result = resolve(
+            &repository,
+            vec![dependency("a", "^1.0.0"), dependency("b", "^1.0.0")],
+        );
+        assert!(matches!(result, Err(Error::Resolution(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn conflicts_settle_on_a_version_neither_side_was_locked_to() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published(
+                "a",
+                "1.0.0",
+                &[("c", ">=1.0.0, <1.4.0", "1.0.0")],
+            ))
+            .insert(published("b", "1.0.0", &[("c", "^1.2.0", "1.5.0")]))
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "1.3.0", &[("d", "^1.0.0", "1.0.0")]))
+            .insert(published("c", "1.5.0", &[]))
+            .insert(published("d", "1.0.0", &[]));
+        let versions = resolve(
+            &repository,
+            vec![dependency("a", "^1.0.0"), dependency("b", "^1.0.0")],
+        )
+        .unwrap();
+        // what the version in between depends on is collected too
+        assert_eq!(
+            versions,
+            resolved(&[
+                ("a", "1.0.0"),
+                ("b", "1.0.0"),
+                ("c", "1.3.0"),
+                ("d", "1.0.0")
+            ])
+        );
+    }
+
+    #[test]
+    fn prereleases_only_when_asked_for() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "1.1.0-beta.1", &[]));
+        let versions = resolve(&repository, vec![dependency("c", "^1.0.0")]).unwrap();
+        assert_eq!(versions, resolved(&[("c", "1.0.0")]));
+
+        repository.include_prerelease = true;
+        let versions = resolve(&repository, vec![dependency("c", "^1.0.0")]).unwrap();
+        assert_eq!(versions, resolved(&[("c", "1.1.0-beta.1")]));
+    }
+
+    #[test]
+    fn yanked_versions_are_skipped() {
+        let mut repository = MemoryRepository::new();
+        let mut yanked = published("c", "1.1.0", &[]);
+        yanked.yanked = true;
+        repository
+            .insert(published("c", "1.0.0", &[]))
+            .insert(yanked);
+        let versions = resolve(&repository, vec![dependency("c", "^1.0.0")]).unwrap();
+        assert_eq!(versions, resolved(&[("c", "1.0.0")]));
+    }
+
+    #[test]
+    fn a_source_with_only_yanked_matches_falls_through_to_the_next() {
+        let mut yanked = published("c", "1.1.0", &[]);
+        yanked.yanked = true;
+        let mut first = MemoryRepository::new();
+        first.insert(yanked);
+        let mut second = MemoryRepository::new();
+        second.insert(published("c", "1.0.0", &[]));
+        let chain = RepositoryChain {
+            repositories: vec![Box::new(first.clone()), Box::new(second)],
+        };
+        let project = package("app", "0.1.0", vec![dependency("c", "^1.0.0")]);
+        let shared = SharedPackageConfig::from_package(&project, &chain, &config()).unwrap();
+        assert_eq!(shared.restored_dependencies[0].version, "1.0.0");
+
+        // with nothing else to fall back to, the yanking is what gets reported
+        let result = resolve(&first, vec![dependency("c", "^1.0.0")]);
+        assert!(
+            matches!(&result, Err(Error::Resolution(message)) if message.contains("yanked")),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn the_projects_pin_wins_over_dependency_ranges() {
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(published("a", "1.0.0", &[("c", "^1.2.0", "1.2.0")]))
+            .insert(published("c", "1.0.0", &[]))
+            .insert(published("c", "1.2.0", &[]));
+        let mut pinned = dependency("c", "=1.0.0");
+        pinned.additional_data.unpinned_range = Some("^1.0.0".to_string());
+        let versions = resolve(&repository, vec![dependency("a", "^1.0.0"), pinned]).unwrap();
+        assert_eq!(versions, resolved(&[("a", "1.0.0"), ("c", "1.0.0")]));
+    }
+
+    #[test]
+    fn features_enabled_by_different_dependents_are_all_collected() {
+        let mut library = published("lib", "1.0.0", &[]);
+        library
+            .config
+            .features
+            .insert("x".to_string(), vec![dependency("fx", "^1.0.0")]);
+        library
+            .config
+            .features
+            .insert("y".to_string(), vec![dependency("fy", "^1.0.0")]);
+        // a and b each lock lib with one of its features
+        let dependent = |id: &str, feature: &str| {
+            let mut dependent = published(id, "1.0.0", &[("lib", "^1.0.0", "1.0.0")]);
+            for locked in &mut dependent.restored_dependencies {
+                locked.dependency.additional_data.features = Some(vec![feature.to_string()]);
+            }
+            dependent
+        };
+        let mut repository = MemoryRepository::new();
+        repository
+            .insert(library)
+            .insert(dependent("a", "x"))
+            .insert(dependent("b", "y"))
+            .insert(published("fx", "1.0.0", &[]))
+            .insert(published("fy", "1.0.0", &[]));
+        let versions = resolve(
+            &repository,
+            vec![dependency("a", "^1.0.0"), dependency("b", "^1.0.0")],
+        )
+        .unwrap();
+        assert_eq!(
+            versions,
+            resolved(&[
+                ("a", "1.0.0"),
+                ("b", "1.0.0"),
+                ("fx", "1.0.0"),
+                ("fy", "1.0.0"),
+                ("lib", "1.0.0")
+            ])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+pub mod memory;
+pub mod qpackages;
+
+use crate::{
+    data::{
+        config::Config, file_repository::FileRepository, shared_package::SharedDependency,
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    version::{Version, VersionReq},
+};
+
+/// Source name of the local repository `qpm install` and `qpm mirror import` fill
+pub const LOCAL_SOURCE: &str = "local";
+
+/// A source of packages that resolution and restore look versions up in
+pub trait Repository {
+    /// What dependencies pin this source by and `qpm list` reports it as
+    fn name(&self) -> &str;
+
+    /// The highest version of `id` satisfying `req` that can be used, `None` if there is none
+    fn get_latest_matching(&self, id: &str, req: &VersionReq) -> Result<Option<Version>>;
+
+    /// Whether versions of `id` satisfy `req` but every one of them was yanked, which
+    /// explains why [`Repository::get_latest_matching`] found none
+    fn is_yanked_only(&self, _id: &str, _req: &VersionReq) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// The shared config of exactly `id` at `version`, `None` if this repository doesn't have it
+    fn get_shared_package(&self, id: &str, version: &str) -> Result<Option<SharedPackageConfig>>;
+
+    /// The repositories this one consults, in priority order, empty unless it combines several
+    fn sources(&self) -> Vec<&dyn Repository> {
+        Vec::new()
+    }
+}
+
+/// The source named `name` within `repository`, for dependencies pinned to one
+pub fn pinned<'a>(repository: &'a dyn Repository, name: &str) -> Result<&'a dyn Repository> {
+    if repository.name() == name {
+        return Ok(repository);
+    }
+    let sources = repository.sources();
+    if let Some(source) = sources.iter().find(|source| source.name() == name) {
+        return Ok(*source);
+    }
+    let known: Vec<_> = sources.iter().map(|source| source.name()).collect();
+    Err(Error::Resolution(format!(
+        "unknown source {}, the configured ones are {}",
+        name,
+        known.join(", ")
+    )))
+}
+
+/// Where `dependency` is restored from: its local path, the source it is pinned to, or
+/// the first source that has its version
+pub fn locate(repository: &dyn Repository, dependency: &SharedDependency) -> Result<String> {
+    let data = &dependency.dependency.additional_data;
+    if let Some(local_path) = &data.local_path {
+        return Ok(format!("path {}", local_path));
+    }
+    if let Some(source) = &data.source {
+        return Ok(pinned(repository, source)?.name().to_string());
+    }
+    let mut sources = repository.sources();
+    if sources.is_empty() {
+        sources.push(repository);
+    }
+    for source in sources {
+        if source
+            .get_shared_package(&dependency.dependency.id, &dependency.version)?
+            .is_some()
+        {
+            return Ok(source.name().to_string());
+        }
+    }
+    Ok("unknown".to_string())
+}
+
+/// Repositories consulted in order, the first one that has an answer wins
+pub struct RepositoryChain<'a> {
+    pub repositories: Vec<Box<dyn Repository + 'a>>,
+}
+
+impl RepositoryChain<'_> {
+    /// Locally installed packages first, which also keeps resolution offline, then the
+    /// configured registries in order. Local paths win over all of them, but never get
+    /// this far.
+    pub fn new(config: &Config) -> Result<RepositoryChain<'_>> {
+        let mut repositories: Vec<Box<dyn Repository>> = vec![Box::new(FileRepository::read()?)];
+        for registry in config.registries() {
+            repositories.push(Box::new(qpackages::QPackages::new(registry, config)));
+        }
+        Ok(RepositoryChain { repositories })
+    }
+}
+
+impl Repository for RepositoryChain<'_> {
+    fn name(&self) -> &str {
+        "all sources"
+    }
+
+    fn get_latest_matching(&self, id: &str, req: &VersionReq) -> Result<Option<Version>> {
+        for repository in &self.repositories {
+            if let Some(version) = repository.get_latest_matching(id, req)? {
+                return Ok(Some(version));
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_yanked_only(&self, id: &str, req: &VersionReq) -> Result<bool> {
+        for repository in &self.repositories {
+            if repository.is_yanked_only(id, req)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_shared_package(&self, id: &str, version: &str) -> Result<Option<SharedPackageConfig>> {
+        for repository in &self.repositories {
+            if let Some(shared) = repository.get_shared_package(id, version)? {
+                return Ok(Some(shared));
+            }
+        }
+        Ok(None)
+    }
+
+    fn sources(&self) -> Vec<&dyn Repository> {
+        self.repositories
+            .iter()
+            .map(|repository| repository.as_ref() as &dyn Repository)
+            .collect()
+    }
+}
+
+impl Repository for FileRepository {
+    fn name(&self) -> &str {
+        LOCAL_SOURCE
+    }
+
+    fn get_latest_matching(&self, id: &str, req: &VersionReq) -> Result<Option<Version>> {
+        Ok(self
+            .get_artifact_matching(id, req)
+            .and_then(|shared| Version::parse(&shared.config.info.version).ok()))
+    }
+
+    fn get_shared_package(&self, id: &str, version: &str) -> Result<Option<SharedPackageConfig>> {
+        Ok(self.get_artifact(id, version).cloned())
+    }
+}
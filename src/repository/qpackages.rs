@@ -0,0 +1,169 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    data::{
+        config::{Config, RegistryConfig},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    network,
+    repository::Repository,
+    version::{Version, VersionReq},
+};
+
+/// Registry used when none are configured
+pub const API_URL: &str = "https://qpackages.com";
+/// Source name of qpackages when no registries are configured
+pub const NAME: &str = "qpackages";
+
+/// A qpackages compatible registry, reached with the network settings of `config`
+pub struct QPackages<'a> {
+    pub registry: RegistryConfig,
+    pub config: &'a Config,
+}
+
+impl QPackages<'_> {
+    pub fn new(registry: RegistryConfig, config: &Config) -> QPackages<'_> {
+        QPackages { registry, config }
+    }
+
+    /// Every configured registry, in order
+    pub fn all(config: &Config) -> Vec<QPackages<'_>> {
+        config
+            .registries()
+            .into_iter()
+            .map(|registry| QPackages::new(registry, config))
+            .collect()
+    }
+
+    /// The registry packages are published to, the first configured one
+    pub fn primary(config: &Config) -> QPackages<'_> {
+        QPackages::all(config).remove(0)
+    }
+
+    fn url(&self) -> &str {
+        self.registry.url.trim_end_matches('/')
+    }
+
+    /// Every package id known to the registry
+    pub fn get_package_ids(&self) -> Result<Vec<String>> {
+        Ok(get_json(self.url(), self.config)?.unwrap_or_default())
+    }
+
+    /// All published versions of a package, `None` if the registry doesn't know the id
+    pub fn get_versions(&self, id: &str) -> Result<Option<Vec<PackageVersion>>> {
+        get_json(&format!("{}/{}?limit=0", self.url(), id), self.config)
+    }
+
+    /// Publishes a package version, authorized by the registry's publish key
+    pub fn publish(&self, shared: &SharedPackageConfig, key: &str) -> Result<()> {
+        let info = &shared.config.info;
+        network::post_json(
+            &format!("{}/{}/{}", self.url(), info.id, info.version),
+            &serde_json::to_vec(shared)?,
+            &[format!("Authorization: {}", key)],
+            self.config,
+        )
+    }
+}
+
+impl QPackages<'_> {
+    /// The (yanked, available) versions of `id` satisfying `req`
+    fn matching(&self, id: &str, req: &VersionReq) -> Result<(Vec<Version>, Vec<Version>)> {
+        let versions = self.get_versions(id)?.unwrap_or_default();
+        let (yanked, available): (Vec<_>, Vec<_>) = versions
+            .iter()
+            .filter_map(|v| Some((Version::parse(&v.version).ok()?, v.yanked)))
+            .filter(|(v, _)| req.matches_with_prerelease(v, self.config.include_prerelease))
+            .partition(|(_, yanked)| *yanked);
+        let versions =
+            |versions: Vec<(Version, bool)>| versions.into_iter().map(|(v, _)| v).collect();
+        Ok((versions(yanked), versions(available)))
+    }
+}
+
+impl Repository for QPackages<'_> {
+    fn name(&self) -> &str {
+        &self.registry.name
+    }
+
+    /// Prereleases are only considered when the range asks for them or the config includes
+    /// them, yanked versions never are
+    fn get_latest_matching(&self, id: &str, req: &VersionReq) -> Result<Option<Version>> {
+        let (_, available) = self.matching(id, req)?;
+        Ok(available.into_iter().max())
+    }
+
+    fn is_yanked_only(&self, id: &str, req: &VersionReq) -> Result<bool> {
+        let (yanked, available) = self.matching(id, req)?;
+        Ok(available.is_empty() && !yanked.is_empty())
+    }
+
+    fn get_shared_package(&self, id: &str, version: &str) -> Result<Option<SharedPackageConfig>> {
+        get_json(&format!("{}/{}/{}", self.url(), id, version), self.config)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackageVersion {
+    pub id: String,
+    pub version: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+fn get_json<T: DeserializeOwned>(url: &str, config: &Config) -> Result<Option<T>> {
+    match network::get(url, config)? {
+        Some(body) => serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| Error::Network(format!("invalid response from {}: {}", url, e))),
+        None => Ok(None),
+    }
+}
+
+/// Every package id known to any configured registry, sorted
+pub fn get_package_ids(config: &Config) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for registry in QPackages::all(config) {
+        ids.extend(registry.get_package_ids()?);
+    }
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// All published versions of a package from the first configured registry that knows it,
+/// `None` if none does
+pub fn get_versions(id: &str, config: &Config) -> Result<Option<Vec<PackageVersion>>> {
+    for registry in QPackages::all(config) {
+        if let Some(versions) = registry.get_versions(id)? {
+            return Ok(Some(versions));
+        }
+    }
+    Ok(None)
+}
+
+/// The shared config of `id` at `version` from the first configured registry that has it
+pub fn get_shared_package(
+    id: &str,
+    version: &str,
+    config: &Config,
+) -> Result<Option<SharedPackageConfig>> {
+    for registry in QPackages::all(config) {
+        if let Some(shared) = registry.get_shared_package(id, version)? {
+            return Ok(Some(shared));
+        }
+    }
+    Ok(None)
+}
+
+/// The highest version of `id` satisfying `req` from the first configured registry that
+/// has one, see [`QPackages::get_latest_matching`]
+pub fn get_latest_matching(id: &str, req: &VersionReq, config: &Config) -> Result<Option<Version>> {
+    for registry in QPackages::all(config) {
+        if let Some(version) = registry.get_latest_matching(id, req)? {
+            return Ok(Some(version));
+        }
+    }
+    Ok(None)
+}
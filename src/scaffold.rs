@@ -0,0 +1,236 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    data::config::Config,
+    error::{Error, Result},
+    network::{
+        self,
+        git::{self, CloneOptions},
+    },
+};
+
+pub const CMAKE_LISTS: &str = r#"cmake_minimum_required(VERSION 3.21)
+project({{id}} VERSION {{version}})
+
+set(CMAKE_CXX_STANDARD 20)
+set(CMAKE_CXX_STANDARD_REQUIRED ON)
+
+include(${CMAKE_CURRENT_SOURCE_DIR}/extern.cmake OPTIONAL)
+
+file(GLOB_RECURSE cpp_file_list ${CMAKE_CURRENT_SOURCE_DIR}/src/*.cpp)
+add_library({{id}} SHARED ${cpp_file_list})
+
+target_include_directories({{id}} PRIVATE include shared ${QPM_INCLUDE_DIRS})
+target_link_libraries({{id}} PRIVATE ${QPM_LINK_LIBRARIES})
+target_compile_definitions({{id}} PRIVATE MOD_ID="{{id}}" VERSION="{{version}}")
+"#;
+
+pub const ANDROID_MK: &str = r#"LOCAL_PATH := $(call my-dir)
+-include $(LOCAL_PATH)/extern.mk
+
+include $(CLEAR_VARS)
+LOCAL_MODULE := {{id}}
+LOCAL_SRC_FILES += $(wildcard $(LOCAL_PATH)/src/*.cpp)
+LOCAL_C_INCLUDES += $(LOCAL_PATH)/include $(LOCAL_PATH)/shared $(QPM_INCLUDE_DIRS) $(QPM_C_INCLUDES)
+LOCAL_CPP_FEATURES += $(QPM_CPP_FEATURES)
+# the newer of C++20 and what the dependencies need
+LOCAL_CPPFLAGS += -std=c++$(lastword $(sort 20 $(QPM_CPP_STANDARD))) -DMOD_ID='"{{id}}"' -DVERSION='"{{version}}"' $(QPM_CPPFLAGS)
+include $(BUILD_SHARED_LIBRARY)
+"#;
+
+pub const APPLICATION_MK: &str = r#"APP_ABI := arm64-v8a
+APP_PLATFORM := android-24
+APP_STL := c++_static
+"#;
+
+pub const MAIN_HPP: &str = r#"#pragma once
+
+// {{name}} by {{author}}
+#define MOD_EXPORT extern "C" __attribute__((visibility("default")))
+"#;
+
+pub const MAIN_CPP: &str = r#"#include "main.hpp"
+
+// Called as soon as the mod is loaded
+MOD_EXPORT void setup() {}
+
+// Called once the game is ready for the mod to do its work
+MOD_EXPORT void load() {}
+"#;
+
+pub const GITIGNORE_ENTRIES: &[&str] = &[
+    "build/",
+    "extern/",
+    "extern.cmake",
+    "extern.mk",
+    "ndkpath.txt",
+    "mods/",
+    "*.qmod",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildSystem {
+    CMake,
+    NdkBuild,
+}
+
+impl BuildSystem {
+    pub fn parse(name: &str) -> Option<BuildSystem> {
+        match name.to_lowercase().as_str() {
+            "cmake" => Some(BuildSystem::CMake),
+            "ndk-build" | "ndkbuild" | "android.mk" => Some(BuildSystem::NdkBuild),
+            _ => None,
+        }
+    }
+}
+
+/// A template file, `path` being relative to the project root
+#[derive(Clone, Debug)]
+pub struct TemplateFile {
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+impl TemplateFile {
+    fn new(path: &str, contents: &str) -> TemplateFile {
+        TemplateFile {
+            path: path.to_string(),
+            contents: contents.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// The files of the built in mod template
+pub fn default_files(build_system: BuildSystem) -> Vec<TemplateFile> {
+    let mut files = vec![
+        TemplateFile::new("include/main.hpp", MAIN_HPP),
+        TemplateFile::new("src/main.cpp", MAIN_CPP),
+    ];
+    match build_system {
+        BuildSystem::CMake => files.push(TemplateFile::new("CMakeLists.txt", CMAKE_LISTS)),
+        BuildSystem::NdkBuild => {
+            files.push(TemplateFile::new("Android.mk", ANDROID_MK));
+            files.push(TemplateFile::new("Application.mk", APPLICATION_MK));
+        }
+    }
+    files
+}
+
+/// Looks up `name` among the templates registered in the config, otherwise treats it as a url
+pub fn resolve_template<'a>(name: &'a str, config: &'a Config) -> &'a str {
+    config
+        .templates
+        .get(name)
+        .map(String::as_str)
+        .unwrap_or(name)
+}
+
+/// Shallow clones a template repository and reads all of its files, minus the git metadata
+pub fn fetch_template(url: &str, config: &Config) -> Result<Vec<TemplateFile>> {
+    let dest = config
+        .tmp_dir()
+        .join(format!("template-{}", std::process::id()));
+    if dest.exists() {
+        fs::remove_dir_all(&dest)?;
+    }
+    let options = CloneOptions {
+        shallow: true,
+        sparse_paths: None,
+        proxy: network::proxy(url, config),
+        tls: network::tls(url, config),
+    };
+    git::clone(url, None, &dest, &options)?;
+
+    let mut files = Vec::new();
+    let result = collect_files(&dest, &dest, &mut files);
+    fs::remove_dir_all(&dest)?;
+    result?;
+    if files.is_empty() {
+        return Err(Error::Validation(format!("template {} has no files", url)));
+    }
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<TemplateFile>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap();
+            files.push(TemplateFile {
+                path: relative.to_string_lossy().replace('\\', "/"),
+                contents: fs::read(&path)?,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Replaces every `{{key}}` placeholder with its value
+pub fn render(text: &str, vars: &BTreeMap<String, String>) -> String {
+    vars.iter().fold(text.to_string(), |text, (key, value)| {
+        text.replace(&format!("{{{{{}}}}}", key), value)
+    })
+}
+
+/// Writes the files below `dir`, skipping files that already exist. Placeholders are
+/// substituted in paths and in text contents, binary files are copied untouched.
+/// Returns the paths that were written.
+pub fn write_files(
+    dir: &Path,
+    files: &[TemplateFile],
+    vars: &BTreeMap<String, String>,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for file in files {
+        let path = dir.join(render(&file.path, vars));
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match std::str::from_utf8(&file.contents) {
+            Ok(text) => fs::write(&path, render(text, vars))?,
+            Err(_) => fs::write(&path, &file.contents)?,
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Appends the given entries to `dir/.gitignore` unless they are already listed
+pub fn ensure_gitignore(dir: &Path, entries: &[&str]) -> Result<()> {
+    let path = dir.join(".gitignore");
+    let mut contents = if path.exists() {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    let missing: Vec<&str> = entries
+        .iter()
+        .copied()
+        .filter(|entry| !contents.lines().any(|line| line.trim() == *entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for entry in missing {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,462 @@
+use std::{fs, path::Path};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+use crate::error::{Error, Result};
+
+const DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+fn definitions() -> Value {
+    json!({
+        "PackageConfig": {
+            "type": "object",
+            "required": ["sharedDir", "dependenciesDir", "info", "dependencies"],
+            "properties": {
+                "schemaVersion": { "type": "integer" },
+                "sharedDir": { "type": "string", "description": "Folder of the headers this package exposes" },
+                "dependenciesDir": { "type": "string", "description": "Folder dependencies are restored into" },
+                "info": { "$ref": "#/definitions/PackageInfo" },
+                "dependencies": { "type": "array", "items": { "$ref": "#/definitions/Dependency" } },
+                "overrides": {
+                    "type": "object",
+                    "additionalProperties": { "$ref": "#/definitions/DependencyOverride" }
+                },
+                "abis": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "ABIs to build and restore binaries for, arm64-v8a when empty"
+                },
+                "features": {
+                    "type": "object",
+                    "description": "Optional dependencies by feature name, enabled by dependents",
+                    "additionalProperties": {
+                        "type": "array",
+                        "items": { "$ref": "#/definitions/Dependency" }
+                    }
+                },
+                "target": {
+                    "type": "object",
+                    "description": "What the project runs on, picks conditional dependencies",
+                    "properties": {
+                        "gameVersion": { "type": "string" },
+                        "platforms": { "type": "array", "items": { "type": "string" } }
+                    }
+                },
+                "ndk": { "type": "string", "description": "NDK revision the project builds with, like 25.2" },
+                "assets": {
+                    "type": "array",
+                    "description": "Files dependent mods ship in their .qmod, copied to destination on the device",
+                    "items": {
+                        "type": "object",
+                        "required": ["path", "destination"],
+                        "properties": {
+                            "path": { "type": "string" },
+                            "destination": { "type": "string" }
+                        }
+                    }
+                },
+                "includeLayout": {
+                    "enum": ["flat", "namespaced"],
+                    "description": "Merge dependency headers into includes/ or keep them in includes/<id>/"
+                },
+                "externLayout": {
+                    "type": "object",
+                    "description": "Folder names and binary arrangement in the dependencies dir",
+                    "properties": {
+                        "includesDir": { "type": "string", "description": "Folder headers are restored to, includes by default" },
+                        "libsDir": { "type": "string", "description": "Folder binaries are restored to, libs by default" },
+                        "binaryLayout": {
+                            "enum": ["shared", "perPackage"],
+                            "description": "All binaries in libs/<abi>/ or each in libs/<id>/<abi>/"
+                        }
+                    }
+                },
+                "hooks": {
+                    "type": "object",
+                    "description": "Shell commands qpm restore runs in the project folder, with the resolution in QPM_* environment variables",
+                    "properties": {
+                        "preRestore": { "type": "array", "items": { "type": "string" }, "description": "Run after resolving, before restoring" },
+                        "postRestore": { "type": "array", "items": { "type": "string" }, "description": "Run after restoring" }
+                    }
+                },
+                "qmod": {
+                    "type": "object",
+                    "description": "Files qpm qmod zip packs, paths relative to the project",
+                    "properties": {
+                        "modFiles": { "type": "array", "items": { "type": "string" } },
+                        "lateModFiles": { "type": "array", "items": { "type": "string" } },
+                        "libraryFiles": { "type": "array", "items": { "type": "string" } },
+                        "fileCopies": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["path", "destination"],
+                                "properties": {
+                                    "path": { "type": "string" },
+                                    "destination": { "type": "string" }
+                                }
+                            }
+                        },
+                        "coverImage": { "type": "string" }
+                    }
+                },
+                "qmodSchemaVersion": { "enum": ["1.0.0", "1.1.0", "1.2.0"], "description": "QMOD manifest version qpm qmod build writes" },
+                "buildInfo": { "type": "boolean", "description": "Generate qpm_build_info.hpp on every restore and build" },
+                "additionalData": { "$ref": "#/definitions/AdditionalPackageData" }
+            }
+        },
+        "PackageInfo": {
+            "type": "object",
+            "required": ["name", "id", "version"],
+            "properties": {
+                "name": { "type": "string" },
+                "id": { "type": "string" },
+                "version": { "type": "string" },
+                "url": { "type": ["string", "null"] },
+                "description": { "type": "string" },
+                "license": { "type": "string", "description": "SPDX identifier of the package's license" },
+                "authors": { "type": "array", "items": { "type": "string" } },
+                "changelog": { "type": "string", "description": "Link to the package's changelog" },
+                "releaseNotes": { "type": "string", "description": "What changed in this version" },
+                "additionalData": { "$ref": "#/definitions/AdditionalPackageData" }
+            }
+        },
+        "AdditionalPackageData": {
+            "type": "object",
+            "properties": {
+                "branchName": { "type": "string" },
+                "headersOnly": { "type": "boolean" },
+                "staticLinking": { "type": "boolean" },
+                "soLink": { "type": "string" },
+                "abiSoLinks": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Binary download per ABI besides arm64-v8a"
+                },
+                "debugSoLink": { "type": "string" },
+                "staticLink": { "type": "string", "description": "The .a of statically linked packages" },
+                "abiStaticLinks": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Static library download per ABI besides arm64-v8a"
+                },
+                "overrideSoName": { "type": "string" },
+                "extraBinaries": {
+                    "type": "array",
+                    "description": "Binaries shipped besides the main one",
+                    "items": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": { "type": "string", "description": "File name it is restored as" },
+                            "soLink": { "type": "string" },
+                            "abiSoLinks": { "type": "object", "additionalProperties": { "type": "string" } },
+                            "link": { "type": "boolean", "description": "Whether dependents link against it, false for plugins loaded at runtime" },
+                            "linkOptions": { "type": "array", "items": { "type": "string" } }
+                        }
+                    }
+                },
+                "modLink": { "type": "string" },
+                "extraFiles": { "type": "array", "items": { "type": "string" }, "description": "Paths or globs to ship besides the shared folder, `src -> dest` places them elsewhere in the includes" },
+                "subFolder": { "type": "string" },
+                "compileOptions": { "$ref": "#/definitions/CompileOptions" },
+                "gameVersions": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Game versions the package works with, exact like 1.37.0 or ranges like >=1.35.0, <1.38.0"
+                }
+            }
+        },
+        "CompileOptions": {
+            "type": "object",
+            "description": "Compiler settings dependents of the package need",
+            "properties": {
+                "systemIncludes": { "type": "array", "items": { "type": "string" } },
+                "cppFeatures": { "type": "array", "items": { "type": "string" } },
+                "cppFlags": { "type": "array", "items": { "type": "string" } },
+                "defines": { "type": "array", "items": { "type": "string" } },
+                "cppStandard": { "type": "integer", "minimum": 11 }
+            }
+        },
+        "Dependency": {
+            "type": "object",
+            "required": ["id", "versionRange"],
+            "properties": {
+                "id": { "type": "string" },
+                "as": { "type": "string", "description": "Name of the dependency's extern folders and CMake target instead of the id" },
+                "versionRange": { "type": "string" },
+                "additionalData": { "$ref": "#/definitions/AdditionalDependencyData" }
+            }
+        },
+        "AdditionalDependencyData": {
+            "type": "object",
+            "properties": {
+                "isPrivate": { "type": "boolean" },
+                "localPath": { "type": "string" },
+                "source": { "type": "string", "description": "Resolve only from this source, local or a registry name" },
+                "unpinnedRange": { "type": "string", "description": "The range before qpm dependency pin, restored by unpin" },
+                "url": { "type": "string", "description": "Fetch the sources from this repository or archive instead, without changing resolution" },
+                "branchName": { "type": "string", "description": "Fetch the sources from this branch instead of the version's tag" },
+                "soLink": { "type": "string", "description": "Download the arm64-v8a binary from here instead of the published link" },
+                "extraFiles": { "type": "array", "items": { "type": "string" }, "description": "Paths or globs to ship besides the shared folder, `src -> dest` places them elsewhere in the includes" },
+                "shallowClone": { "type": "boolean" },
+                "sparseCheckout": { "type": "boolean" },
+                "abiSoLinks": { "type": "object", "additionalProperties": { "type": "string" } },
+                "features": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Features of the dependency to enable"
+                },
+                "gameVersion": { "type": "string", "description": "Range of game versions this dependency applies to" },
+                "platforms": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Devices this dependency applies to"
+                }
+            }
+        },
+        "DependencyOverride": {
+            "type": "object",
+            "properties": {
+                "versionRange": { "type": "string" },
+                "url": { "type": "string" },
+                "localPath": { "type": "string" }
+            }
+        },
+        "SharedDependency": {
+            "type": "object",
+            "required": ["dependency", "version"],
+            "properties": {
+                "dependency": { "$ref": "#/definitions/Dependency" },
+                "version": { "type": "string" }
+            }
+        }
+    })
+}
+
+fn with_definitions(title: &str, root: &str) -> Value {
+    json!({
+        "$schema": DRAFT,
+        "title": title,
+        "$ref": format!("#/definitions/{}", root),
+        "definitions": definitions(),
+    })
+}
+
+/// Schema of qpm.json
+pub fn package_config() -> Value {
+    with_definitions("qpm.json", "PackageConfig")
+}
+
+/// Schema of qpm.shared.json
+pub fn shared_package_config() -> Value {
+    let mut schema = with_definitions("qpm.shared.json", "SharedPackageConfig");
+    schema["definitions"]["SharedPackageConfig"] = json!({
+        "type": "object",
+        "required": ["config", "restoredDependencies"],
+        "properties": {
+            "schemaVersion": { "type": "integer" },
+            "config": { "$ref": "#/definitions/PackageConfig" },
+            "restoredDependencies": {
+                "type": "array",
+                "items": { "$ref": "#/definitions/SharedDependency" }
+            },
+            "packageHash": { "type": "string" },
+            "signature": { "type": "string", "description": "ssh signature over the rest of the config" },
+            "yanked": { "type": "boolean", "description": "Not picked for new version ranges anymore" },
+            "deprecated": {
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string" },
+                    "replacement": { "type": "string", "description": "Id of the package to use instead" }
+                }
+            }
+        }
+    });
+    schema
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => {
+            let pointer = reference.trim_start_matches('#');
+            resolve(root, root.pointer(pointer).unwrap_or(&Value::Null))
+        }
+        None => schema,
+    }
+}
+
+fn validate_at(root: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = resolve(root, schema);
+    let location = if path.is_empty() { "/" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let actual = type_name(value);
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let matches = allowed
+            .iter()
+            .any(|t| *t == actual || (*t == "number" && actual == "integer"));
+        if !allowed.is_empty() && !matches {
+            errors.push(format!(
+                "{}: expected {}, found {}",
+                location,
+                allowed.join(" or "),
+                actual
+            ));
+            return;
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(root, schema, object, path, errors),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(root, item_schema, item, &format!("{}/{}", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn validate_object(
+    root: &Value,
+    schema: &Value,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let location = if path.is_empty() { "/" } else { path };
+    for required in schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !object.contains_key(required) {
+            errors.push(format!(
+                "{}: missing required field \"{}\"",
+                location, required
+            ));
+        }
+    }
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, child) in object {
+        let child_path = format!("{}/{}", path, key);
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => validate_at(root, child_schema, child, &child_path, errors),
+            None => {
+                if let Some(additional) =
+                    schema.get("additionalProperties").filter(|a| a.is_object())
+                {
+                    validate_at(root, additional, child, &child_path, errors);
+                }
+            }
+        }
+    }
+}
+
+fn unknown_at(root: &Value, schema: &Value, value: &Value, path: &str, unknown: &mut Vec<String>) {
+    let schema = resolve(root, schema);
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties").filter(|a| a.is_object());
+            for (key, child) in object {
+                let child_path = format!("{}/{}", path, key);
+                match (properties.and_then(|p| p.get(key)), additional) {
+                    (Some(child_schema), _) | (None, Some(child_schema)) => {
+                        unknown_at(root, child_schema, child, &child_path, unknown)
+                    }
+                    (None, None) if properties.is_some() => unknown.push(child_path),
+                    (None, None) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    unknown_at(root, item_schema, item, &format!("{}/{}", path, i), unknown);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Paths of fields in `value` that `schema` doesn't describe, which serde silently drops
+pub fn unknown_fields(schema: &Value, value: &Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    unknown_at(schema, schema, value, "", &mut unknown);
+    unknown
+}
+
+/// Every place `value` violates `schema`, as `/json/pointer: problem` messages
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, schema, value, "", &mut errors);
+    errors
+}
+
+/// Reads a json file into a value, reporting syntax errors with their line and column
+pub fn read_value(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| {
+        let message = e.to_string();
+        let suffix = format!(" at line {} column {}", e.line(), e.column());
+        Error::Validation(format!(
+            "{}:{}:{}: {}",
+            path.display(),
+            e.line(),
+            e.column(),
+            message.trim_end_matches(&suffix)
+        ))
+    })
+}
+
+/// Writes a manifest the same way every time: keys sorted, indented by two spaces and
+/// ending in a newline
+pub fn write_value(path: &Path, value: &Value) -> Result<()> {
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Deserializes a value read from `path`, reporting schema violations with the path of the
+/// offending field rather than serde's first error
+pub fn from_value<T: DeserializeOwned>(path: &Path, value: Value, schema: &Value) -> Result<T> {
+    let errors = validate(schema, &value);
+    if !errors.is_empty() {
+        return Err(Error::Validation(format!(
+            "{} is invalid:\n    {}",
+            path.display(),
+            errors.join("\n    ")
+        )));
+    }
+    serde_json::from_value(value)
+        .map_err(|e| Error::Validation(format!("{}: {}", path.display(), e)))
+}
+
+pub fn read_validated<T: DeserializeOwned>(path: &Path, schema: &Value) -> Result<T> {
+    from_value(path, read_value(path)?, schema)
+}
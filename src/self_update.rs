@@ -0,0 +1,213 @@
+use std::{
+    env, fs,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache,
+    data::config::{get_config_dir, Config},
+    error::{Error, Result},
+    network,
+    utils::{fs::write_atomic, hash::sha256_file},
+    version::Version,
+};
+
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/sc2ad/QuestPackageManager-Rust/releases/latest";
+/// The version qpm was built as
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Seconds between the checks behind the new version notice
+const CHECK_INTERVAL: u64 = 24 * 60 * 60;
+/// Seconds the notice's check may take, it must not hold up the command it follows
+const CHECK_TIMEOUT: u32 = 5;
+
+/// The parts of a GitHub release qpm reads
+#[derive(Deserialize, Clone, Debug)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> Result<Version> {
+        Version::parse(self.tag_name.trim_start_matches('v'))
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// When the notice last looked for a release, in the config dir
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct CheckState {
+    checked_at: u64,
+    latest: Option<String>,
+}
+
+fn state_path() -> PathBuf {
+    get_config_dir().join("update-check.json")
+}
+
+/// The release asset with the binary for this platform, like `qpm-rust-linux-x86_64`.
+/// Its checksum is published next to it with a `.sha256` suffix.
+pub fn asset_name() -> String {
+    format!(
+        "qpm-rust-{}-{}{}",
+        env::consts::OS,
+        env::consts::ARCH,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+pub fn latest_release(config: &Config) -> Result<Release> {
+    let body = network::get(RELEASES_URL, config)?
+        .ok_or_else(|| Error::Network("qpm has no releases yet".into()))?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Whether `release` is newer than the running qpm
+pub fn is_newer(release: &Release) -> Result<bool> {
+    Ok(release.version()? > Version::parse(CURRENT_VERSION)?)
+}
+
+/// Downloads the binary of `release` for this platform, checks it against its published
+/// checksum and puts it in place of the running executable
+pub fn install(release: &Release, config: &Config) -> Result<PathBuf> {
+    let name = asset_name();
+    let binary = release.asset(&name).ok_or_else(|| {
+        Error::Validation(format!(
+            "release {} has no binary for this platform, {}",
+            release.tag_name, name
+        ))
+    })?;
+    let checksum = release.asset(&format!("{}.sha256", name)).ok_or_else(|| {
+        Error::Validation(format!(
+            "release {} publishes no checksum for {}, refusing to install it",
+            release.tag_name, name
+        ))
+    })?;
+
+    let exe = env::current_exe()?.canonicalize()?;
+    let new = sibling(&exe, "new");
+    network::download_file(&binary.browser_download_url, &new, config)?;
+    let verified = verify(&new, &checksum.browser_download_url, config).and_then(|_| {
+        make_executable(&new)?;
+        swap(&exe, &new)
+    });
+    if verified.is_err() {
+        let _ = fs::remove_file(&new);
+    }
+    verified.map(|_| exe)
+}
+
+fn verify(binary: &Path, checksum_url: &str, config: &Config) -> Result<()> {
+    let published = network::get(checksum_url, config)?
+        .ok_or_else(|| Error::Network(format!("{} does not exist", checksum_url)))?;
+    // `<hash>  <file name>` like sha256sum writes, or the hash alone
+    let published = String::from_utf8_lossy(&published)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = sha256_file(binary)?;
+    if published != actual {
+        return Err(Error::Validation(format!(
+            "the downloaded binary has the checksum {} instead of the published {}",
+            actual, published
+        )));
+    }
+    Ok(())
+}
+
+/// `<exe>.<suffix>` next to `exe`
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut path = exe.as_os_str().to_owned();
+    path.push(format!(".{}", suffix));
+    PathBuf::from(path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Replaces `exe` with `new`. Windows won't replace a running executable but lets it be
+/// renamed, so it is moved to `<exe>.old` first and deleted on a later run.
+fn swap(exe: &Path, new: &Path) -> Result<()> {
+    if cfg!(windows) {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+        if let Err(e) = fs::rename(new, exe) {
+            // put the old one back rather than leave no qpm at all
+            fs::rename(&old, exe)?;
+            return Err(e.into());
+        }
+    } else {
+        fs::rename(new, exe)?;
+    }
+    Ok(())
+}
+
+/// Deletes the executable a Windows self-update left behind
+pub fn clean_up() {
+    if let Ok(exe) = env::current_exe() {
+        let _ = fs::remove_file(sibling(&exe, "old"));
+    }
+}
+
+/// Prints a notice when a newer qpm was released, looking it up at most once a day. Quiet
+/// when `updateCheck` is off, stderr isn't a terminal or anything fails.
+pub fn notify(config: &Config) {
+    if !config.update_check || !std::io::stderr().is_terminal() {
+        return;
+    }
+    let mut state: CheckState = fs::read(state_path())
+        .ok()
+        .and_then(|state| serde_json::from_slice(&state).ok())
+        .unwrap_or_default();
+    let now = cache::now();
+    if now.saturating_sub(state.checked_at) >= CHECK_INTERVAL {
+        let mut quick = config.clone();
+        quick.timeout = CHECK_TIMEOUT;
+        quick.connect_timeout = CHECK_TIMEOUT;
+        state.checked_at = now;
+        // an offline machine checks again tomorrow instead of on every command
+        state.latest = latest_release(&quick)
+            .ok()
+            .map(|release| release.tag_name)
+            .or(state.latest);
+        if let Ok(json) = serde_json::to_vec_pretty(&state) {
+            let _ = write_atomic(&state_path(), &json);
+        }
+    }
+    let newer = state.latest.as_deref().and_then(|latest| {
+        let latest = Version::parse(latest.trim_start_matches('v')).ok()?;
+        (latest > Version::parse(CURRENT_VERSION).ok()?).then_some(latest)
+    });
+    if let Some(latest) = newer {
+        eprintln!(
+            "qpm {} is available, you have {}. Run qpm self-update to install it, or qpm config set updateCheck false to stop these notices.",
+            latest, CURRENT_VERSION
+        );
+    }
+}
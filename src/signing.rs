@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{config::Config, shared_package::SharedPackageConfig},
+    error::{Error, Result},
+    utils::fs::encode_path_component,
+};
+
+/// ssh-keygen namespace qpm signatures are made in, so a signature over a package can't
+/// be passed off as one over something else
+pub const NAMESPACE: &str = "qpm";
+
+/// What restore does about packages that aren't signed by a trusted key
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SignaturePolicy {
+    Ignore,
+    #[default]
+    Warn,
+    Require,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SigningConfig {
+    /// Private ssh key `qpm publish --sign` signs with
+    pub key: Option<String>,
+    /// Public keys, as in a `.pub` file, that restored packages may be signed by
+    pub trusted_keys: Vec<String>,
+    pub policy: SignaturePolicy,
+}
+
+/// How a package's signature checked out
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verification {
+    Valid,
+    Unsigned,
+    Invalid(String),
+}
+
+/// The bytes a signature covers: the shared config without its signature and without
+/// the yanked and deprecated marks, which the registry sets after publishing
+fn signed_bytes(shared: &SharedPackageConfig) -> Result<Vec<u8>> {
+    let mut unsigned = shared.clone();
+    unsigned.signature = None;
+    unsigned.yanked = false;
+    unsigned.deprecated = None;
+    Ok(serde_json::to_vec(&unsigned)?)
+}
+
+fn ssh_keygen(args: &[&str], key: &Path, input: &[u8]) -> Result<std::process::Output> {
+    let mut child = Command::new("ssh-keygen")
+        .args(args)
+        .arg(key)
+        .args(["-n", NAMESPACE])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Validation(format!("failed to run ssh-keygen: {}", e)))?;
+    child.stdin.take().unwrap().write_all(input)?;
+    Ok(child.wait_with_output()?)
+}
+
+/// Signs `shared` with the private ssh key at `key`, returning the armored signature
+pub fn sign(shared: &SharedPackageConfig, key: &Path) -> Result<String> {
+    let output = ssh_keygen(&["-Y", "sign", "-f"], key, &signed_bytes(shared)?)?;
+    if !output.status.success() {
+        return Err(Error::Validation(format!(
+            "signing with {} failed: {}",
+            key.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Checks the signature of `shared` against `trusted_keys`
+pub fn verify(
+    shared: &SharedPackageConfig,
+    trusted_keys: &[String],
+    config: &Config,
+) -> Result<Verification> {
+    let signature = match &shared.signature {
+        Some(signature) => signature,
+        None => return Ok(Verification::Unsigned),
+    };
+    let tmp = config.tmp_dir();
+    fs::create_dir_all(&tmp)?;
+    let info = &shared.config.info;
+    let name = format!(
+        "{}-{}",
+        encode_path_component(&info.id),
+        encode_path_component(&info.version)
+    );
+    let allowed_signers = tmp.join(format!("{}.allowed_signers", name));
+    let signature_file = tmp.join(format!("{}.sig", name));
+    let signers: String = trusted_keys
+        .iter()
+        .map(|key| {
+            format!(
+                "{} namespaces=\"{}\" {}\n",
+                NAMESPACE,
+                NAMESPACE,
+                key.trim()
+            )
+        })
+        .collect();
+    fs::write(&allowed_signers, signers)?;
+    fs::write(&signature_file, signature)?;
+
+    let signature_path = signature_file.display().to_string();
+    let output = ssh_keygen(
+        &["-Y", "verify", "-I", NAMESPACE, "-s", &signature_path, "-f"],
+        &allowed_signers,
+        &signed_bytes(shared)?,
+    );
+    let _ = fs::remove_file(&allowed_signers);
+    let _ = fs::remove_file(&signature_file);
+    let output = output?;
+    if output.status.success() {
+        Ok(Verification::Valid)
+    } else {
+        Ok(Verification::Invalid(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Applies the configured policy to the signature of a restored package, returning the
+/// problem when the policy only warns about it
+pub fn check(shared: &SharedPackageConfig, config: &Config) -> Result<Option<String>> {
+    let signing = &config.signing;
+    let policy = signing.policy;
+    if policy == SignaturePolicy::Ignore {
+        return Ok(None);
+    }
+    if signing.trusted_keys.is_empty() {
+        // nothing to check against, only worth complaining about when it is required
+        return match policy {
+            SignaturePolicy::Require => Err(Error::Validation(
+                "signatures are required but no trusted keys are configured, see qpm config set signing.trustedKeys".into(),
+            )),
+            _ => Ok(None),
+        };
+    }
+    let info = &shared.config.info;
+    let problem = match verify(shared, &signing.trusted_keys, config)? {
+        Verification::Valid => return Ok(None),
+        Verification::Unsigned => format!("{} {} is not signed", info.id, info.version),
+        Verification::Invalid(reason) => format!(
+            "{} {} is not signed by a trusted key: {}",
+            info.id, info.version, reason
+        ),
+    };
+    match policy {
+        SignaturePolicy::Require => Err(Error::Validation(problem)),
+        _ => Ok(Some(problem)),
+    }
+}
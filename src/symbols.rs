@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    build, cache,
+    data::{
+        config::Config,
+        package::{PackageConfig, DEFAULT_ABI},
+        shared_package::SharedPackageConfig,
+    },
+    error::{Error, Result},
+    network,
+    repository::Repository,
+    utils::fs::remove_dir_if_exists,
+};
+
+/// Folder in the dependencies dir unstripped binaries are restored into
+pub const SYMBOLS_DIR: &str = "symbols";
+
+pub fn symbols_dir(dir: &Path, package: &PackageConfig) -> PathBuf {
+    dir.join(&package.dependencies_dir).join(SYMBOLS_DIR)
+}
+
+/// Where the unstripped binary of a cached package version is kept
+pub fn cached_symbols(config: &Config, package: &PackageConfig) -> PathBuf {
+    let info = &package.info;
+    cache::package_dir(config, &info.id, &info.version)
+        .join("debug")
+        .join(cache::so_name(package))
+}
+
+/// Downloads the `debugSoLink` of a package into the cache, None if it publishes none
+pub fn ensure_symbols(shared: &SharedPackageConfig, config: &Config) -> Result<Option<PathBuf>> {
+    let link = match &shared.config.info.additional_data.debug_so_link {
+        Some(link) => link,
+        None => return Ok(None),
+    };
+    let path = cached_symbols(config, &shared.config);
+    if !path.exists() {
+        outln!(
+            "Downloading symbols of {} {}",
+            shared.config.info.id,
+            shared.config.info.version
+        );
+        network::download_file(link, &path, config)?;
+    }
+    Ok(Some(path))
+}
+
+/// Replaces the symbols folder with the unstripped binaries of every restored dependency
+/// that has them, returning how many were found
+pub fn restore_symbols(
+    dir: &Path,
+    shared: &SharedPackageConfig,
+    repository: &dyn Repository,
+    config: &Config,
+) -> Result<usize> {
+    let symbols = symbols_dir(dir, &shared.config);
+    remove_dir_if_exists(&symbols)?;
+    fs::create_dir_all(&symbols)?;
+
+    let mut found = 0;
+    for dependency in &shared.restored_dependencies {
+        let dependency_shared = dependency.get_shared_package(repository, config)?;
+        if cache::is_headers_only(&dependency_shared.config) {
+            continue;
+        }
+        let so_name = cache::so_name(&dependency_shared.config);
+        // local checkouts aren't stripped until they're packaged
+        let path = match dependency.dependency.additional_data.local_dir() {
+            Some(local_dir) => cache::local_binary(&local_dir, DEFAULT_ABI, &so_name),
+            None => ensure_symbols(&dependency_shared, config)?,
+        };
+        if let Some(path) = path {
+            fs::copy(&path, symbols.join(&so_name))?;
+            found += 1;
+        }
+    }
+    Ok(found)
+}
+
+/// A frame of a tombstone or logcat backtrace, like
+/// `#00 pc 000000000001a2b4  /data/app/.../libfoo.so (BuildId: ...)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub index: String,
+    pub pc: String,
+    pub library: String,
+}
+
+pub fn parse_backtrace(text: &str) -> Vec<Frame> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let start = line.find('#')?;
+            let mut words = line[start..].split_whitespace();
+            let index = words.next()?.to_string();
+            if words.next()? != "pc" {
+                return None;
+            }
+            let pc = words.next()?.to_string();
+            let path = words.next()?;
+            let library = path.rsplit('/').next()?.to_string();
+            Some(Frame { index, pc, library })
+        })
+        .collect()
+}
+
+/// The unstripped binary for a library named in a backtrace: restored dependency
+/// symbols first, then this project's own build output
+pub fn find_symbols(dir: &Path, package: &PackageConfig, library: &str) -> Option<PathBuf> {
+    let restored = symbols_dir(dir, package).join(library);
+    if restored.exists() {
+        return Some(restored);
+    }
+    if library != cache::so_name(package) {
+        return None;
+    }
+    package.abis().iter().find_map(|abi| {
+        build::BUILD_TYPES.iter().find_map(|build_type| {
+            let options = build::BuildOptions {
+                abi: abi.clone(),
+                build_type: build_type.to_string(),
+            };
+            Some(build::output_dir(dir, &options).join(library)).filter(|path| path.exists())
+        })
+    })
+}
+
+/// The NDK's llvm-addr2line
+pub fn addr2line_path(ndk: &Path) -> Option<PathBuf> {
+    let prebuilt = ndk.join("toolchains/llvm/prebuilt");
+    let exe = if cfg!(windows) {
+        "llvm-addr2line.exe"
+    } else {
+        "llvm-addr2line"
+    };
+    fs::read_dir(prebuilt)
+        .ok()?
+        .flatten()
+        .map(|host| host.path().join("bin").join(exe))
+        .find(|path| path.exists())
+}
+
+/// Symbolizes `addresses` in `symbols`, one `function at file:line` per address
+pub fn addr2line(tool: &Path, symbols: &Path, addresses: &[String]) -> Result<Vec<String>> {
+    let output = Command::new(tool)
+        .args(["--demangle", "--functions", "--exe"])
+        .arg(symbols)
+        .args(addresses)
+        .output()
+        .map_err(|e| Error::Build(format!("failed to run {}: {}", tool.display(), e)))?;
+    if !output.status.success() {
+        return Err(Error::Build(format!(
+            "addr2line failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    Ok(lines.chunks(2).map(|pair| pair.join(" at ")).collect())
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{cancel, error::Result, logging, utils::ignore::IgnoreRules};
+
+/// What a tree copy does with symbolic links it comes across
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Copy what the link points to
+    #[default]
+    Follow,
+    /// Recreate the link with the same target, copying the target where links can't be made
+    Preserve,
+    /// Leave links out
+    Skip,
+}
+
+/// Where a copy is at, handed to the progress callback after every file
+#[derive(Clone, Copy, Debug)]
+pub struct CopyProgress<'a> {
+    /// The file just copied, relative to the source
+    pub path: &'a Path,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Decides what to copy from each path relative to the source and whether it is a folder
+pub type CopyFilter<'a> = &'a dyn Fn(&Path, bool) -> bool;
+
+/// How [`copy_tree`] copies
+#[derive(Default)]
+pub struct CopyOptions<'a> {
+    /// A rejected folder is skipped with everything inside
+    pub filter: Option<CopyFilter<'a>>,
+    /// Give the copies the modification times of the originals, so tools comparing them
+    /// don't consider everything changed
+    pub preserve_mtime: bool,
+    pub symlinks: SymlinkPolicy,
+    pub progress: Option<&'a dyn Fn(CopyProgress)>,
+}
+
+impl<'a> CopyOptions<'a> {
+    /// Copies everything, following links
+    pub fn new() -> CopyOptions<'a> {
+        CopyOptions::default()
+    }
+
+    pub fn filter(mut self, filter: CopyFilter<'a>) -> CopyOptions<'a> {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn preserve_mtime(mut self, preserve_mtime: bool) -> CopyOptions<'a> {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> CopyOptions<'a> {
+        self.symlinks = symlinks;
+        self
+    }
+
+    pub fn progress(mut self, progress: &'a dyn Fn(CopyProgress)) -> CopyOptions<'a> {
+        self.progress = Some(progress);
+        self
+    }
+}
+
+/// How much a copy copied
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CopyStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Recursively copies `src` into `dst`, merging with anything already there. `src` may
+/// also be a single file, copied to `dst` itself.
+pub fn copy_tree(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    let mut stats = CopyStats::default();
+    if fs::metadata(src)?.is_dir() {
+        walk(src, dst, Path::new(""), options, &mut stats)?;
+    } else {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        copy_file(src, dst, Path::new(""), options, &mut stats)?;
+    }
+    logging::debug(
+        "fs",
+        format!(
+            "copied {} to {}, {} files and {} bytes",
+            src.display(),
+            dst.display(),
+            stats.files,
+            stats.bytes
+        ),
+    );
+    Ok(stats)
+}
+
+fn walk(
+    src: &Path,
+    dst: &Path,
+    relative: &Path,
+    options: &CopyOptions,
+    stats: &mut CopyStats,
+) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    let mut entries = fs::read_dir(src)?.collect::<std::io::Result<Vec<_>>>()?;
+    // read_dir order depends on the file system, progress shouldn't
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        cancel::check()?;
+        let path = relative.join(entry.file_name());
+        let target = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            match options.symlinks {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Preserve => {
+                    let is_dir = entry.path().is_dir();
+                    if options.filter.is_some_and(|filter| !filter(&path, is_dir)) {
+                        continue;
+                    }
+                    if copy_link(&entry.path(), &target, is_dir).is_ok() {
+                        continue;
+                    }
+                }
+                SymlinkPolicy::Follow => {}
+            }
+        }
+        // the metadata of what a followed link points to
+        let is_dir = fs::metadata(entry.path())?.is_dir();
+        if options.filter.is_some_and(|filter| !filter(&path, is_dir)) {
+            continue;
+        }
+        if is_dir {
+            walk(&entry.path(), &target, &path, options, stats)?;
+        } else {
+            copy_file(&entry.path(), &target, &path, options, stats)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(
+    src: &Path,
+    dst: &Path,
+    relative: &Path,
+    options: &CopyOptions,
+    stats: &mut CopyStats,
+) -> Result<()> {
+    // a link left at the target by an earlier copy would have its target overwritten
+    if fs::symlink_metadata(dst).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        fs::remove_file(dst)?;
+    }
+    stats.bytes += fs::copy(src, dst)?;
+    stats.files += 1;
+    if options.preserve_mtime {
+        let modified = fs::metadata(src)?.modified()?;
+        fs::File::options()
+            .write(true)
+            .open(dst)?
+            .set_modified(modified)?;
+    }
+    if let Some(progress) = options.progress {
+        progress(CopyProgress {
+            path: relative,
+            files: stats.files,
+            bytes: stats.bytes,
+        });
+    }
+    Ok(())
+}
+
+/// Recreates the link at `src` as `dst`
+fn copy_link(src: &Path, dst: &Path, is_dir: bool) -> std::io::Result<()> {
+    let target: PathBuf = fs::read_link(src)?;
+    if fs::symlink_metadata(dst).is_ok() {
+        if dst.is_dir() && !fs::symlink_metadata(dst)?.file_type().is_symlink() {
+            fs::remove_dir_all(dst)?;
+        } else {
+            fs::remove_file(dst)?;
+        }
+    }
+    #[cfg(unix)]
+    {
+        let _ = is_dir;
+        std::os::unix::fs::symlink(target, dst)
+    }
+    #[cfg(windows)]
+    {
+        if is_dir {
+            std::os::windows::fs::symlink_dir(target, dst)
+        } else {
+            std::os::windows::fs::symlink_file(target, dst)
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (target, is_dir);
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// A filter leaving out what `ignore` ignores, for paths below `prefix` in the folder
+/// holding the `.qpmignore`
+pub fn ignoring<'a>(
+    ignore: &'a IgnoreRules,
+    prefix: &'a Path,
+) -> impl Fn(&Path, bool) -> bool + 'a {
+    move |path, is_dir| !ignore.is_ignored(&prefix.join(path), is_dir)
+}
@@ -0,0 +1,284 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Result, logging, utils::hash::fnv1a_hex};
+
+/// Paths of all files below `dir`, relative to it and sorted
+pub fn files_below(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), &path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    walk(dir, Path::new(""), &mut files)?;
+    // read_dir order depends on the file system
+    files.sort();
+    Ok(files)
+}
+
+/// Whether a relative path from an untrusted source can't be safely joined to a folder:
+/// it is absolute, has a drive letter or climbs out with `..`
+pub fn escapes_root(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    path.starts_with('/')
+        || path.starts_with('\\')
+        || drive
+        || path.split(['/', '\\']).any(|component| component == "..")
+}
+
+/// Symlinks `dst` to `src` when `symlink` is set and the platform allows it, copying
+/// otherwise
+pub fn link_or_copy(src: &Path, dst: &Path, symlink: bool) -> Result<()> {
+    if fs::symlink_metadata(dst).is_ok() {
+        fs::remove_file(dst)?;
+    }
+    if symlink {
+        // relative targets would resolve against the link's own directory
+        let src = &src.canonicalize()?;
+        #[cfg(unix)]
+        let linked = std::os::unix::fs::symlink(src, dst);
+        #[cfg(windows)]
+        let linked = std::os::windows::fs::symlink_file(src, dst);
+        #[cfg(not(any(unix, windows)))]
+        let linked: std::io::Result<()> = Err(std::io::ErrorKind::Unsupported.into());
+        if linked.is_ok() {
+            logging::debug(
+                "fs",
+                format!("linked {} to {}", dst.display(), src.display()),
+            );
+            return Ok(());
+        }
+    }
+    fs::copy(src, dst)?;
+    logging::debug(
+        "fs",
+        format!("copied {} to {}", src.display(), dst.display()),
+    );
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place, so
+/// readers see either the old or the new file but never half of one
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    logging::debug("fs", format!("wrote {}", path.display()));
+    Ok(())
+}
+
+/// Total size in bytes of all files below `path`
+pub fn dir_size(path: &Path) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += dir_size(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+pub fn remove_dir_if_exists(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        logging::debug("fs", format!("removing {}", dir.display()));
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Formats a byte count for humans, like `12.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Device names Windows reserves in every folder, whatever the extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Longest component [`encode_path_component`] makes, below the 255 bytes file systems
+/// allow so suffixes like `.extracting` still fit
+const MAX_COMPONENT_LEN: usize = 200;
+
+/// Turns an id or version into a single path component that is valid on every platform.
+/// Anything but ASCII letters, digits, `-`, `_` and `.` is written as `%XX`, as are
+/// trailing dots and the first character of names Windows reserves, so `1.0.0+build.1`
+/// becomes `1.0.0%2Bbuild.1`. Plain ids and versions come out unchanged. Names encoding
+/// past [`MAX_COMPONENT_LEN`] are cut short and end in `~` and a hash of the whole name,
+/// which [`decode_path_component`] can't reverse.
+pub fn encode_path_component(name: &str) -> String {
+    let stem = name.split('.').next().unwrap_or_default();
+    let reserved = RESERVED_NAMES.contains(&stem.to_ascii_lowercase().as_str());
+    let trailing_dots = name.len() - name.trim_end_matches('.').len();
+    let mut encoded = String::with_capacity(name.len());
+    for (i, byte) in name.bytes().enumerate() {
+        let safe = byte.is_ascii_alphanumeric() || b"-_.".contains(&byte);
+        if !safe || (i == 0 && reserved) || i >= name.len() - trailing_dots {
+            encoded.push_str(&format!("%{:02X}", byte));
+        } else {
+            encoded.push(byte as char);
+        }
+    }
+    if encoded.len() > MAX_COMPONENT_LEN {
+        let hash = fnv1a_hex(name.as_bytes());
+        let mut end = MAX_COMPONENT_LEN - hash.len() - 1;
+        // never cut an escape in half
+        if let Some(escape) = encoded[end.saturating_sub(2)..end].find('%') {
+            end = end - 2 + escape;
+        }
+        encoded.truncate(end);
+        encoded.push('~');
+        encoded.push_str(&hash);
+    }
+    encoded
+}
+
+/// Reverses [`encode_path_component`], leaving malformed escapes as they are
+pub fn decode_path_component(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Lets deep paths past the 260 character `MAX_PATH` limit work on Windows by making
+/// absolute ones verbatim (`\\?\`) paths. Paths within 100 characters of the limit are
+/// converted too, since the files below them would cross it. Other platforms have no
+/// such limit and get `path` back as is.
+pub fn long_path(path: PathBuf) -> PathBuf {
+    #[cfg(windows)]
+    {
+        // verbatim paths skip normalization, so they must be absolute and use backslashes
+        const MAX_PATH: usize = 260;
+        let text = path.to_string_lossy();
+        if path.is_absolute() && text.len() >= MAX_PATH - 100 && !text.starts_with(r"\\?\") {
+            let text = text.replace('/', r"\");
+            return match text.strip_prefix(r"\\") {
+                Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+                None => PathBuf::from(format!(r"\\?\{}", text)),
+            };
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_path_component, encode_path_component, MAX_COMPONENT_LEN};
+
+    #[test]
+    fn plain_names_are_unchanged() {
+        for name in ["bs-hook", "beatsaber_hook", "1.0.0", "3.14.0-beta.2"] {
+            assert_eq!(encode_path_component(name), name);
+        }
+    }
+
+    #[test]
+    fn illegal_characters_are_escaped() {
+        assert_eq!(encode_path_component("1.0.0+build.1"), "1.0.0%2Bbuild.1");
+        assert_eq!(encode_path_component("a/b\\c:d"), "a%2Fb%5Cc%3Ad");
+        assert_eq!(encode_path_component("<>|?*\""), "%3C%3E%7C%3F%2A%22");
+        assert_eq!(encode_path_component("100%"), "100%25");
+        assert_eq!(encode_path_component("caf\u{e9}"), "caf%C3%A9");
+        assert_eq!(encode_path_component("1.0."), "1.0%2E");
+        assert_eq!(encode_path_component(".."), "%2E%2E");
+    }
+
+    #[test]
+    fn reserved_names_are_escaped() {
+        assert_eq!(encode_path_component("con"), "%63on");
+        assert_eq!(encode_path_component("NUL"), "%4EUL");
+        assert_eq!(encode_path_component("com1.json"), "%63om1.json");
+        assert_eq!(encode_path_component("console"), "console");
+        assert_eq!(encode_path_component("lpt10"), "lpt10");
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        for name in [
+            "1.0.0+build.1",
+            "con",
+            "aux.h",
+            "a b",
+            "1.0.",
+            "100%",
+            "caf\u{e9}",
+        ] {
+            assert_eq!(decode_path_component(&encode_path_component(name)), name);
+        }
+    }
+
+    #[test]
+    fn long_names_are_capped() {
+        let long = "a".repeat(300);
+        let encoded = encode_path_component(&long);
+        assert_eq!(encoded.len(), MAX_COMPONENT_LEN);
+        assert!(encoded.starts_with(&"a".repeat(100)));
+        assert_ne!(encoded, encode_path_component(&"a".repeat(301)));
+
+        // escapes at the cut are dropped whole
+        for prefix in 0..3 {
+            let name = format!("{}{}", "b".repeat(prefix), "+".repeat(300));
+            let encoded = encode_path_component(&name);
+            assert!(encoded.len() <= MAX_COMPONENT_LEN);
+            let (kept, hash) = encoded.split_once('~').unwrap();
+            assert_eq!(hash.len(), 16);
+            assert!(kept.trim_start_matches('b').len() % 3 == 0, "{}", kept);
+        }
+
+        let short = "a".repeat(MAX_COMPONENT_LEN);
+        assert_eq!(encode_path_component(&short), short);
+    }
+}
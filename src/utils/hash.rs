@@ -0,0 +1,58 @@
+use std::{path::Path, process::Command};
+
+use crate::error::{Error, Result};
+
+/// 64-bit FNV-1a, stable across platforms and releases unlike `DefaultHasher`
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(bytes))
+}
+
+/// SHA-256 of a file as lowercase hex, computed by the platform's own tool
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let tools: &[(&str, &[&str])] = if cfg!(windows) {
+        &[("certutil", &["-hashfile"])]
+    } else {
+        &[("sha256sum", &[]), ("shasum", &["-a", "256"])]
+    };
+    for (tool, args) in tools {
+        let mut cmd = Command::new(tool);
+        cmd.args(*args).arg(path);
+        if cfg!(windows) {
+            cmd.arg("SHA256");
+        }
+        let output = match cmd.output() {
+            Ok(output) if output.status.success() => output,
+            _ => continue,
+        };
+        // certutil prints the hash on its second line, the others first thing
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let hash = stdout.lines().find_map(|line| {
+            let first = line
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            // older certutil versions separate the bytes with spaces
+            let joined = line.split_whitespace().collect::<String>();
+            vec![first, joined]
+                .into_iter()
+                .find(|word| word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()))
+        });
+        if let Some(hash) = hash {
+            return Ok(hash.to_lowercase());
+        }
+    }
+    Err(Error::Io(std::io::Error::other(format!(
+        "failed to hash {}, no sha256sum, shasum or certutil found",
+        path.display()
+    ))))
+}
@@ -0,0 +1,188 @@
+use std::{fs, path::Path};
+
+use crate::error::Result;
+
+/// Lists files to leave out when a package's files are copied, in gitignore syntax
+pub const IGNORE_FILE_NAME: &str = ".qpmignore";
+
+/// The patterns of a `.qpmignore`, matched against paths relative to the folder holding it.
+/// Like in a `.gitignore` the last matching pattern wins, `!` re-includes, a trailing `/`
+/// only matches folders and a pattern containing a `/` is anchored to that folder.
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    /// Matched against the whole relative path, `**/` prepended when unanchored
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRules {
+    /// The rules of the `.qpmignore` in `dir`, none if there isn't one
+    pub fn load(dir: &Path) -> Result<IgnoreRules> {
+        match fs::read_to_string(dir.join(IGNORE_FILE_NAME)) {
+            Ok(text) => Ok(IgnoreRules::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(IgnoreRules::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(text: &str) -> IgnoreRules {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            let anchored = pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+            if pattern.is_empty() {
+                continue;
+            }
+            rules.push(Rule {
+                glob: if anchored {
+                    pattern.to_string()
+                } else {
+                    format!("**/{}", pattern)
+                },
+                negated,
+                dir_only,
+            });
+        }
+        IgnoreRules { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path`, relative to the folder of the `.qpmignore`, is left out. Everything
+    /// inside an ignored folder is too.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut prefix = String::new();
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        for (i, component) in components.iter().enumerate() {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(component);
+            let last = i + 1 == components.len();
+            if self.matches(&prefix, !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether the last rule matching `path` itself ignores it
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && glob_matches(&rule.glob, path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Matches `text` against a glob where `*` and `?` stay within a path component, `**`
+/// spans components and `[...]` is a character class, `[!...]` a negated one
+pub fn glob_matches(glob: &str, text: &str) -> bool {
+    matches_from(glob.as_bytes(), text.as_bytes())
+}
+
+fn matches_from(glob: &[u8], text: &[u8]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some(b'*') if glob.get(1) == Some(&b'*') => {
+            let rest = &glob[2..];
+            match rest.strip_prefix(b"/") {
+                // `**/` matches no folder or any number of them
+                Some(rest) => {
+                    matches_from(rest, text)
+                        || text
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, c)| **c == b'/')
+                            .any(|(i, _)| matches_from(rest, &text[i + 1..]))
+                }
+                None => (0..=text.len()).any(|i| matches_from(rest, &text[i..])),
+            }
+        }
+        Some(b'*') => {
+            let rest = &glob[1..];
+            for i in 0..=text.len() {
+                if matches_from(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => {
+            text.first().is_some_and(|c| *c != b'/') && matches_from(&glob[1..], &text[1..])
+        }
+        Some(b'[') => match class_end(glob) {
+            Some(end) => {
+                text.first()
+                    .is_some_and(|c| *c != b'/' && class_matches(&glob[1..end], *c))
+                    && matches_from(&glob[end + 1..], &text[1..])
+            }
+            // an unclosed bracket is just a bracket
+            None => text.first() == Some(&b'[') && matches_from(&glob[1..], &text[1..]),
+        },
+        Some(b'\\') if glob.len() > 1 => {
+            text.first() == Some(&glob[1]) && matches_from(&glob[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && matches_from(&glob[1..], &text[1..]),
+    }
+}
+
+/// The index of the `]` closing the class opened at the start of `glob`
+fn class_end(glob: &[u8]) -> Option<usize> {
+    let mut i = 1;
+    if matches!(glob.get(i), Some(b'!') | Some(b'^')) {
+        i += 1;
+    }
+    // a `]` right after the opening is part of the class
+    if glob.get(i) == Some(&b']') {
+        i += 1;
+    }
+    glob[i..].iter().position(|c| *c == b']').map(|end| i + end)
+}
+
+fn class_matches(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class.first() {
+        Some(b'!') | Some(b'^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
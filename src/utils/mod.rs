@@ -0,0 +1,6 @@
+pub mod copy;
+pub mod fs;
+pub mod hash;
+pub mod ignore;
+pub mod output;
+pub mod prompt;
@@ -0,0 +1,44 @@
+use std::{
+    fmt,
+    io::{self, ErrorKind, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::error::{Error, Result};
+
+/// Set once a write found stdout closed
+static CLOSED: AtomicBool = AtomicBool::new(false);
+/// The first other error writing to stdout, which fails the command once it is done
+static FAILED: Mutex<Option<io::Error>> = Mutex::new(None);
+
+/// Writes command output to stdout. When whatever reads it stopped, like `head` after its
+/// lines, the rest of the output is dropped and the command still finishes, so caches and
+/// files aren't left half written. Other errors, like a full disk behind a redirected stdout,
+/// drop the rest of the output as well and are reported by [`check`].
+pub fn write(args: fmt::Arguments) {
+    if CLOSED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut stdout = io::stdout().lock();
+    match stdout.write_fmt(args) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => CLOSED.store(true, Ordering::Relaxed),
+        Err(e) => {
+            CLOSED.store(true, Ordering::Relaxed);
+            if let Ok(mut failed) = FAILED.lock() {
+                failed.get_or_insert(e);
+            }
+        }
+    }
+}
+
+/// Fails with the error output couldn't be written with, if there was one
+pub fn check() -> Result<()> {
+    match FAILED.lock().ok().and_then(|mut failed| failed.take()) {
+        Some(e) => Err(Error::Io(e)),
+        None => Ok(()),
+    }
+}
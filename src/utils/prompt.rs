@@ -0,0 +1,43 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{cancel, error::Result};
+
+/// Asks questions on the terminal, or silently takes the defaults when `assume_yes` is set
+pub struct Prompt {
+    pub assume_yes: bool,
+}
+
+impl Prompt {
+    pub fn ask(&self, question: &str, default: &str) -> Result<String> {
+        if self.assume_yes {
+            return Ok(default.to_string());
+        }
+        if default.is_empty() {
+            out!("{}: ", question);
+        } else {
+            out!("{} [{}]: ", question, default);
+        }
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        // reading the line isn't interrupted by Ctrl-C, what follows is
+        cancel::check()?;
+        let answer = line.trim();
+        Ok(if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer.to_string()
+        })
+    }
+
+    pub fn confirm(&self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        let answer = self.ask(&format!("{} ({})", question, hint), "")?;
+        Ok(match answer.to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => default,
+        })
+    }
+}
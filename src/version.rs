@@ -0,0 +1,565 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Bound,
+    str::FromStr,
+};
+
+use crate::error::{Error, Result};
+
+/// A semver version. Build metadata is kept for display but, as semver says, ignored when
+/// comparing, so `1.0.0+a` equals `1.0.0+b`.
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: String,
+    pub build: String,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: String::new(),
+            build: String::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Version> {
+        let invalid = || Error::Validation(format!("invalid version '{}'", text));
+        let text = text.trim();
+        let text = text.strip_prefix('v').unwrap_or(text);
+        let (text, build) = match text.split_once('+') {
+            Some((text, build)) => (text, build),
+            None => (text, ""),
+        };
+        let (text, pre) = match text.split_once('-') {
+            Some((text, pre)) => (text, pre),
+            None => (text, ""),
+        };
+        let mut parts = text.split('.');
+        let mut number = || -> Result<u64> {
+            parts
+                .next()
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let version = Version {
+            major: number()?,
+            minor: number()?,
+            patch: number()?,
+            pre: pre.to_string(),
+            build: build.to_string(),
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Version> {
+        Version::parse(s)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => cmp_prerelease(&self.pre, &other.pre),
+            })
+    }
+}
+
+/// Compares dot separated prerelease identifiers as semver specifies: numeric identifiers
+/// numerically and below alphanumeric ones, a shorter list of equal identifiers first
+fn cmp_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// Hashes what [`Ord`] compares, numeric prerelease identifiers by their value
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.major, self.minor, self.patch).hash(state);
+        if self.pre.is_empty() {
+            return;
+        }
+        for identifier in self.pre.split('.') {
+            match identifier.parse::<u64>() {
+                Ok(number) => number.hash(state),
+                Err(_) => identifier.hash(state),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+/// A single constraint such as `^1.2` or `<2.0.0`, missing components act as wildcards
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparator {
+    pub op: Op,
+    pub major: u64,
+    pub minor: Option<u64>,
+    pub patch: Option<u64>,
+    pub pre: String,
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Result<Comparator> {
+        let invalid = || Error::Validation(format!("invalid version requirement '{}'", text));
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| text.strip_prefix(prefix).map(|rest| (*op, rest)))
+        .unwrap_or((Op::Caret, text));
+        let rest = rest.trim();
+        let rest = rest.strip_prefix('v').unwrap_or(rest);
+        let rest = rest.split_once('+').map_or(rest, |(rest, _)| rest);
+        let (rest, pre) = match rest.split_once('-') {
+            Some((rest, pre)) => (rest, pre.to_string()),
+            None => (rest, String::new()),
+        };
+
+        let mut numbers = Vec::new();
+        let mut wildcard = false;
+        for part in rest.split('.') {
+            if matches!(part, "*" | "x" | "X") {
+                wildcard = true;
+            } else if wildcard {
+                return Err(invalid());
+            } else {
+                numbers.push(part.parse::<u64>().map_err(|_| invalid())?);
+            }
+        }
+        if numbers.len() > 3 || (numbers.len() < 3 && !pre.is_empty()) {
+            return Err(invalid());
+        }
+        let op = if wildcard && op == Op::Caret {
+            Op::Wildcard
+        } else {
+            op
+        };
+        Ok(Comparator {
+            op,
+            major: *numbers.first().ok_or_else(invalid)?,
+            minor: numbers.get(1).copied(),
+            patch: numbers.get(2).copied(),
+            pre,
+        })
+    }
+
+    fn version(&self) -> Version {
+        Version {
+            major: self.major,
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+            build: String::new(),
+        }
+    }
+
+    /// The first version past the range spanned by the given components
+    fn upper(&self) -> Version {
+        match (self.minor, self.patch) {
+            (None, _) => Version::new(self.major + 1, 0, 0),
+            (Some(minor), None) => Version::new(self.major, minor + 1, 0),
+            (Some(minor), Some(patch)) => Version::new(self.major, minor, patch + 1),
+        }
+    }
+
+    /// The lowest and highest versions this matches, ignoring prerelease rules
+    pub fn bounds(&self) -> (Bound<Version>, Bound<Version>) {
+        let lower = self.version();
+        match self.op {
+            Op::Exact | Op::Wildcard if self.patch.is_some() => {
+                (Bound::Included(lower.clone()), Bound::Included(lower))
+            }
+            Op::Exact | Op::Wildcard => (Bound::Included(lower), Bound::Excluded(self.upper())),
+            Op::Greater if self.patch.is_some() => (Bound::Excluded(lower), Bound::Unbounded),
+            Op::Greater => (Bound::Included(self.upper()), Bound::Unbounded),
+            Op::GreaterEq => (Bound::Included(lower), Bound::Unbounded),
+            Op::Less => (Bound::Unbounded, Bound::Excluded(lower)),
+            Op::LessEq if self.patch.is_some() => (Bound::Unbounded, Bound::Included(lower)),
+            Op::LessEq => (Bound::Unbounded, Bound::Excluded(self.upper())),
+            Op::Tilde => {
+                let upper = match self.minor {
+                    Some(minor) => Version::new(self.major, minor + 1, 0),
+                    None => Version::new(self.major + 1, 0, 0),
+                };
+                (Bound::Included(lower), Bound::Excluded(upper))
+            }
+            Op::Caret => {
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    (major, _, _) => Version::new(major + 1, 0, 0),
+                };
+                (Bound::Included(lower), Bound::Excluded(upper))
+            }
+        }
+    }
+
+    /// A comparator on exactly `version`
+    fn on(op: Op, version: &Version) -> Comparator {
+        Comparator {
+            op,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        let lower = self.version();
+        match self.op {
+            Op::Exact | Op::Wildcard => {
+                if self.patch.is_some() {
+                    version.cmp(&lower) == Ordering::Equal
+                } else {
+                    *version >= lower && *version < self.upper()
+                }
+            }
+            Op::Greater => {
+                if self.patch.is_some() {
+                    *version > lower
+                } else {
+                    *version >= self.upper()
+                }
+            }
+            Op::GreaterEq => *version >= lower,
+            Op::Less => *version < lower,
+            Op::LessEq => {
+                if self.patch.is_some() {
+                    *version <= lower
+                } else {
+                    *version < self.upper()
+                }
+            }
+            Op::Tilde => {
+                let upper = match self.minor {
+                    Some(minor) => Version::new(self.major, minor + 1, 0),
+                    None => Version::new(self.major + 1, 0, 0),
+                };
+                *version >= lower && *version < upper
+            }
+            Op::Caret => {
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    (major, _, _) => Version::new(major + 1, 0, 0),
+                };
+                *version >= lower && *version < upper
+            }
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+            Op::Wildcard => "",
+        };
+        write!(f, "{}{}", op, self.major)?;
+        match self.minor {
+            Some(minor) => write!(f, ".{}", minor)?,
+            None if self.op == Op::Wildcard => return write!(f, ".*"),
+            None => return Ok(()),
+        }
+        match self.patch {
+            Some(patch) => write!(f, ".{}", patch)?,
+            None if self.op == Op::Wildcard => return write!(f, ".*"),
+            None => return Ok(()),
+        }
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of comparators that must all match, separated by commas or spaces.
+/// `*` or an empty string matches everything.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct VersionReq {
+    pub comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub const STAR: VersionReq = VersionReq {
+        comparators: Vec::new(),
+    };
+
+    pub fn parse(text: &str) -> Result<VersionReq> {
+        let mut comparators = Vec::new();
+        let mut pending_op: Option<&str> = None;
+        for token in text.split(|c: char| c == ',' || c.is_whitespace()) {
+            if token.is_empty() || token == "*" {
+                continue;
+            }
+            // allow a space between the operator and the version, like `>= 1.0.0`
+            if token.chars().all(|c| "<>=~^".contains(c)) {
+                pending_op = Some(token);
+                continue;
+            }
+            let token = match pending_op.take() {
+                Some(op) => format!("{}{}", op, token),
+                None => token.to_string(),
+            };
+            comparators.push(Comparator::parse(&token)?);
+        }
+        if pending_op.is_some() {
+            return Err(Error::Validation(format!(
+                "invalid version requirement '{}'",
+                text
+            )));
+        }
+        Ok(VersionReq { comparators })
+    }
+
+    /// A caret requirement on exactly this version, the default for new dependencies
+    pub fn caret(version: &Version) -> VersionReq {
+        VersionReq {
+            comparators: vec![Comparator {
+                op: Op::Caret,
+                major: version.major,
+                minor: Some(version.minor),
+                patch: Some(version.patch),
+                pre: version.pre.clone(),
+            }],
+        }
+    }
+
+    /// The requirement matching what all of `reqs` match, as a lower and an upper bound,
+    /// like `>=1.2.0, <1.4.0` for `^1.2.0` and `<1.4.0`. `None` when nothing can match them
+    /// all. Prerelease rules aren't taken into account.
+    pub fn intersect(reqs: &[VersionReq]) -> Option<VersionReq> {
+        let mut lower = Bound::Unbounded;
+        let mut upper = Bound::Unbounded;
+        for comparator in reqs.iter().flat_map(|req| &req.comparators) {
+            let (low, high) = comparator.bounds();
+            if is_tighter(&low, &lower, Ordering::Greater) {
+                lower = low;
+            }
+            if is_tighter(&high, &upper, Ordering::Less) {
+                upper = high;
+            }
+        }
+        let empty = match (&lower, &upper) {
+            (Bound::Included(low), Bound::Included(high)) => low > high,
+            (Bound::Included(low), Bound::Excluded(high))
+            | (Bound::Excluded(low), Bound::Included(high))
+            | (Bound::Excluded(low), Bound::Excluded(high)) => low >= high,
+            _ => false,
+        };
+        if empty {
+            return None;
+        }
+        let comparators = match (lower, upper) {
+            (Bound::Included(low), Bound::Included(high)) if low == high => {
+                vec![Comparator::on(Op::Exact, &low)]
+            }
+            (lower, upper) => {
+                let lower = match lower {
+                    Bound::Included(low) => Some(Comparator::on(Op::GreaterEq, &low)),
+                    Bound::Excluded(low) => Some(Comparator::on(Op::Greater, &low)),
+                    Bound::Unbounded => None,
+                };
+                let upper = match upper {
+                    Bound::Included(high) => Some(Comparator::on(Op::LessEq, &high)),
+                    Bound::Excluded(high) => Some(Comparator::on(Op::Less, &high)),
+                    Bound::Unbounded => None,
+                };
+                lower.into_iter().chain(upper).collect()
+            }
+        };
+        Some(VersionReq { comparators })
+    }
+
+    /// Whether `version` satisfies this requirement. Prereleases only match when a
+    /// comparator opts into prereleases of that exact major.minor.patch, like `>=1.2.0-rc.1`.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.matches_with_prerelease(version, false)
+    }
+
+    /// Like `matches`, but with `include_prerelease` any prerelease within the range matches
+    pub fn matches_with_prerelease(&self, version: &Version, include_prerelease: bool) -> bool {
+        if !self.comparators.iter().all(|c| c.matches(version)) {
+            return false;
+        }
+        !version.is_prerelease()
+            || include_prerelease
+            || self.comparators.iter().any(|c| {
+                !c.pre.is_empty()
+                    && (c.major, c.minor, c.patch)
+                        == (version.major, Some(version.minor), Some(version.patch))
+            })
+    }
+}
+
+/// Whether `bound` limits more than `current`, where a tighter limit compares as `towards`:
+/// greater for lower bounds, less for upper ones
+fn is_tighter(bound: &Bound<Version>, current: &Bound<Version>, towards: Ordering) -> bool {
+    match (bound, current) {
+        (Bound::Unbounded, _) => false,
+        (_, Bound::Unbounded) => true,
+        (Bound::Included(a), Bound::Included(b)) | (Bound::Excluded(a), Bound::Excluded(b)) => {
+            a.cmp(b) == towards
+        }
+        // at the same version excluding it is the tighter limit
+        (Bound::Excluded(a), Bound::Included(b)) => a.cmp(b) != towards.reverse(),
+        (Bound::Included(a), Bound::Excluded(b)) => a.cmp(b) == towards,
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<VersionReq> {
+        VersionReq::parse(s)
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.comparators.is_empty() {
+            return write!(f, "*");
+        }
+        for (i, comparator) in self.comparators.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", comparator)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Version;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn build_metadata_is_ignored() {
+        assert_eq!(version("1.2.3+a"), version("1.2.3+b"));
+        assert_eq!(version("1.2.3+a"), version("1.2.3"));
+        assert_eq!(version("1.2.3+a").to_string(), "1.2.3+a");
+        let set: HashSet<_> = ["1.2.3", "1.2.3+a", "1.2.3+b"]
+            .iter()
+            .map(|v| version(v))
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn equality_agrees_with_ordering() {
+        let versions = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-beta",
+            "1.0.0",
+            "1.0.1",
+        ];
+        for (i, a) in versions.iter().enumerate() {
+            for (j, b) in versions.iter().enumerate() {
+                assert_eq!(version(a) == version(b), i == j, "{} {}", a, b);
+                assert_eq!(version(a).cmp(&version(b)), i.cmp(&j), "{} {}", a, b);
+            }
+        }
+        assert_ne!(version("1.0.0-rc.1"), version("1.0.0"));
+    }
+}
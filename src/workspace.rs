@@ -0,0 +1,103 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::vendor::VENDOR_DIR,
+    data::package::{PackageConfig, PACKAGE_FILE_NAME},
+    error::{Error, Result},
+};
+
+/// A package of a workspace, a folder tree holding several qpm.json
+pub struct Member {
+    pub dir: PathBuf,
+    pub package: PackageConfig,
+}
+
+impl Member {
+    /// The ids of the other members this one depends on
+    pub fn workspace_dependencies<'a>(&'a self, ids: &'a BTreeSet<String>) -> Vec<&'a str> {
+        self.package
+            .dependencies
+            .iter()
+            .map(|d| d.id.as_str())
+            .filter(|id| ids.contains(*id) && *id != self.package.info.id)
+            .collect()
+    }
+}
+
+/// Every package at or below `root`, skipping hidden folders, vendored packages and the
+/// dependencies folders of the packages found
+pub fn members(root: &Path) -> Result<Vec<Member>> {
+    fn walk(dir: &Path, members: &mut Vec<Member>) -> Result<()> {
+        let mut skip = BTreeSet::new();
+        if dir.join(PACKAGE_FILE_NAME).exists() {
+            let package = PackageConfig::read(dir)?;
+            skip.insert(package.dependencies_dir.clone());
+            members.push(Member {
+                dir: dir.to_path_buf(),
+                package,
+            });
+        }
+        let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || name == VENDOR_DIR || skip.contains(&name) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), members)?;
+            }
+        }
+        Ok(())
+    }
+    let mut members = Vec::new();
+    walk(root, &mut members)?;
+    let mut dirs: BTreeMap<&str, &Path> = BTreeMap::new();
+    for member in &members {
+        if let Some(other) = dirs.insert(&member.package.info.id, &member.dir) {
+            return Err(Error::Validation(format!(
+                "{} and {} are both the package {}",
+                other.display(),
+                member.dir.display(),
+                member.package.info.id
+            )));
+        }
+    }
+    Ok(members)
+}
+
+/// `members` ordered so each comes after the members it depends on, by id where the order
+/// is free. Fails on members depending on each other in a cycle.
+pub fn dependency_order(members: Vec<Member>) -> Result<Vec<Member>> {
+    let ids: BTreeSet<String> = members.iter().map(|m| m.package.info.id.clone()).collect();
+    let mut pending: BTreeMap<String, Member> = members
+        .into_iter()
+        .map(|m| (m.package.info.id.clone(), m))
+        .collect();
+    let mut ordered: Vec<Member> = Vec::new();
+    while !pending.is_empty() {
+        let ready = pending
+            .values()
+            .find(|member| {
+                member
+                    .workspace_dependencies(&ids)
+                    .iter()
+                    .all(|id| !pending.contains_key(*id))
+            })
+            .map(|member| member.package.info.id.clone());
+        match ready {
+            Some(id) => ordered.extend(pending.remove(&id)),
+            None => {
+                return Err(Error::Validation(format!(
+                    "these workspace packages depend on each other in a cycle: {}",
+                    pending.keys().cloned().collect::<Vec<_>>().join(", ")
+                )))
+            }
+        }
+    }
+    Ok(ordered)
+}